@@ -0,0 +1,337 @@
+use graphics::math::{Matrix2d, Vec2d};
+use graphics::types::Color;
+use graphics::Transformed;
+use std::f64::consts::{PI, TAU};
+
+// Something animations can move. Animations run on plain values, this says how a type turns into
+// them and back, which also picks the space it gets interpolated in
+pub trait Animatable: Sized {
+    // How many values `to_values` gives
+    const LEN: usize;
+
+    fn to_values(&self) -> Vec<f64>;
+    fn from_values(values: &[f64]) -> Self;
+
+    // Values to move towards from `from`, for types that can get there more than one way
+    fn target_values(&self, _from: &[f64]) -> Vec<f64> {
+        self.to_values()
+    }
+}
+
+impl Animatable for f64 {
+    const LEN: usize = 1;
+
+    fn to_values(&self) -> Vec<f64> {
+        vec![*self]
+    }
+
+    fn from_values(values: &[f64]) -> Self {
+        values[0]
+    }
+}
+
+impl Animatable for Vec2d {
+    const LEN: usize = 2;
+
+    fn to_values(&self) -> Vec<f64> {
+        self.to_vec()
+    }
+
+    fn from_values(values: &[f64]) -> Self {
+        [values[0], values[1]]
+    }
+}
+
+// Parallel timelines give their values one after the other, read back as a tuple
+impl<A: Animatable, B: Animatable> Animatable for (A, B) {
+    const LEN: usize = A::LEN + B::LEN;
+
+    fn to_values(&self) -> Vec<f64> {
+        let mut values = self.0.to_values();
+        values.extend(self.1.to_values());
+        values
+    }
+
+    fn from_values(values: &[f64]) -> Self {
+        (
+            A::from_values(&values[..A::LEN]),
+            B::from_values(&values[A::LEN..]),
+        )
+    }
+
+    fn target_values(&self, from: &[f64]) -> Vec<f64> {
+        let mut values = self.0.target_values(&from[..A::LEN]);
+        values.extend(self.1.target_values(&from[A::LEN..]));
+        values
+    }
+}
+
+// Radians, turning whichever way is shorter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Angle(pub f64);
+
+impl Animatable for Angle {
+    const LEN: usize = 1;
+
+    fn to_values(&self) -> Vec<f64> {
+        vec![self.0]
+    }
+
+    fn from_values(values: &[f64]) -> Self {
+        Angle(values[0].rem_euclid(TAU))
+    }
+
+    fn target_values(&self, from: &[f64]) -> Vec<f64> {
+        vec![from[0] + shortest_turn(from[0], self.0)]
+    }
+}
+
+fn shortest_turn(from: f64, to: f64) -> f64 {
+    let turn = (to - from).rem_euclid(TAU);
+    if turn > PI {
+        turn - TAU
+    } else {
+        turn
+    }
+}
+
+// sRGB colour mixed in linear light, so fades between two colours don't darken in the middle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinearColor(pub Color);
+
+impl Animatable for LinearColor {
+    const LEN: usize = 4;
+
+    fn to_values(&self) -> Vec<f64> {
+        let [r, g, b, a] = self.0;
+        vec![
+            srgb_to_linear(r as f64),
+            srgb_to_linear(g as f64),
+            srgb_to_linear(b as f64),
+            a as f64,
+        ]
+    }
+
+    fn from_values(values: &[f64]) -> Self {
+        LinearColor([
+            linear_to_srgb(values[0]) as f32,
+            linear_to_srgb(values[1]) as f32,
+            linear_to_srgb(values[2]) as f32,
+            values[3].clamp(0.0, 1.0) as f32,
+        ])
+    }
+}
+
+// sRGB colour mixed in Oklab, where the steps in between look evenly spaced
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PerceptualColor(pub Color);
+
+impl Animatable for PerceptualColor {
+    const LEN: usize = 4;
+
+    fn to_values(&self) -> Vec<f64> {
+        let [r, g, b, a] = self.0;
+        let [l, a_axis, b_axis] = linear_to_oklab([
+            srgb_to_linear(r as f64),
+            srgb_to_linear(g as f64),
+            srgb_to_linear(b as f64),
+        ]);
+        vec![l, a_axis, b_axis, a as f64]
+    }
+
+    fn from_values(values: &[f64]) -> Self {
+        let [r, g, b] = oklab_to_linear([values[0], values[1], values[2]]);
+        PerceptualColor([
+            linear_to_srgb(r) as f32,
+            linear_to_srgb(g) as f32,
+            linear_to_srgb(b) as f32,
+            values[3].clamp(0.0, 1.0) as f32,
+        ])
+    }
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Matrices from https://bottosson.github.io/posts/oklab/
+fn linear_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+fn oklab_to_linear([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = l + 0.3963377774 * a + 0.2158037573 * b;
+    let m_ = l - 0.1055613458 * a - 0.0638541728 * b;
+    let s_ = l - 0.0894841775 * a - 1.2914855480 * b;
+    let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076127010 * s,
+    ]
+}
+
+// Moved, rotated and scaled separately so rotations don't squash anything on the way
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform2d {
+    pub translation: Vec2d,
+    pub rotation: Angle,
+    pub scale: Vec2d,
+}
+
+impl Transform2d {
+    pub const IDENTITY: Transform2d = Transform2d {
+        translation: [0.0, 0.0],
+        rotation: Angle(0.0),
+        scale: [1.0, 1.0],
+    };
+
+    // Applied on top of an existing transform, like a context's
+    pub fn apply(&self, transform: Matrix2d) -> Matrix2d {
+        transform
+            .trans(self.translation[0], self.translation[1])
+            .rot_rad(self.rotation.0)
+            .scale(self.scale[0], self.scale[1])
+    }
+}
+
+impl Animatable for Transform2d {
+    const LEN: usize = 5;
+
+    fn to_values(&self) -> Vec<f64> {
+        vec![
+            self.translation[0],
+            self.translation[1],
+            self.rotation.0,
+            self.scale[0],
+            self.scale[1],
+        ]
+    }
+
+    fn from_values(values: &[f64]) -> Self {
+        Self {
+            translation: [values[0], values[1]],
+            rotation: Angle::from_values(&values[2..3]),
+            scale: [values[3], values[4]],
+        }
+    }
+
+    fn target_values(&self, from: &[f64]) -> Vec<f64> {
+        let mut values = self.to_values();
+        values[2] = self.rotation.target_values(&from[2..3])[0];
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-6;
+
+    fn assert_values_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() < EPSILON,
+                "expected {:?}, got {:?}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn tuples_split_their_values_in_order() {
+        let value = (1.0, [2.0, 3.0]);
+        assert_eq!(value.to_values(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(<(f64, Vec2d)>::from_values(&[1.0, 2.0, 3.0]), value);
+        assert_eq!(<(f64, Vec2d)>::LEN, 3);
+    }
+
+    #[test]
+    fn angles_turn_the_short_way() {
+        // From just before a full turn to just after it, crossing zero
+        let target = Angle(0.1).target_values(&[TAU - 0.1]);
+        assert_values_close(&target, &[TAU + 0.1]);
+
+        let target = Angle(PI * 1.5).target_values(&[0.0]);
+        assert_values_close(&target, &[-PI * 0.5]);
+
+        assert!((Angle::from_values(&[TAU + 0.1]).0 - 0.1).abs() < EPSILON);
+    }
+
+    #[test]
+    fn colors_survive_the_round_trip() {
+        let color = [0.2, 0.6, 0.87, 0.5];
+        let linear = LinearColor::from_values(&LinearColor(color).to_values()).0;
+        let perceptual = PerceptualColor::from_values(&PerceptualColor(color).to_values()).0;
+
+        for round_trip in [linear, perceptual] {
+            for (a, e) in round_trip.iter().zip(color) {
+                assert!(
+                    (a - e).abs() < 1e-4,
+                    "expected {:?}, got {:?}",
+                    color,
+                    round_trip
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn linear_color_midpoints_are_brighter_than_srgb_ones() {
+        let black = LinearColor([0.0, 0.0, 0.0, 1.0]).to_values();
+        let white = LinearColor([1.0, 1.0, 1.0, 1.0]).to_values();
+        let middle = black
+            .iter()
+            .zip(&white)
+            .map(|(a, b)| (a + b) / 2.0)
+            .collect::<Vec<_>>();
+
+        let [r, _, _, a] = LinearColor::from_values(&middle).0;
+        assert!(r > 0.7);
+        assert_eq!(a, 1.0);
+    }
+
+    #[test]
+    fn transforms_turn_the_short_way() {
+        let from = Transform2d {
+            translation: [0.0, 0.0],
+            rotation: Angle(0.1),
+            scale: [1.0, 1.0],
+        };
+        let to = Transform2d {
+            translation: [10.0, 20.0],
+            rotation: Angle(TAU - 0.1),
+            scale: [2.0, 0.5],
+        };
+
+        assert_values_close(
+            &to.target_values(&from.to_values()),
+            &[10.0, 20.0, -0.1, 2.0, 0.5],
+        );
+    }
+}
@@ -1,8 +1,41 @@
-use std::collections::HashMap;
+use crate::animatable::Animatable;
+use std::collections::{HashMap, VecDeque};
 use std::f64::consts::PI;
+use std::marker::PhantomData;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
-#[derive(PartialEq, Clone)]
+// Source of time for animations, in milliseconds. Only the difference between two readings
+// matters, so any clock that counts up works, like the wall clock or an audio track's position
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+pub struct WallClock {
+    start: Instant,
+}
+
+impl WallClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for WallClock {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+// Distance to the target and speed below which springs and decays count as settled
+const SETTLE_DISTANCE: f64 = 1e-4;
+const SETTLE_SPEED: f64 = 1e-3;
+// Springs get integrated in steps this long, so they move the same at any frame rate
+const SPRING_STEP: f64 = 1.0 / 240.0;
+
+#[derive(Clone)]
 pub enum AnimationType {
     Timed {
         duration: Duration,
@@ -10,14 +43,77 @@ pub enum AnimationType {
         end_values: Box<[f64]>,
         easing_type: EasingType,
     },
-    Conditional {
+    // Moves at a constant speed until the condition holds for the current values
+    Velocity {
+        start_values: Box<[f64]>,
+        per_second: Box<[f64]>,
         condition: fn(current_values: &[f64]) -> bool,
-        increase_per_second: Box<[f64]>,
+    },
+    // Damped spring pulled towards the target, ends once it comes to rest there. Its target can be
+    // moved with `AnimationsManager::retarget` while it keeps its position and speed
+    Spring {
         start_values: Box<[f64]>,
+        target_values: Box<[f64]>,
+        stiffness: f64,
+        damping: f64,
     },
+    // Covers half of the remaining distance to the target every half life, can be retargeted too
+    Decay {
+        start_values: Box<[f64]>,
+        target_values: Box<[f64]>,
+        half_life: Duration,
+    },
+}
+
+impl AnimationType {
+    pub fn timed<T: Animatable>(
+        from: &T,
+        to: &T,
+        duration: Duration,
+        easing_type: EasingType,
+    ) -> Self {
+        let start_values = from.to_values();
+
+        AnimationType::Timed {
+            duration,
+            end_values: to.target_values(&start_values).into(),
+            start_values: start_values.into(),
+            easing_type,
+        }
+    }
+
+    pub fn spring<T: Animatable>(from: &T, to: &T, stiffness: f64, damping: f64) -> Self {
+        let start_values = from.to_values();
+
+        AnimationType::Spring {
+            target_values: to.target_values(&start_values).into(),
+            start_values: start_values.into(),
+            stiffness,
+            damping,
+        }
+    }
+
+    pub fn decay<T: Animatable>(from: &T, to: &T, half_life: Duration) -> Self {
+        let start_values = from.to_values();
+
+        AnimationType::Decay {
+            target_values: to.target_values(&start_values).into(),
+            start_values: start_values.into(),
+            half_life,
+        }
+    }
+
+    fn start_values(&self) -> &[f64] {
+        match self {
+            AnimationType::Timed { start_values, .. }
+            | AnimationType::Velocity { start_values, .. }
+            | AnimationType::Spring { start_values, .. }
+            | AnimationType::Decay { start_values, .. } => start_values,
+        }
+    }
 }
 
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum EasingType {
     Linear,
     EasingOut,
@@ -54,8 +150,62 @@ pub enum EasingType {
     BounceIn,
     BounceOut,
     BounceInOut,
+    // Same as CSS's cubic-bezier(x1, y1, x2, y2), x1 and x2 being within 0-1
+    CubicBezier(f64, f64, f64, f64),
+    // Goes through each keyframe in turn, starting from 0
+    Keyframes(Rc<[Keyframe]>),
+    // Lookup table of another curve, see `sampled`
+    Sampled(Rc<[f64]>),
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Keyframe {
+    // 0-1, always after the previous keyframe's
+    pub progress: f64,
+    pub value: f64,
+    // Curve from the previous keyframe to this one
+    pub easing_type: EasingType,
 }
 
+// Every easing that goes by its name alone
+const NAMED_EASINGS: [EasingType; 35] = [
+    EasingType::Linear,
+    EasingType::EasingOut,
+    EasingType::EasingIn,
+    EasingType::QuadIn,
+    EasingType::QuadOut,
+    EasingType::QuadInOut,
+    EasingType::CubicIn,
+    EasingType::CubicOut,
+    EasingType::CubicInOut,
+    EasingType::QuartIn,
+    EasingType::QuartOut,
+    EasingType::QuartInOut,
+    EasingType::QuintIn,
+    EasingType::QuintOut,
+    EasingType::QuintInOut,
+    EasingType::SineIn,
+    EasingType::SineOut,
+    EasingType::SineInOut,
+    EasingType::ExpoIn,
+    EasingType::ExpoOut,
+    EasingType::ExpoInOut,
+    EasingType::CircIn,
+    EasingType::CircOut,
+    EasingType::CircInOut,
+    EasingType::ElasticIn,
+    EasingType::ElasticOut,
+    EasingType::ElasticHalfOut,
+    EasingType::ElasticQuarterOut,
+    EasingType::ElasticInOut,
+    EasingType::BackIn,
+    EasingType::BackOut,
+    EasingType::BackInOut,
+    EasingType::BounceIn,
+    EasingType::BounceOut,
+    EasingType::BounceInOut,
+];
+
 impl EasingType {
     pub fn apply(&self, x: f64) -> f64 {
         match self {
@@ -94,6 +244,9 @@ impl EasingType {
             EasingType::BounceIn => Self::bounce_in(x),
             EasingType::BounceOut => Self::bounce_out(x),
             EasingType::BounceInOut => Self::bounce_in_out(x),
+            EasingType::CubicBezier(x1, y1, x2, y2) => Self::cubic_bezier(*x1, *y1, *x2, *y2, x),
+            EasingType::Keyframes(keyframes) => Self::keyframes(keyframes, x),
+            EasingType::Sampled(table) => Self::lookup(table, x),
         }
     }
 
@@ -225,18 +378,20 @@ impl EasingType {
     }
 
     fn circ_in(t: f64) -> f64 {
-        -(1.0 - t * t).sqrt() - 1.0
+        1.0 - (1.0 - t * t).sqrt()
     }
 
     fn circ_out(t: f64) -> f64 {
+        let t = t - 1.0;
         (1.0 - t * t).sqrt()
     }
 
     fn circ_in_out(t: f64) -> f64 {
         let t = t * 2.0;
         if t < 1.0 {
-            -(1.0 - t * t).sqrt() / 2.0
+            -((1.0 - t * t).sqrt() - 1.0) / 2.0
         } else {
+            let t = t - 2.0;
             ((1.0 - t * t).sqrt() + 1.0) / 2.0
         }
     }
@@ -259,10 +414,10 @@ impl EasingType {
         let c2 = c1 * 1.525;
         let t = t * 2.0;
         if t < 1.0 {
-            (c2 + 1.0) * t * t * t - c2 * t * t
+            ((c2 + 1.0) * t * t * t - c2 * t * t) / 2.0
         } else {
             let t = t - 2.0;
-            (c2 + 1.0) * t * t * t + c2 * t * t + 2.0
+            ((c2 + 1.0) * t * t * t + c2 * t * t + 2.0) / 2.0
         }
     }
 
@@ -273,7 +428,7 @@ impl EasingType {
         } else if t == 1.0 {
             1.0
         } else {
-            -(2.0f64.powf(10.0 * (t - 1.0)) * (c4 * (t - 1.0)).sin())
+            -(2.0f64.powf(10.0 * t - 10.0) * ((t * 10.0 - 10.75) * c4).sin())
         }
     }
 
@@ -284,29 +439,27 @@ impl EasingType {
         } else if t == 1.0 {
             1.0
         } else {
-            2.0f64.powf(-10.0 * t) * (c4 * t).sin() + 1.0
+            2.0f64.powf(-10.0 * t) * ((t * 10.0 - 0.75) * c4).sin() + 1.0
         }
     }
 
+    // Same as osu!'s, they settle after half and a quarter of the oscillations
     fn elastic_half_out(t: f64) -> f64 {
-        let c4 = (2.0 * PI) / 3.0;
-        if t == 0.0 {
-            0.0
-        } else if t == 1.0 {
-            1.0
-        } else {
-            2.0f64.powf(-10.0 * t) * (c4 * t).sin() + 0.5
-        }
+        Self::elastic_out_scaled(t, 0.5)
     }
 
     fn elastic_quarter_out(t: f64) -> f64 {
-        let c4 = (2.0 * PI) / 3.0;
+        Self::elastic_out_scaled(t, 0.25)
+    }
+
+    fn elastic_out_scaled(t: f64, oscillations: f64) -> f64 {
+        let c = (2.0 * PI) / 0.3;
         if t == 0.0 {
             0.0
         } else if t == 1.0 {
             1.0
         } else {
-            2.0f64.powf(-10.0 * t) * (c4 * t).sin() + 0.25
+            2.0f64.powf(-10.0 * t) * ((oscillations * t - 0.075) * c).sin() + 1.0
         }
     }
 
@@ -317,11 +470,10 @@ impl EasingType {
         } else if t == 1.0 {
             1.0
         } else {
-            let t = t * 2.0;
-            if t < 1.0 {
-                -(2.0f64.powf(10.0 * (t - 1.0)) * (c5 * (t - 1.0 - 0.1125)).sin()) / 2.0
+            if t < 0.5 {
+                -(2.0f64.powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * c5).sin()) / 2.0
             } else {
-                2.0f64.powf(-10.0 * (t - 1.0)) * (c5 * (t - 1.0 - 0.1125)).sin() / 2.0 + 1.0
+                2.0f64.powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * c5).sin() / 2.0 + 1.0
             }
         }
     }
@@ -354,32 +506,165 @@ impl EasingType {
             Self::bounce_out(t * 2.0 - 1.0) * 0.5 + 0.5
         }
     }
+
+    fn cubic_bezier(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+        // One coordinate of the curve from (0, 0) to (1, 1) and its slope
+        let at = |a: f64, b: f64, t: f64| {
+            let u = 1.0 - t;
+            3.0 * u * u * t * a + 3.0 * u * t * t * b + t * t * t
+        };
+        let slope = |a: f64, b: f64, t: f64| {
+            let u = 1.0 - t;
+            3.0 * u * u * a + 6.0 * u * t * (b - a) + 3.0 * t * t * (1.0 - b)
+        };
+
+        // Newton's method gets there in a few steps, unless the curve flattens out
+        let mut t = x;
+        for _ in 0..8 {
+            let error = at(x1, x2, t) - x;
+            if error.abs() < 1e-7 {
+                return at(y1, y2, t);
+            }
+
+            let d = slope(x1, x2, t);
+            if d.abs() < 1e-6 {
+                break;
+            }
+            t = (t - error / d).clamp(0.0, 1.0);
+        }
+
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..40 {
+            t = (low + high) / 2.0;
+            if at(x1, x2, t) < x {
+                low = t;
+            } else {
+                high = t;
+            }
+        }
+
+        at(y1, y2, t)
+    }
+
+    fn keyframes(keyframes: &[Keyframe], x: f64) -> f64 {
+        let (mut progress, mut value) = (0.0, 0.0);
+
+        for keyframe in keyframes {
+            if x <= keyframe.progress {
+                let t = (x - progress) / (keyframe.progress - progress);
+                return value + (keyframe.value - value) * keyframe.easing_type.apply(t);
+            }
+
+            (progress, value) = (keyframe.progress, keyframe.value);
+        }
+
+        value
+    }
+
+    fn lookup(table: &[f64], x: f64) -> f64 {
+        let position = x.clamp(0.0, 1.0) * (table.len() - 1) as f64;
+        let index = (position as usize).min(table.len() - 2);
+        let t = position - index as f64;
+
+        table[index] + (table[index + 1] - table[index]) * t
+    }
+
+    // Same curve read from a table of evenly spaced points, for curves that are slow to compute
+    pub fn sampled(&self, samples: usize) -> EasingType {
+        let samples = samples.max(2);
+
+        EasingType::Sampled(
+            (0..samples)
+                .map(|i| self.apply(i as f64 / (samples - 1) as f64))
+                .collect(),
+        )
+    }
+
+    // Names like `ExpoOut`, `cubic-bezier(x1, y1, x2, y2)` or
+    // `keyframes(<progress> <value> [easing]; ...)`, going linearly to keyframes without an easing
+    pub fn parse(value: &str) -> Option<EasingType> {
+        let value = value.trim();
+
+        if let Some(args) = value
+            .strip_prefix("cubic-bezier(")
+            .and_then(|v| v.strip_suffix(')'))
+        {
+            let numbers = args
+                .split(',')
+                .map(|v| v.trim().parse::<f64>().ok())
+                .collect::<Option<Vec<_>>>()?;
+
+            return match numbers[..] {
+                [x1, y1, x2, y2] if (0.0..=1.0).contains(&x1) && (0.0..=1.0).contains(&x2) => {
+                    Some(EasingType::CubicBezier(x1, y1, x2, y2))
+                }
+                _ => None,
+            };
+        }
+
+        if let Some(keys) = value
+            .strip_prefix("keyframes(")
+            .and_then(|v| v.strip_suffix(')'))
+        {
+            let mut keyframes = Vec::new();
+            let mut last_progress = 0.0;
+
+            for key in keys.split(';') {
+                let (progress, rest) = key.trim().split_once(char::is_whitespace)?;
+                let rest = rest.trim_start();
+                let (value, easing) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+                let progress = progress.parse::<f64>().ok()?;
+                if progress <= last_progress || progress > 1.0 {
+                    return None;
+                }
+                last_progress = progress;
+
+                keyframes.push(Keyframe {
+                    progress,
+                    value: value.parse().ok()?,
+                    easing_type: match easing.trim() {
+                        "" => EasingType::Linear,
+                        easing => EasingType::parse(easing)?,
+                    },
+                });
+            }
+
+            return Some(EasingType::Keyframes(keyframes.into()));
+        }
+
+        NAMED_EASINGS
+            .iter()
+            .find(|easing_type| format!("{:?}", easing_type) == value)
+            .cloned()
+    }
 }
 
-#[derive(PartialEq)]
-pub struct Animation {
+struct Tween {
     animation_type: AnimationType,
-    start_time: Instant,
+    // Milliseconds of animation time
+    start_time: f64,
+    last_tick: f64,
     current_values: Box<[f64]>,
-    ended: bool,
+    // Per second, for springs
+    velocities: Box<[f64]>,
 }
 
-impl Animation {
-    pub fn new(animation_type: AnimationType, start_time: Instant) -> Self {
-        let start_vals = match &animation_type {
-            AnimationType::Timed { start_values, .. } => start_values,
-            AnimationType::Conditional { start_values, .. } => start_values,
-        };
+impl Tween {
+    fn new(animation_type: AnimationType, start_time: f64) -> Self {
+        let start_values = animation_type.start_values();
 
         Self {
-            animation_type: animation_type.clone(),
+            current_values: start_values.into(),
+            velocities: vec![0.0; start_values.len()].into(),
+            animation_type,
             start_time,
-            current_values: start_vals.clone(),
-            ended: false,
+            last_tick: start_time,
         }
     }
 
-    fn tick(&mut self) {
+    // Returns when the animation ended once it has
+    fn tick(&mut self, now: f64) -> Option<f64> {
         match &self.animation_type {
             AnimationType::Timed {
                 duration,
@@ -387,55 +672,415 @@ impl Animation {
                 end_values,
                 easing_type,
             } => {
-                let t = f64::abs(
-                    self.start_time.elapsed().as_millis() as f64 / duration.as_millis() as f64,
-                );
-                if t > 1.0 {
+                let duration = duration.as_secs_f64() * 1000.0;
+                let elapsed = (now - self.start_time).max(0.0);
+                if elapsed >= duration {
                     self.current_values = end_values.clone();
-                    self.ended = true;
                     println!("Animation ended");
-                } else {
-                    let t = easing_type.apply(t);
-                    let current_values = start_values
-                        .iter()
-                        .zip(end_values.iter())
-                        .map(|(start, end)| start + (end - start) * t)
-                        .collect::<Box<[f64]>>();
-                    self.current_values = current_values;
-
-                    /*self.current_values.iter().for_each(|v| {
-                        println!("{}", v);
-                    });*/
+                    return Some(self.start_time + duration);
                 }
+
+                let t = easing_type.apply(elapsed / duration);
+                let current_values = start_values
+                    .iter()
+                    .zip(end_values.iter())
+                    .map(|(start, end)| start + (end - start) * t)
+                    .collect::<Box<[f64]>>();
+                self.current_values = current_values;
+
+                /*self.current_values.iter().for_each(|v| {
+                    println!("{}", v);
+                });*/
             }
-            AnimationType::Conditional {
-                condition,
-                increase_per_second,
+            AnimationType::Velocity {
                 start_values,
+                per_second,
+                condition,
             } => {
+                let elapsed = (now - self.start_time).max(0.0) / 1000.0;
                 let current_values = start_values
                     .iter()
-                    .zip(increase_per_second.iter())
-                    .map(|(start, increase)| start + increase)
+                    .zip(per_second.iter())
+                    .map(|(start, speed)| start + speed * elapsed)
                     .collect::<Box<[f64]>>();
 
                 self.current_values = current_values;
                 if condition(&self.current_values) {
-                    self.ended = true;
                     println!("Animation ended");
+                    return Some(now);
+                }
+            }
+            AnimationType::Spring {
+                target_values,
+                stiffness,
+                damping,
+                ..
+            } => {
+                // Time left over from the last step waits for the next tick
+                let steps = ((now - self.last_tick).max(0.0) / 1000.0 / SPRING_STEP).floor();
+                self.last_tick += steps * SPRING_STEP * 1000.0;
+                let dt = SPRING_STEP;
+
+                for _ in 0..steps as usize {
+                    for ((value, velocity), target) in self
+                        .current_values
+                        .iter_mut()
+                        .zip(self.velocities.iter_mut())
+                        .zip(target_values.iter())
+                    {
+                        *velocity += (stiffness * (target - *value) - damping * *velocity) * dt;
+                        *value += *velocity * dt;
+                    }
+                }
+
+                if self.settled(target_values) {
+                    self.current_values = target_values.clone();
+                    self.velocities.fill(0.0);
+                    return Some(now);
+                }
+
+                return None;
+            }
+            AnimationType::Decay {
+                target_values,
+                half_life,
+                ..
+            } => {
+                let elapsed = (now - self.last_tick).max(0.0);
+                let covered = 1.0 - 0.5f64.powf(elapsed / (half_life.as_secs_f64() * 1000.0));
+
+                for (value, target) in self.current_values.iter_mut().zip(target_values.iter()) {
+                    *value += (target - *value) * covered;
+                }
+
+                if self.settled(target_values) {
+                    self.current_values = target_values.clone();
+                    return Some(now);
                 }
             }
         }
+
+        self.last_tick = now;
+        None
+    }
+
+    fn settled(&self, target_values: &[f64]) -> bool {
+        self.current_values
+            .iter()
+            .zip(target_values)
+            .all(|(value, target)| (target - value).abs() < SETTLE_DISTANCE)
+            && self
+                .velocities
+                .iter()
+                .all(|velocity| velocity.abs() < SETTLE_SPEED)
     }
 
-    pub fn get_current_values(&self) -> &[f64] {
+    // Moves the target of a spring or decay from where it currently is
+    fn retarget(&mut self, target: &[f64]) -> bool {
+        match &mut self.animation_type {
+            AnimationType::Spring { target_values, .. }
+            | AnimationType::Decay { target_values, .. } => {
+                *target_values = target.into();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn values(&self) -> &[f64] {
         &self.current_values
     }
 }
 
+// Describes how animations follow each other, started all at once with `AnimationsManager::play`
+#[derive(Clone)]
+pub enum Timeline {
+    Tween(AnimationType),
+    // Does nothing for a while, mostly useful in sequences
+    Delay(Duration),
+    // Each step starts when the previous one ends. Its values are the current step's, or the last
+    // ones seen while a step without values like a delay plays
+    Sequence(Vec<Timeline>),
+    // Every timeline starts together and the group ends with the last one. Its values are all of
+    // theirs, one timeline after the other
+    Parallel(Vec<Timeline>),
+    // Plays the timeline `count` times, or forever without one. Ping-pong plays every other pass
+    // backwards
+    Repeat {
+        timeline: Box<Timeline>,
+        count: Option<u32>,
+        ping_pong: bool,
+    },
+}
+
+impl Timeline {
+    // Values before anything started moving
+    fn initial_values(&self) -> Box<[f64]> {
+        match self {
+            Timeline::Tween(animation_type) => animation_type.start_values().into(),
+            Timeline::Delay(_) => Box::new([]),
+            Timeline::Sequence(steps) => steps
+                .iter()
+                .map(Timeline::initial_values)
+                .find(|values| !values.is_empty())
+                .unwrap_or_default(),
+            Timeline::Parallel(timelines) => timelines
+                .iter()
+                .flat_map(|timeline| timeline.initial_values().into_vec())
+                .collect(),
+            Timeline::Repeat { timeline, .. } => timeline.initial_values(),
+        }
+    }
+
+    // Same timeline played from its end to its start, tweens keep their easing
+    fn reversed(&self) -> Timeline {
+        match self {
+            Timeline::Tween(AnimationType::Timed {
+                duration,
+                start_values,
+                end_values,
+                easing_type,
+            }) => Timeline::Tween(AnimationType::Timed {
+                duration: *duration,
+                start_values: end_values.clone(),
+                end_values: start_values.clone(),
+                easing_type: easing_type.clone(),
+            }),
+            Timeline::Tween(AnimationType::Spring {
+                start_values,
+                target_values,
+                stiffness,
+                damping,
+            }) => Timeline::Tween(AnimationType::Spring {
+                start_values: target_values.clone(),
+                target_values: start_values.clone(),
+                stiffness: *stiffness,
+                damping: *damping,
+            }),
+            Timeline::Tween(AnimationType::Decay {
+                start_values,
+                target_values,
+                half_life,
+            }) => Timeline::Tween(AnimationType::Decay {
+                start_values: target_values.clone(),
+                target_values: start_values.clone(),
+                half_life: *half_life,
+            }),
+            // There's no telling where a velocity ends before it gets there
+            Timeline::Tween(AnimationType::Velocity { .. }) | Timeline::Delay(_) => self.clone(),
+            Timeline::Sequence(steps) => {
+                Timeline::Sequence(steps.iter().rev().map(Timeline::reversed).collect())
+            }
+            Timeline::Parallel(timelines) => {
+                Timeline::Parallel(timelines.iter().map(Timeline::reversed).collect())
+            }
+            Timeline::Repeat {
+                timeline,
+                count,
+                ping_pong,
+            } => Timeline::Repeat {
+                timeline: Box::new(timeline.reversed()),
+                count: *count,
+                ping_pong: *ping_pong,
+            },
+        }
+    }
+}
+
+// A timeline while it plays
+enum Playing {
+    Tween(Tween),
+    Delay {
+        end: f64,
+    },
+    Sequence {
+        current: Box<Playing>,
+        next_steps: VecDeque<Timeline>,
+        last_values: Box<[f64]>,
+    },
+    Parallel(Vec<(Playing, Option<f64>)>),
+    Repeat {
+        timeline: Timeline,
+        current: Box<Playing>,
+        remaining: Option<u32>,
+        ping_pong: bool,
+        backwards: bool,
+        pass_start: f64,
+    },
+}
+
+impl Playing {
+    fn start(timeline: &Timeline, at: f64) -> Self {
+        match timeline {
+            Timeline::Tween(animation_type) => {
+                Playing::Tween(Tween::new(animation_type.clone(), at))
+            }
+            Timeline::Delay(duration) => Playing::Delay {
+                end: at + duration.as_secs_f64() * 1000.0,
+            },
+            Timeline::Sequence(steps) => {
+                let mut next_steps = steps.iter().cloned().collect::<VecDeque<_>>();
+                let current = match next_steps.pop_front() {
+                    Some(step) => Playing::start(&step, at),
+                    None => Playing::Delay { end: at },
+                };
+
+                Playing::Sequence {
+                    current: Box::new(current),
+                    next_steps,
+                    last_values: timeline.initial_values(),
+                }
+            }
+            Timeline::Parallel(timelines) => Playing::Parallel(
+                timelines
+                    .iter()
+                    .map(|timeline| (Playing::start(timeline, at), None))
+                    .collect(),
+            ),
+            Timeline::Repeat {
+                timeline,
+                count,
+                ping_pong,
+            } => Playing::Repeat {
+                timeline: (**timeline).clone(),
+                current: Box::new(Playing::start(timeline, at)),
+                remaining: count.map(|count| count.max(1)),
+                ping_pong: *ping_pong,
+                backwards: false,
+                pass_start: at,
+            },
+        }
+    }
+
+    // Returns when the timeline ended once it has. The next one in a sequence starts right then
+    // instead of on the tick that noticed, so steps don't drift apart
+    fn tick(&mut self, now: f64) -> Option<f64> {
+        match self {
+            Playing::Tween(animation) => animation.tick(now),
+            Playing::Delay { end } => (*end <= now).then_some(*end),
+            Playing::Sequence {
+                current,
+                next_steps,
+                last_values,
+            } => loop {
+                let end = current.tick(now);
+
+                let values = current.values();
+                if !values.is_empty() {
+                    *last_values = values;
+                }
+
+                let end = end?;
+                match next_steps.pop_front() {
+                    Some(step) => **current = Playing::start(&step, end),
+                    None => return Some(end),
+                }
+            },
+            Playing::Parallel(timelines) => {
+                for (timeline, end) in timelines.iter_mut() {
+                    if end.is_none() {
+                        *end = timeline.tick(now);
+                    }
+                }
+
+                timelines
+                    .iter()
+                    .map(|(_, end)| *end)
+                    .collect::<Option<Vec<_>>>()
+                    .map(|ends| ends.into_iter().fold(f64::NEG_INFINITY, f64::max))
+            }
+            Playing::Repeat {
+                timeline,
+                current,
+                remaining,
+                ping_pong,
+                backwards,
+                pass_start,
+            } => loop {
+                let end = current.tick(now)?;
+
+                if let Some(remaining) = remaining {
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        return Some(end);
+                    }
+                }
+
+                // A pass that takes no time would repeat forever within this tick
+                if end == *pass_start {
+                    return Some(end);
+                }
+
+                *backwards = *ping_pong && !*backwards;
+                let next = if *backwards {
+                    timeline.reversed()
+                } else {
+                    timeline.clone()
+                };
+                **current = Playing::start(&next, end);
+                *pass_start = end;
+            },
+        }
+    }
+    fn values(&self) -> Box<[f64]> {
+        match self {
+            Playing::Tween(tween) => tween.values().into(),
+            Playing::Delay { .. } => Box::new([]),
+            Playing::Sequence {
+                current,
+                last_values,
+                ..
+            } => {
+                let values = current.values();
+                if values.is_empty() {
+                    last_values.clone()
+                } else {
+                    values
+                }
+            }
+            Playing::Parallel(timelines) => timelines
+                .iter()
+                .flat_map(|(timeline, _)| timeline.values().into_vec())
+                .collect(),
+            Playing::Repeat { current, .. } => current.values(),
+        }
+    }
+}
+
+// Nobody reading which animations ended would otherwise let them pile up
+const MAX_FINISHED: usize = 256;
+
+// Handle to a playing animation, whose values read back as a `T`
+pub struct Animation<T> {
+    id: u32,
+    value_type: PhantomData<T>,
+}
+
+impl<T> Clone for Animation<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Animation<T> {}
+
+struct PlayingAnimation {
+    playing: Playing,
+    values: Box<[f64]>,
+    ended: bool,
+}
+
 pub struct AnimationsManager {
-    animations: HashMap<u32, Animation>,
+    animations: HashMap<u32, PlayingAnimation>,
+    // Animations that ended and haven't been asked about yet, oldest first
+    finished: VecDeque<u32>,
     cur_id: u32,
+    // Animation time in milliseconds, which stands still while paused and runs at `time_scale`
+    // times the clock's speed
+    time: f64,
+    last_clock_time: Option<f64>,
+    paused: bool,
+    time_scale: f64,
 }
 
 impl AnimationsManager {
@@ -444,17 +1089,23 @@ impl AnimationsManager {
 
         Self {
             animations: HashMap::new(),
+            finished: VecDeque::new(),
             cur_id: 0,
+            time: 0.0,
+            last_clock_time: None,
+            paused: false,
+            time_scale: 1.0,
         }
     }
 
-    pub fn add(&mut self, animation_type: AnimationType) -> u32 {
+    pub fn play<T: Animatable>(&mut self, timeline: Timeline) -> Animation<T> {
         println!("Adding animation");
 
         if self.cur_id == u32::MAX {
             println!("Animation id overflow, resetting");
 
             self.cur_id = 0;
+            self.finished.clear();
 
             let iter = self.animations.drain();
             let mut new_map = HashMap::new();
@@ -470,30 +1121,655 @@ impl AnimationsManager {
         println!("Animation id: {}", self.cur_id);
 
         self.cur_id += 1;
-        self.animations
-            .insert(self.cur_id, Animation::new(animation_type, Instant::now()));
+        self.animations.insert(
+            self.cur_id,
+            PlayingAnimation {
+                playing: Playing::start(&timeline, self.time),
+                values: timeline.initial_values(),
+                ended: false,
+            },
+        );
 
-        self.cur_id
+        Animation {
+            id: self.cur_id,
+            value_type: PhantomData,
+        }
     }
 
-    pub fn remove(&mut self, animation_id: u32) {
-        self.animations.remove(&animation_id);
+    pub fn remove<T>(&mut self, animation: Animation<T>) {
+        self.animations.remove(&animation.id);
+        self.finished.retain(|id| *id != animation.id);
     }
 
-    pub fn tick(&mut self) {
+    pub fn tick(&mut self, clock: &impl Clock) {
+        let clock_time = clock.now();
+
+        // Clocks going back, like an audio track being seeked, don't rewind anything
+        if let (Some(last), false) = (self.last_clock_time, self.paused) {
+            self.time += (clock_time - last).max(0.0) * self.time_scale;
+        }
+        self.last_clock_time = Some(clock_time);
+
         self.animations.retain(|_, animation| !animation.ended);
-        self.animations.values_mut().for_each(|a| a.tick());
+        for (id, animation) in self.animations.iter_mut() {
+            if animation.playing.tick(self.time).is_some() {
+                animation.ended = true;
+                if self.finished.len() == MAX_FINISHED {
+                    self.finished.pop_front();
+                }
+                self.finished.push_back(*id);
+            }
+            animation.values = animation.playing.values();
+        }
+    }
+
+    pub fn get<T: Animatable>(&self, animation: Animation<T>) -> Option<T> {
+        self.animations
+            .get(&animation.id)
+            .map(|playing| T::from_values(&playing.values))
+    }
+
+    // Animations still playing, and ones that ended since the last tick
+    pub fn count(&self) -> usize {
+        self.animations.len()
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    // Moves the target of a spring or decay without restarting it. Returns false when there's
+    // nothing to retarget, for other timelines or once the animation is gone
+    pub fn retarget<T: Animatable>(&mut self, animation: Animation<T>, target: &T) -> bool {
+        let Some(playing) = self.animations.get_mut(&animation.id) else {
+            return false;
+        };
+
+        let Playing::Tween(tween) = &mut playing.playing else {
+            return false;
+        };
+
+        if !tween.retarget(&target.target_values(&playing.values)) {
+            return false;
+        }
+
+        // One that already settled starts moving again
+        playing.ended = false;
+        self.finished.retain(|id| *id != animation.id);
+        true
+    }
+
+    // True once for each time the animation ends, so only its owner should ask
+    pub fn take_finished<T>(&mut self, animation: Animation<T>) -> bool {
+        let before = self.finished.len();
+        self.finished.retain(|id| *id != animation.id);
+        self.finished.len() != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::math::Vec2d;
+    use std::cell::Cell;
+
+    const EPSILON: f64 = 1e-9;
+
+    struct ManualClock {
+        time: Cell<f64>,
+    }
+
+    impl ManualClock {
+        fn new() -> Self {
+            Self {
+                time: Cell::new(0.0),
+            }
+        }
+
+        fn set(&self, time: f64) {
+            self.time.set(time);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> f64 {
+            self.time.get()
+        }
+    }
+
+    fn tween(from: f64, to: f64, millis: u64, easing_type: EasingType) -> Timeline {
+        Timeline::Tween(AnimationType::timed(
+            &from,
+            &to,
+            Duration::from_millis(millis),
+            easing_type,
+        ))
+    }
+
+    fn tick_at(anim_mgr: &mut AnimationsManager, clock: &ManualClock, time: f64) {
+        clock.set(time);
+        anim_mgr.tick(clock);
     }
 
-    pub fn get(&self, animation_id: u32) -> Option<&Animation> {
-        let a = self.animations.get(&animation_id);
+    fn assert_close(actual: f64, expected: f64, what: &str) {
+        assert!(
+            (actual - expected).abs() < EPSILON,
+            "{}: expected {}, got {}",
+            what,
+            expected,
+            actual
+        );
+    }
+
+    fn easing_name(easing_type: &EasingType) -> String {
+        format!("{:?}", easing_type)
+    }
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        for easing_type in &NAMED_EASINGS {
+            let name = easing_name(easing_type);
+            assert_close(easing_type.apply(0.0), 0.0, &name);
+            assert_close(easing_type.apply(1.0), 1.0, &name);
+        }
+    }
+
+    #[test]
+    fn easings_do_not_jump_at_the_ends() {
+        for easing_type in &NAMED_EASINGS {
+            let name = easing_name(easing_type);
+            assert!(easing_type.apply(1e-6).abs() < 1e-2, "{} at start", name);
+            assert!(
+                (easing_type.apply(1.0 - 1e-6) - 1.0).abs() < 1e-2,
+                "{} at end",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn in_easings_mirror_out_easings() {
+        let pairs = [
+            (EasingType::EasingIn, EasingType::EasingOut),
+            (EasingType::QuadIn, EasingType::QuadOut),
+            (EasingType::CubicIn, EasingType::CubicOut),
+            (EasingType::QuartIn, EasingType::QuartOut),
+            (EasingType::QuintIn, EasingType::QuintOut),
+            (EasingType::SineIn, EasingType::SineOut),
+            (EasingType::ExpoIn, EasingType::ExpoOut),
+            (EasingType::CircIn, EasingType::CircOut),
+            (EasingType::ElasticIn, EasingType::ElasticOut),
+            (EasingType::BackIn, EasingType::BackOut),
+            (EasingType::BounceIn, EasingType::BounceOut),
+        ];
+
+        for (easing_in, easing_out) in &pairs {
+            for i in 0..=20 {
+                let t = i as f64 / 20.0;
+                assert_close(
+                    easing_in.apply(t),
+                    1.0 - easing_out.apply(1.0 - t),
+                    &format!("{} at {}", easing_name(easing_in), t),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn in_out_easings_are_symmetric() {
+        let in_outs = [
+            EasingType::QuadInOut,
+            EasingType::CubicInOut,
+            EasingType::QuartInOut,
+            EasingType::QuintInOut,
+            EasingType::SineInOut,
+            EasingType::ExpoInOut,
+            EasingType::CircInOut,
+            EasingType::ElasticInOut,
+            EasingType::BackInOut,
+            EasingType::BounceInOut,
+        ];
+
+        for easing_type in &in_outs {
+            let name = easing_name(easing_type);
+            assert_close(easing_type.apply(0.5), 0.5, &name);
 
-        if let Some(b) = a {
-            b.get_current_values().iter().for_each(|v| {
-                println!("{}", v);
-            });
+            for i in 0..=20 {
+                let t = i as f64 / 20.0;
+                assert_close(
+                    easing_type.apply(t) + easing_type.apply(1.0 - t),
+                    1.0,
+                    &format!("{} at {}", name, t),
+                );
+            }
         }
+    }
+
+    #[test]
+    fn cubic_beziers_match_css() {
+        // CSS's `ease`, with values from browsers
+        let ease = EasingType::CubicBezier(0.25, 0.1, 0.25, 1.0);
+        assert!((ease.apply(0.25) - 0.4094).abs() < 1e-3);
+        assert!((ease.apply(0.5) - 0.8024).abs() < 1e-3);
+        assert_close(ease.apply(0.0), 0.0, "ease");
+        assert_close(ease.apply(1.0), 1.0, "ease");
+
+        let linear = EasingType::CubicBezier(0.0, 0.0, 1.0, 1.0);
+        for i in 0..=20 {
+            let t = i as f64 / 20.0;
+            assert!((linear.apply(t) - t).abs() < 1e-6);
+        }
+
+        // Flat at the start, where Newton's method can't find its way
+        let flat = EasingType::CubicBezier(1.0, 0.0, 1.0, 0.0);
+        assert!(flat.apply(0.5) < 0.2);
+    }
+
+    #[test]
+    fn keyframes_ease_each_segment() {
+        let easing_type =
+            EasingType::parse("keyframes(0.5 1.2 QuadOut; 1 1 cubic-bezier(0.4, 0, 0.2, 1))")
+                .unwrap();
+
+        assert_close(easing_type.apply(0.0), 0.0, "start");
+        assert_close(
+            easing_type.apply(0.25),
+            1.2 * EasingType::QuadOut.apply(0.5),
+            "first",
+        );
+        assert_close(easing_type.apply(0.5), 1.2, "keyframe");
+        assert_close(easing_type.apply(1.0), 1.0, "end");
+        assert!(easing_type.apply(0.75) < 1.2 && easing_type.apply(0.75) > 1.0);
+    }
+
+    #[test]
+    fn easings_parse_from_text() {
+        for easing_type in &NAMED_EASINGS {
+            let name = format!("{:?}", easing_type);
+            assert_eq!(EasingType::parse(&name).as_ref(), Some(easing_type));
+        }
+
+        assert_eq!(
+            EasingType::parse(" cubic-bezier(0.1, 0.7, 1.0, 0.1) "),
+            Some(EasingType::CubicBezier(0.1, 0.7, 1.0, 0.1))
+        );
+        assert_eq!(
+            EasingType::parse("keyframes(0.3 0.5; 1 1 SineOut)"),
+            Some(EasingType::Keyframes(
+                vec![
+                    Keyframe {
+                        progress: 0.3,
+                        value: 0.5,
+                        easing_type: EasingType::Linear,
+                    },
+                    Keyframe {
+                        progress: 1.0,
+                        value: 1.0,
+                        easing_type: EasingType::SineOut,
+                    },
+                ]
+                .into()
+            ))
+        );
+
+        for invalid in [
+            "Bouncy",
+            "cubic-bezier(0.1, 0.7, 1.0)",
+            "cubic-bezier(1.5, 0, 0, 1)",
+            "keyframes(0.5 1; 0.4 1)",
+            "keyframes(0.5 1 Bouncy)",
+            "keyframes()",
+        ] {
+            assert_eq!(EasingType::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn sampled_easings_stay_close_to_their_curve() {
+        for easing_type in NAMED_EASINGS
+            .iter()
+            .cloned()
+            .chain([EasingType::CubicBezier(0.68, -0.6, 0.32, 1.6)])
+        {
+            let sampled = easing_type.sampled(256);
+            let name = easing_name(&easing_type);
+            assert_close(sampled.apply(0.0), easing_type.apply(0.0), &name);
+            assert_close(sampled.apply(1.0), easing_type.apply(1.0), &name);
+
+            for i in 0..=100 {
+                let t = i as f64 / 100.0;
+                assert!(
+                    (sampled.apply(t) - easing_type.apply(t)).abs() < 0.02,
+                    "{} at {}",
+                    name,
+                    t
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tweens_follow_their_easing_at_fixed_times() {
+        for easing_type in &NAMED_EASINGS {
+            let name = easing_name(easing_type);
+            let clock = ManualClock::new();
+            let mut anim_mgr = AnimationsManager::new();
+            tick_at(&mut anim_mgr, &clock, 0.0);
+
+            let id: Animation<f64> = anim_mgr.play(tween(10.0, 20.0, 1000, easing_type.clone()));
+
+            for time in [0.0, 100.0, 250.0, 500.0, 750.0, 999.0] {
+                tick_at(&mut anim_mgr, &clock, time);
+                let expected = 10.0 + 10.0 * easing_type.apply(time / 1000.0);
+                assert_close(anim_mgr.get(id).unwrap(), expected, &name);
+                assert!(!anim_mgr.take_finished(id), "{} ended early", name);
+            }
+
+            tick_at(&mut anim_mgr, &clock, 1000.0);
+            assert_close(anim_mgr.get(id).unwrap(), 20.0, &name);
+            assert!(anim_mgr.take_finished(id), "{} didn't end", name);
+            assert!(!anim_mgr.take_finished(id));
+
+            tick_at(&mut anim_mgr, &clock, 1016.0);
+            assert!(anim_mgr.get(id).is_none());
+        }
+    }
+
+    #[test]
+    fn finished_animations_are_reported_to_their_own_handle() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+
+        let long: Animation<f64> = anim_mgr.play(tween(0.0, 1.0, 300, EasingType::Linear));
+        let short: Animation<f64> = anim_mgr.play(tween(0.0, 1.0, 100, EasingType::Linear));
+        let removed: Animation<f64> = anim_mgr.play(tween(0.0, 1.0, 100, EasingType::Linear));
+
+        tick_at(&mut anim_mgr, &clock, 150.0);
+        anim_mgr.remove(removed);
+        assert!(!anim_mgr.take_finished(removed));
+
+        // Asking about one doesn't use up another's
+        assert!(!anim_mgr.take_finished(long));
+        tick_at(&mut anim_mgr, &clock, 400.0);
+        assert!(anim_mgr.take_finished(long));
+        assert!(anim_mgr.take_finished(short));
+        assert!(!anim_mgr.take_finished(short));
+    }
+
+    #[test]
+    fn pausing_stops_animation_time() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 5000.0);
+        let id: Animation<f64> = anim_mgr.play(tween(0.0, 1.0, 1000, EasingType::Linear));
+
+        tick_at(&mut anim_mgr, &clock, 5250.0);
+        anim_mgr.set_paused(true);
+        tick_at(&mut anim_mgr, &clock, 9000.0);
+        assert_close(anim_mgr.get(id).unwrap(), 0.25, "paused");
+
+        anim_mgr.set_paused(false);
+        tick_at(&mut anim_mgr, &clock, 9250.0);
+        assert_close(anim_mgr.get(id).unwrap(), 0.5, "resumed");
+    }
+
+    #[test]
+    fn time_scale_changes_animation_speed() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        anim_mgr.set_time_scale(0.5);
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<f64> = anim_mgr.play(tween(0.0, 1.0, 1000, EasingType::Linear));
+
+        tick_at(&mut anim_mgr, &clock, 1000.0);
+        assert_close(anim_mgr.get(id).unwrap(), 0.5, "half speed");
+
+        anim_mgr.set_time_scale(2.0);
+        tick_at(&mut anim_mgr, &clock, 1100.0);
+        assert_close(anim_mgr.get(id).unwrap(), 0.7, "double speed");
+    }
+
+    #[test]
+    fn clock_going_back_does_not_rewind() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 2000.0);
+        let id: Animation<f64> = anim_mgr.play(tween(0.0, 1.0, 1000, EasingType::Linear));
+
+        tick_at(&mut anim_mgr, &clock, 2500.0);
+        tick_at(&mut anim_mgr, &clock, 100.0);
+        assert_close(anim_mgr.get(id).unwrap(), 0.5, "after seeking back");
+
+        tick_at(&mut anim_mgr, &clock, 200.0);
+        assert_close(anim_mgr.get(id).unwrap(), 0.6, "after seeking back");
+    }
+
+    #[test]
+    fn sequences_start_each_step_when_the_previous_one_ends() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<f64> = anim_mgr.play(Timeline::Sequence(vec![
+            Timeline::Delay(Duration::from_millis(100)),
+            tween(0.0, 1.0, 100, EasingType::Linear),
+            tween(1.0, 3.0, 100, EasingType::Linear),
+        ]));
+
+        tick_at(&mut anim_mgr, &clock, 50.0);
+        assert_close(anim_mgr.get(id).unwrap(), 0.0, "during the delay");
+
+        // One tick can go through several steps
+        tick_at(&mut anim_mgr, &clock, 250.0);
+        assert_close(anim_mgr.get(id).unwrap(), 2.0, "second tween");
+        assert!(!anim_mgr.take_finished(id));
+
+        tick_at(&mut anim_mgr, &clock, 300.0);
+        assert_close(anim_mgr.get(id).unwrap(), 3.0, "end");
+        assert!(anim_mgr.take_finished(id));
+    }
+
+    #[test]
+    fn parallel_groups_end_with_their_longest_timeline() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<(f64, f64)> = anim_mgr.play(Timeline::Parallel(vec![
+            tween(0.0, 1.0, 100, EasingType::Linear),
+            Timeline::Sequence(vec![
+                Timeline::Delay(Duration::from_millis(50)),
+                tween(5.0, 6.0, 100, EasingType::Linear),
+            ]),
+        ]));
+        assert_eq!(anim_mgr.get(id).unwrap(), (0.0, 5.0));
+
+        tick_at(&mut anim_mgr, &clock, 100.0);
+        assert_eq!(anim_mgr.get(id).unwrap(), (1.0, 5.5));
+        assert!(!anim_mgr.take_finished(id));
+
+        tick_at(&mut anim_mgr, &clock, 150.0);
+        assert_eq!(anim_mgr.get(id).unwrap(), (1.0, 6.0));
+        assert!(anim_mgr.take_finished(id));
+    }
+
+    #[test]
+    fn steps_after_a_parallel_group_start_when_the_group_ended() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<(f64, f64)> = anim_mgr.play(Timeline::Sequence(vec![
+            Timeline::Parallel(vec![
+                tween(0.0, 1.0, 100, EasingType::Linear),
+                tween(0.0, 1.0, 150, EasingType::Linear),
+            ]),
+            Timeline::Parallel(vec![
+                tween(1.0, 2.0, 100, EasingType::Linear),
+                tween(1.0, 2.0, 100, EasingType::Linear),
+            ]),
+        ]));
+
+        // Noticed 20ms late, but the second group still started at 150
+        tick_at(&mut anim_mgr, &clock, 170.0);
+        let (first, second) = anim_mgr.get(id).unwrap();
+        assert_close(first, 1.2, "first");
+        assert_close(second, 1.2, "second");
+    }
+
+    #[test]
+    fn ping_pong_repeats_play_every_other_pass_backwards() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<f64> = anim_mgr.play(Timeline::Repeat {
+            timeline: Box::new(tween(0.0, 1.0, 100, EasingType::Linear)),
+            count: Some(3),
+            ping_pong: true,
+        });
+
+        for (time, expected) in [(50.0, 0.5), (125.0, 0.75), (175.0, 0.25), (250.0, 0.5)] {
+            tick_at(&mut anim_mgr, &clock, time);
+            assert_close(anim_mgr.get(id).unwrap(), expected, &format!("at {}", time));
+            assert!(!anim_mgr.take_finished(id));
+        }
+
+        tick_at(&mut anim_mgr, &clock, 400.0);
+        assert_close(anim_mgr.get(id).unwrap(), 1.0, "end");
+        assert!(anim_mgr.take_finished(id));
+    }
+
+    #[test]
+    fn endless_repeats_keep_going() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<f64> = anim_mgr.play(Timeline::Repeat {
+            timeline: Box::new(tween(0.0, 1.0, 100, EasingType::Linear)),
+            count: None,
+            ping_pong: false,
+        });
+
+        tick_at(&mut anim_mgr, &clock, 100_050.0);
+        assert_close(anim_mgr.get(id).unwrap(), 0.5, "after many passes");
+        assert!(!anim_mgr.take_finished(id));
+    }
+
+    #[test]
+    fn velocities_move_at_a_constant_speed_until_their_condition_holds() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<Vec2d> = anim_mgr.play(Timeline::Tween(AnimationType::Velocity {
+            start_values: Box::new([1.0, 0.0]),
+            per_second: Box::new([2.0, -1.0]),
+            condition: |values| values[0] >= 4.0,
+        }));
+
+        tick_at(&mut anim_mgr, &clock, 500.0);
+        assert_eq!(anim_mgr.get(id).unwrap(), [2.0, -0.5]);
+        assert!(!anim_mgr.take_finished(id));
+
+        tick_at(&mut anim_mgr, &clock, 1500.0);
+        assert_eq!(anim_mgr.get(id).unwrap(), [4.0, -1.5]);
+        assert!(anim_mgr.take_finished(id));
+    }
+
+    #[test]
+    fn springs_move_the_same_at_any_frame_rate() {
+        let mut samples = Vec::new();
+
+        for frame_time in [1.0, 7.0, 33.0] {
+            let clock = ManualClock::new();
+            let mut anim_mgr = AnimationsManager::new();
+            tick_at(&mut anim_mgr, &clock, 0.0);
+            let id: Animation<f64> = anim_mgr.play(Timeline::Tween(AnimationType::Spring {
+                start_values: Box::new([0.0]),
+                target_values: Box::new([1.0]),
+                stiffness: 300.0,
+                damping: 20.0,
+            }));
+
+            let mut time = 0.0;
+            let mut overshot = false;
+            let mut frame_samples = Vec::new();
+            while !anim_mgr.take_finished(id) {
+                time += frame_time;
+                tick_at(&mut anim_mgr, &clock, time);
+
+                let value = anim_mgr.get(id).unwrap();
+                overshot |= value > 1.0;
+                // Times every frame rate goes through
+                if time % 231.0 == 0.0 {
+                    frame_samples.push(value);
+                }
+                assert!(time < 5000.0, "spring never settled");
+            }
+
+            // Underdamped, so it goes past the target before coming back
+            assert!(overshot);
+            assert_eq!(anim_mgr.get(id).unwrap(), 1.0);
+            samples.push(frame_samples);
+        }
+
+        for frame_samples in &samples[1..] {
+            for (value, expected) in frame_samples.iter().zip(&samples[0]) {
+                assert_close(*value, *expected, "spring position");
+            }
+        }
+    }
+
+    #[test]
+    fn retargeted_springs_keep_their_position_and_speed() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<f64> = anim_mgr.play(Timeline::Tween(AnimationType::Spring {
+            start_values: Box::new([0.0]),
+            target_values: Box::new([1.0]),
+            stiffness: 300.0,
+            damping: 40.0,
+        }));
+
+        tick_at(&mut anim_mgr, &clock, 50.0);
+        let before = anim_mgr.get(id).unwrap();
+        assert!(anim_mgr.retarget(id, &0.0));
+
+        // Still heading towards the old target for a moment instead of jumping back
+        tick_at(&mut anim_mgr, &clock, 55.0);
+        let after = anim_mgr.get(id).unwrap();
+        assert!(after > before && after - before < 0.05);
+
+        tick_at(&mut anim_mgr, &clock, 5000.0);
+        assert_eq!(anim_mgr.get(id).unwrap(), 0.0);
+        assert!(anim_mgr.take_finished(id));
+
+        // Settled springs start moving again when retargeted
+        assert!(anim_mgr.retarget(id, &2.0));
+        tick_at(&mut anim_mgr, &clock, 5050.0);
+        assert!(anim_mgr.get(id).unwrap() > 0.0);
+    }
+
+    #[test]
+    fn decays_halve_the_distance_every_half_life() {
+        let clock = ManualClock::new();
+        let mut anim_mgr = AnimationsManager::new();
+        tick_at(&mut anim_mgr, &clock, 0.0);
+        let id: Animation<f64> = anim_mgr.play(Timeline::Tween(AnimationType::Decay {
+            start_values: Box::new([0.0]),
+            target_values: Box::new([8.0]),
+            half_life: Duration::from_millis(100),
+        }));
+
+        tick_at(&mut anim_mgr, &clock, 100.0);
+        assert_close(anim_mgr.get(id).unwrap(), 4.0, "one half life");
+        tick_at(&mut anim_mgr, &clock, 150.0);
+        tick_at(&mut anim_mgr, &clock, 200.0);
+        assert_close(anim_mgr.get(id).unwrap(), 6.0, "two half lives");
 
-        a
+        let timed: Animation<f64> = anim_mgr.play(tween(0.0, 1.0, 100, EasingType::Linear));
+        assert!(!anim_mgr.retarget(timed, &2.0));
     }
 }
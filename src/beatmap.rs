@@ -0,0 +1,180 @@
+use rosu_pp::beatmap::{EffectPoint, TimingPoint};
+use rosu_pp::Beatmap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub struct VideoEvent {
+    pub filename: String,
+    pub offset: f64,
+}
+
+// The parts of a .osu file rosu-pp doesn't care about: metadata and events
+pub struct BeatmapInfo {
+    pub directory: PathBuf,
+    pub audio_filename: String,
+    pub preview_time: Option<f64>,
+    pub title: String,
+    pub artist: String,
+    // In the original script, often Japanese, where the plain ones are romanised
+    pub title_unicode: Option<String>,
+    pub artist_unicode: Option<String>,
+    pub version: String,
+    pub background: Option<String>,
+    pub video: Option<VideoEvent>,
+}
+
+impl BeatmapInfo {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+
+        let mut info = Self {
+            directory: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            audio_filename: String::new(),
+            preview_time: None,
+            title: String::new(),
+            artist: String::new(),
+            title_unicode: None,
+            artist_unicode: None,
+            version: String::new(),
+            background: None,
+            video: None,
+        };
+
+        let mut section = "";
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = &line[1..line.len() - 1];
+                continue;
+            }
+
+            match section {
+                "General" | "Metadata" => {
+                    if let Some((key, value)) = line.split_once(':') {
+                        let value = value.trim().to_string();
+
+                        match key.trim() {
+                            "AudioFilename" => info.audio_filename = value,
+                            // -1 means the mapper didn't set one
+                            "PreviewTime" => {
+                                info.preview_time =
+                                    value.parse().ok().filter(|time: &f64| *time >= 0.0)
+                            }
+                            "Title" => info.title = value,
+                            "Artist" => info.artist = value,
+                            "TitleUnicode" if !value.is_empty() => info.title_unicode = Some(value),
+                            "ArtistUnicode" if !value.is_empty() => {
+                                info.artist_unicode = Some(value)
+                            }
+                            "Version" => info.version = value,
+                            _ => {}
+                        }
+                    }
+                }
+                "Events" => info.parse_event(line),
+                _ => {}
+            }
+        }
+
+        Ok(info)
+    }
+
+    pub fn display_title(&self) -> &str {
+        self.title_unicode.as_deref().unwrap_or(&self.title)
+    }
+
+    pub fn display_artist(&self) -> &str {
+        self.artist_unicode.as_deref().unwrap_or(&self.artist)
+    }
+
+    fn parse_event(&mut self, line: &str) {
+        let mut parts = line.splitn(4, ',');
+
+        let (Some(event_type), Some(start), Some(filename)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return;
+        };
+
+        let filename = filename.trim().trim_matches('"').to_string();
+
+        match event_type.trim() {
+            "0" | "Background" => self.background = Some(filename),
+            "1" | "Video" => {
+                self.video = Some(VideoEvent {
+                    filename,
+                    offset: start.trim().parse().unwrap_or(0.0),
+                })
+            }
+            _ => {}
+        }
+    }
+
+    pub fn file(&self, filename: &str) -> PathBuf {
+        self.directory.join(filename)
+    }
+}
+
+pub struct Beat {
+    pub index: i64,
+    pub progress: f64,
+}
+
+// Beat and kiai lookups for whatever track is currently playing
+#[derive(Default)]
+pub struct TrackTiming {
+    timing_points: Vec<TimingPoint>,
+    effect_points: Vec<EffectPoint>,
+}
+
+impl TrackTiming {
+    pub fn from_beatmap(map: &Beatmap) -> Self {
+        Self {
+            timing_points: map.timing_points.to_vec(),
+            effect_points: map.effect_points.to_vec(),
+        }
+    }
+
+    pub fn beat_at(&self, time: f64) -> Beat {
+        let point = self
+            .timing_points
+            .iter()
+            .rev()
+            .find(|point| point.time <= time)
+            .or(self.timing_points.first())
+            .copied()
+            .unwrap_or_default();
+
+        let beats = (time - point.time) / point.beat_len;
+
+        Beat {
+            index: beats.floor() as i64,
+            progress: beats.rem_euclid(1.0),
+        }
+    }
+
+    pub fn kiai_at(&self, time: f64) -> bool {
+        self.effect_points
+            .iter()
+            .rev()
+            .find(|point| point.time <= time)
+            .is_some_and(|point| point.kiai)
+    }
+}
+
+// osu!'s difficulty scaling: `min` at 0, `mid` at 5 and `max` at 10
+pub fn difficulty_range(difficulty: f64, min: f64, mid: f64, max: f64) -> f64 {
+    if difficulty > 5.0 {
+        mid + (max - mid) * (difficulty - 5.0) / 5.0
+    } else if difficulty < 5.0 {
+        mid - (mid - min) * (5.0 - difficulty) / 5.0
+    } else {
+        mid
+    }
+}
@@ -0,0 +1,96 @@
+use super::objects::{CatchObject, PLAYFIELD_WIDTH};
+
+// Pixels per millisecond
+pub const BASE_WALK_SPEED: f64 = 0.5;
+pub const BASE_DASH_SPEED: f64 = 1.0;
+
+const BASE_SIZE: f64 = 106.75;
+// Only this much of the plate actually catches
+pub const ALLOWED_CATCH_RANGE: f64 = 0.8;
+
+pub fn catch_width(cs: f32) -> f64 {
+    let scale = 1.0 - 0.7 * (cs as f64 - 5.0) / 5.0;
+    BASE_SIZE * scale.abs() * ALLOWED_CATCH_RANGE
+}
+
+struct HyperDash {
+    // Replaces the dash speed while moving towards the target
+    speed_multiplier: f64,
+    target_x: f64,
+    direction: f64,
+}
+
+pub struct Catcher {
+    pub x: f64,
+    pub dashing: bool,
+    width: f64,
+    hyper_dash: Option<HyperDash>,
+}
+
+impl Catcher {
+    pub fn new(cs: f32) -> Self {
+        Self {
+            x: PLAYFIELD_WIDTH / 2.0,
+            dashing: false,
+            width: catch_width(cs),
+            hyper_dash: None,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn is_hyper_dashing(&self) -> bool {
+        self.hyper_dash.is_some()
+    }
+
+    // Direction is -1 for left, 1 for right and 0 when standing still
+    pub fn update(&mut self, direction: f64, elapsed: f64) {
+        if direction == 0.0 {
+            return;
+        }
+
+        let speed = match &self.hyper_dash {
+            Some(hyper_dash) if hyper_dash.direction == direction => {
+                BASE_DASH_SPEED * hyper_dash.speed_multiplier
+            }
+            _ if self.dashing => BASE_DASH_SPEED,
+            _ => BASE_WALK_SPEED,
+        };
+
+        self.x = (self.x + direction * speed * elapsed).clamp(0.0, PLAYFIELD_WIDTH);
+
+        // Hyperdashes stop right on their target instead of overshooting it
+        if let Some(hyper_dash) = &self.hyper_dash {
+            if (hyper_dash.direction > 0.0 && hyper_dash.target_x < self.x)
+                || (hyper_dash.direction < 0.0 && hyper_dash.target_x > self.x)
+            {
+                self.x = hyper_dash.target_x;
+                self.hyper_dash = None;
+            }
+        }
+    }
+
+    pub fn can_catch(&self, x: f64) -> bool {
+        (x - self.x).abs() <= self.width / 2.0
+    }
+
+    // Catching an object marked for a hyperdash gives just enough speed to reach the next one
+    pub fn start_hyper_dash(&mut self, caught: &CatchObject, target: &CatchObject) {
+        let time_difference = target.start_time - caught.start_time;
+        let position_difference = target.x - self.x;
+        let velocity = position_difference / f64::max(1.0, time_difference - 1000.0 / 60.0);
+        let speed_multiplier = velocity.abs() / BASE_DASH_SPEED;
+
+        self.hyper_dash = (speed_multiplier > 1.0).then_some(HyperDash {
+            speed_multiplier,
+            target_x: target.x,
+            direction: position_difference.signum(),
+        });
+    }
+
+    pub fn stop_hyper_dash(&mut self) {
+        self.hyper_dash = None;
+    }
+}
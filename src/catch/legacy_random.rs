@@ -0,0 +1,62 @@
+const INT_TO_REAL: f64 = 1.0 / (i32::MAX as f64 + 1.0);
+const INT_MASK: u32 = 0x7FFFFFFF;
+
+// osu!stable's xorshift generator. Conversion has to replay its exact sequence for fruits
+// to land where they do in osu!, so every value stable drew has to be drawn here too
+pub struct LegacyRandom {
+    x: u32,
+    y: u32,
+    z: u32,
+    w: u32,
+}
+
+impl LegacyRandom {
+    pub fn new(seed: i32) -> Self {
+        Self {
+            x: seed as u32,
+            y: 842502087,
+            z: 3579807591,
+            w: 273326509,
+        }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let t = self.x ^ (self.x << 11);
+        self.x = self.y;
+        self.y = self.z;
+        self.z = self.w;
+        self.w = self.w ^ (self.w >> 19) ^ t ^ (t >> 8);
+        self.w
+    }
+
+    pub fn next_int(&mut self) -> i32 {
+        (INT_MASK & self.next_u32()) as i32
+    }
+
+    pub fn next_double(&mut self) -> f64 {
+        INT_TO_REAL * self.next_int() as f64
+    }
+
+    pub fn next_in_range(&mut self, lower: i32, upper: i32) -> i32 {
+        (lower as f64 + self.next_double() * (upper - lower) as f64) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_the_catch_seed() {
+        let mut rng = LegacyRandom::new(1337);
+        let ints = (0..5).map(|_| rng.next_int()).collect::<Vec<_>>();
+        assert_eq!(
+            ints,
+            [274941776, 514112300, 938046240, 1928063929, 2025352051]
+        );
+
+        let mut rng = LegacyRandom::new(1337);
+        assert_eq!(rng.next_double(), 274941776.0 / 2147483648.0);
+        assert_eq!(rng.next_in_range(-20, 20), -10);
+    }
+}
@@ -0,0 +1,7 @@
+mod catcher;
+mod legacy_random;
+mod objects;
+mod playfield;
+mod score;
+
+pub use playfield::CatchPlayfield;
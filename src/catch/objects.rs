@@ -0,0 +1,383 @@
+use super::catcher::{catch_width, ALLOWED_CATCH_RANGE, BASE_DASH_SPEED};
+use super::legacy_random::LegacyRandom;
+use crate::slider_path::{self, SliderEventKind, SliderPath};
+use rosu_pp::parse::{HitObjectKind, PathControlPoint};
+use rosu_pp::{Beatmap, GameMode};
+
+pub const PLAYFIELD_WIDTH: f64 = 512.0;
+
+// Seed osu!stable uses for every random offset in catch conversion
+const RNG_SEED: i32 = 1337;
+// Tiny droplets are spread at most this far apart in time
+const TINY_DROPLET_SPACING: f64 = 100.0;
+const BANANA_SPACING: f64 = 100.0;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CatchObjectKind {
+    Fruit,
+    Droplet,
+    TinyDroplet,
+    Banana,
+}
+
+#[derive(Clone, Debug)]
+pub struct CatchObject {
+    pub start_time: f64,
+    pub x: f64,
+    pub kind: CatchObjectKind,
+    // Index of the next object when it can only be reached by hyperdashing
+    pub hyper_dash_target: Option<usize>,
+}
+
+impl CatchObject {
+    fn new(start_time: f64, x: f64, kind: CatchObjectKind) -> Self {
+        Self {
+            start_time,
+            x,
+            kind,
+            hyper_dash_target: None,
+        }
+    }
+
+    // Fruits and droplets, the objects hyperdashes are computed between
+    pub fn is_palpable(&self) -> bool {
+        matches!(self.kind, CatchObjectKind::Fruit | CatchObjectKind::Droplet)
+    }
+}
+
+// Circles become fruits, sliders juice streams and spinners banana showers. Random offsets are
+// drawn in object order from a single generator, like osu!stable does
+pub fn convert(map: &Beatmap) -> Vec<CatchObject> {
+    let map = map.convert_mode(GameMode::Catch);
+    let mut rng = LegacyRandom::new(RNG_SEED);
+    let mut objects = Vec::new();
+
+    for obj in &map.hit_objects {
+        match &obj.kind {
+            HitObjectKind::Circle => objects.push(CatchObject::new(
+                obj.start_time,
+                obj.pos.x as f64,
+                CatchObjectKind::Fruit,
+            )),
+            HitObjectKind::Slider {
+                pixel_len,
+                repeats,
+                control_points,
+                ..
+            } => {
+                let mut stream = juice_stream(
+                    &map,
+                    obj.start_time,
+                    obj.pos.x as f64,
+                    *pixel_len,
+                    *repeats + 1,
+                    control_points,
+                );
+
+                for object in &mut stream {
+                    match object.kind {
+                        CatchObjectKind::TinyDroplet => {
+                            let offset = rng.next_in_range(-20, 20) as f64;
+                            object.x += offset.clamp(-object.x, PLAYFIELD_WIDTH - object.x);
+                        }
+                        // Stable picked a random rotation for droplets
+                        CatchObjectKind::Droplet => {
+                            rng.next_int();
+                        }
+                        _ => {}
+                    }
+                }
+
+                objects.extend(stream);
+            }
+            HitObjectKind::Spinner { end_time } | HitObjectKind::Hold { end_time } => {
+                for time in banana_times(obj.start_time, *end_time) {
+                    let x = rng.next_double() * PLAYFIELD_WIDTH;
+
+                    // Stable picked a random type, rotation and colour for each banana
+                    rng.next_int();
+                    rng.next_int();
+                    rng.next_int();
+
+                    objects.push(CatchObject::new(time, x, CatchObjectKind::Banana));
+                }
+            }
+        }
+    }
+
+    for object in &mut objects {
+        object.x = object.x.clamp(0.0, PLAYFIELD_WIDTH);
+    }
+
+    objects.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    init_hyper_dashes(&mut objects, map.cs);
+
+    objects
+}
+
+fn juice_stream(
+    map: &Beatmap,
+    start_time: f64,
+    x: f64,
+    pixel_len: Option<f64>,
+    span_count: usize,
+    control_points: &[PathControlPoint],
+) -> Vec<CatchObject> {
+    let path = SliderPath::new(control_points, pixel_len);
+
+    let (velocity, tick_distance) = slider_path::slider_velocity(map, start_time);
+
+    let span_duration = path.distance() / velocity;
+    let events = slider_path::slider_events(
+        start_time,
+        span_duration,
+        velocity,
+        tick_distance,
+        path.distance(),
+        span_count,
+    );
+
+    let mut objects = Vec::new();
+
+    for (i, event) in events.iter().enumerate() {
+        // Gaps between events are filled with tiny droplets. Times get truncated first, which
+        // is how stable did it
+        if let Some(last) = i.checked_sub(1).map(|i| events[i]) {
+            let since_last = (event.time as i32 - last.time as i32) as f64;
+
+            if since_last > 80.0 {
+                let mut spacing = since_last;
+                while spacing > TINY_DROPLET_SPACING {
+                    spacing /= 2.0;
+                }
+
+                let mut t = spacing;
+                while t < since_last {
+                    let progress =
+                        last.progress + t / since_last * (event.progress - last.progress);
+
+                    objects.push(CatchObject::new(
+                        last.time + t,
+                        x + path.position_at(progress)[0],
+                        CatchObjectKind::TinyDroplet,
+                    ));
+
+                    t += spacing;
+                }
+            }
+        }
+
+        let kind = match event.kind {
+            SliderEventKind::Tick => CatchObjectKind::Droplet,
+            SliderEventKind::Head | SliderEventKind::Repeat | SliderEventKind::Tail => {
+                CatchObjectKind::Fruit
+            }
+            SliderEventKind::LegacyLastTick => continue,
+        };
+
+        objects.push(CatchObject::new(
+            event.time,
+            x + path.position_at(event.progress)[0],
+            kind,
+        ));
+    }
+
+    objects.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+    objects
+}
+
+fn banana_times(start_time: f64, end_time: f64) -> Vec<f64> {
+    let mut spacing = end_time - start_time;
+    while spacing > BANANA_SPACING {
+        spacing /= 2.0;
+    }
+
+    if spacing <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut times = Vec::new();
+    let mut time = start_time;
+
+    while time <= end_time {
+        times.push(time);
+        time += spacing;
+    }
+
+    times
+}
+
+// Marks every fruit or droplet from which the next one can't be reached by dashing. Whatever
+// distance is left over on a reachable jump carries over to the next one in the same direction
+fn init_hyper_dashes(objects: &mut [CatchObject], cs: f32) {
+    // osu!stable computed hyperdashes using the full width of the plate
+    let half_catcher_width = catch_width(cs) / 2.0 / ALLOWED_CATCH_RANGE;
+
+    let palpable = (0..objects.len())
+        .filter(|&i| objects[i].is_palpable())
+        .collect::<Vec<_>>();
+
+    let mut last_direction = 0;
+    let mut last_excess = half_catcher_width;
+
+    for pair in palpable.windows(2) {
+        let (current, next) = (&objects[pair[0]], &objects[pair[1]]);
+
+        let direction = if next.x > current.x { 1 } else { -1 };
+        // A quarter of a frame of leniency
+        let time_to_next = next.start_time - current.start_time - 1000.0 / 60.0 / 4.0;
+        let distance_to_next = (next.x - current.x).abs()
+            - if last_direction == direction {
+                last_excess
+            } else {
+                half_catcher_width
+            };
+        let distance_to_hyper = time_to_next * BASE_DASH_SPEED - distance_to_next;
+
+        if distance_to_hyper < 0.0 {
+            objects[pair[0]].hyper_dash_target = Some(pair[1]);
+            last_excess = half_catcher_width;
+        } else {
+            last_excess = distance_to_hyper.clamp(0.0, half_catcher_width);
+        }
+
+        last_direction = direction;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(hit_objects: &str) -> Beatmap {
+        let osu = format!(
+            "osu file format v14\n\n[General]\nMode: 0\n\n[Difficulty]\nHPDrainRate:5\n\
+             CircleSize:4\nOverallDifficulty:5\nApproachRate:5\nSliderMultiplier:1.4\n\
+             SliderTickRate:1\n\n[TimingPoints]\n0,500,4,2,0,100,1,0\n\n[HitObjects]\n{}",
+            hit_objects
+        );
+        Beatmap::from_bytes(osu.as_bytes()).unwrap()
+    }
+
+    fn summary(objects: &[CatchObject]) -> Vec<(CatchObjectKind, f64)> {
+        objects.iter().map(|o| (o.kind, o.start_time)).collect()
+    }
+
+    #[test]
+    fn spinners_shower_bananas_at_random_positions() {
+        let objects = convert(&map("256,192,1000,12,0,1400\n"));
+
+        // 400ms halved until it's at most 100ms apart
+        assert_eq!(
+            summary(&objects),
+            [1000.0, 1100.0, 1200.0, 1300.0, 1400.0].map(|t| (CatchObjectKind::Banana, t))
+        );
+
+        // Every banana draws its position, then three more values stable uses for its looks
+        let xs = objects.iter().map(|o| o.x).collect::<Vec<_>>();
+        let expected = [
+            65.55122756958008,
+            482.88155817985535,
+            164.770085811615,
+            315.21669006347656,
+            145.71700882911682,
+        ];
+        for (x, expected) in xs.iter().zip(expected) {
+            assert!((x - expected).abs() < 1e-9, "{} != {}", x, expected);
+        }
+    }
+
+    #[test]
+    fn sliders_become_juice_streams() {
+        // 200px at 0.28px/ms, with a tick a beat in
+        let objects = convert(&map("100,192,1000,2,0,L|300:192,1,200\n"));
+        let tail_time = 1000.0 + 200.0 / 0.28;
+
+        use CatchObjectKind::*;
+        let mut expected = vec![(Fruit, 1000.0)];
+        // Tiny droplets 62.5ms apart up to the tick
+        expected.extend((1..8).map(|i| (TinyDroplet, 1000.0 + 62.5 * i as f64)));
+        expected.push((Droplet, 1500.0));
+        // Stable's last tick 36ms before the tail still splits the gap after the tick
+        expected.push((TinyDroplet, 1589.0));
+        expected.push((Fruit, tail_time));
+
+        let actual = summary(&objects);
+        assert_eq!(actual.len(), expected.len());
+        for ((kind, time), (expected_kind, expected_time)) in actual.iter().zip(&expected) {
+            assert_eq!(kind, expected_kind);
+            assert!(
+                (time - expected_time).abs() < 1e-6,
+                "{} != {}",
+                time,
+                expected_time
+            );
+        }
+
+        assert_eq!(objects[0].x, 100.0);
+        assert_eq!(objects[8].x, 240.0);
+        assert!((objects[10].x - 300.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn tiny_droplets_get_random_offsets() {
+        let map = map("100,192,1000,2,0,L|300:192,1,200\n");
+        let converted = map.convert_mode(GameMode::Catch);
+        let HitObjectKind::Slider {
+            pixel_len,
+            repeats,
+            control_points,
+            ..
+        } = &converted.hit_objects[0].kind
+        else {
+            panic!("not a slider");
+        };
+        let on_path = juice_stream(
+            &converted,
+            1000.0,
+            100.0,
+            *pixel_len,
+            repeats + 1,
+            control_points,
+        );
+        let objects = convert(&map);
+
+        let offsets = objects
+            .iter()
+            .zip(&on_path)
+            .filter(|(object, _)| object.kind == CatchObjectKind::TinyDroplet)
+            .map(|(object, on_path)| (object.x - on_path.x).round() as i32)
+            .collect::<Vec<_>>();
+
+        // Between 20 left and right, with the droplet at 1500ms drawing a value in between
+        assert_eq!(offsets, [-14, -10, -2, 15, 17, 1, 0, -7]);
+
+        let moved = objects
+            .iter()
+            .zip(&on_path)
+            .filter(|(object, _)| object.kind != CatchObjectKind::TinyDroplet)
+            .all(|(object, on_path)| object.x == on_path.x);
+        assert!(moved);
+    }
+
+    #[test]
+    fn hyperdashes_mark_jumps_a_dash_cant_make() {
+        let mut objects = vec![
+            CatchObject::new(0.0, 0.0, CatchObjectKind::Fruit),
+            // Bananas aren't part of the chain
+            CatchObject::new(50.0, 256.0, CatchObjectKind::Banana),
+            // Across the whole playfield in 100ms
+            CatchObject::new(100.0, 512.0, CatchObjectKind::Fruit),
+            // Easily walked
+            CatchObject::new(1000.0, 450.0, CatchObjectKind::Droplet),
+            CatchObject::new(1100.0, 400.0, CatchObjectKind::Fruit),
+        ];
+        init_hyper_dashes(&mut objects, 4.0);
+
+        let targets = objects
+            .iter()
+            .map(|o| o.hyper_dash_target)
+            .collect::<Vec<_>>();
+        assert_eq!(targets, [Some(2), None, None, None, None]);
+    }
+}
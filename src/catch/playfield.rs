@@ -0,0 +1,255 @@
+use super::catcher::Catcher;
+use super::objects::{self, CatchObject, CatchObjectKind, PLAYFIELD_WIDTH};
+use super::score::CatchScore;
+use crate::beatmap::difficulty_range;
+use crate::ruleset::{self, Ruleset, RulesetContext, ScoreSummary};
+use crate::text::Fonts;
+use graphics::math::Scalar;
+use graphics::{ellipse, rectangle, Context, Ellipse, Text, Transformed};
+use piston_window::{Button, G2d, Key};
+use rosu_pp::{Beatmap, GameMode};
+
+const PLAYFIELD_HEIGHT: f64 = 384.0;
+// Height of the plate the catcher catches objects on, in playfield pixels
+const CATCHER_Y: f64 = 340.0;
+const FRUIT_RADIUS: f64 = 32.0;
+
+const FRUIT_COLOR: [f32; 4] = [0.4, 0.85, 0.35, 1.0];
+const DROPLET_COLOR: [f32; 4] = [0.55, 0.8, 1.0, 1.0];
+const BANANA_COLOR: [f32; 4] = [1.0, 0.9, 0.3, 1.0];
+const HYPER_DASH_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CatchAction {
+    Left,
+    Right,
+    Dash,
+}
+
+pub struct CatchBindings {
+    pub left: Key,
+    pub right: Key,
+    pub dash: Key,
+}
+
+impl Default for CatchBindings {
+    fn default() -> Self {
+        Self {
+            left: Key::Left,
+            right: Key::Right,
+            dash: Key::LShift,
+        }
+    }
+}
+
+pub struct CatchPlayfield {
+    objects: Vec<CatchObject>,
+    // Everything before this has been judged
+    next_object: usize,
+    catcher: Catcher,
+    bindings: CatchBindings,
+    score: CatchScore,
+    // How long objects take to fall down to the catcher
+    preempt: f64,
+    object_radius: f64,
+    left_held: bool,
+    right_held: bool,
+    last_update: Option<f64>,
+}
+
+impl Ruleset for CatchPlayfield {
+    type Action = CatchAction;
+
+    const MODE: GameMode = GameMode::Catch;
+
+    fn new(map: &Beatmap, _ctx: &RulesetContext) -> Self {
+        let objects = objects::convert(map);
+        println!("Loaded {} catch objects", objects.len());
+
+        let scale = 1.0 - 0.7 * (map.cs as f64 - 5.0) / 5.0;
+
+        Self {
+            objects,
+            next_object: 0,
+            catcher: Catcher::new(map.cs),
+            bindings: CatchBindings::default(),
+            score: CatchScore::default(),
+            preempt: difficulty_range(map.ar as f64, 1800.0, 1200.0, 450.0),
+            object_radius: FRUIT_RADIUS * scale,
+            left_held: false,
+            right_held: false,
+            last_update: None,
+        }
+    }
+
+    fn action_for(&self, button: Button) -> Option<Self::Action> {
+        match button {
+            Button::Keyboard(key) if key == self.bindings.left => Some(CatchAction::Left),
+            Button::Keyboard(key) if key == self.bindings.right => Some(CatchAction::Right),
+            Button::Keyboard(key) if key == self.bindings.dash => Some(CatchAction::Dash),
+            _ => None,
+        }
+    }
+
+    fn press(&mut self, action: Self::Action, _time: f64) {
+        self.set_held(action, true);
+    }
+
+    fn release(&mut self, action: Self::Action, _time: f64) {
+        self.set_held(action, false);
+    }
+
+    fn update(&mut self, time: f64) {
+        let elapsed = self.last_update.map_or(0.0, |last| (time - last).max(0.0));
+        self.last_update = Some(time);
+
+        let direction = match (self.left_held, self.right_held) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        };
+        self.catcher.update(direction, elapsed);
+
+        // Objects are caught or missed the moment they reach the plate
+        while let Some(object) = self.objects.get(self.next_object) {
+            if object.start_time > time {
+                break;
+            }
+
+            let caught = self.catcher.can_catch(object.x);
+            self.score.add_result(object.kind, caught);
+
+            if object.is_palpable() {
+                match object.hyper_dash_target {
+                    Some(target) if caught => {
+                        self.catcher.start_hyper_dash(object, &self.objects[target])
+                    }
+                    _ => self.catcher.stop_hyper_dash(),
+                }
+            }
+
+            self.next_object += 1;
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next_object >= self.objects.len()
+    }
+
+    fn score(&self) -> ScoreSummary {
+        ScoreSummary {
+            score: self.score.score,
+            max_combo: self.score.max_combo,
+            accuracy: self.score.accuracy(),
+        }
+    }
+
+    fn judgement_counts(&self) -> String {
+        format!(
+            "Fruits: {} | Droplets: {} | Tiny droplets: {} | Misses: {} | Bananas: {}",
+            self.score.fruits,
+            self.score.droplets,
+            self.score.tiny_droplets,
+            self.score.misses,
+            self.score.bananas,
+        )
+    }
+
+    fn render(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, time: f64) {
+        // The playfield keeps osu!'s 512x384 proportions and is fit into the window
+        let (scale, [x, y]) =
+            ruleset::fit_playfield(c.get_view_size(), [PLAYFIELD_WIDTH, PLAYFIELD_HEIGHT], 0.9);
+        let playfield = c.trans(x, y).scale(scale, scale);
+
+        rectangle(
+            [0.0, 0.0, 0.0, 0.5],
+            [0.0, 0.0, PLAYFIELD_WIDTH, PLAYFIELD_HEIGHT],
+            playfield.transform,
+            g,
+        );
+
+        let visible_end = self.objects[self.next_object..]
+            .iter()
+            .position(|object| object.start_time - time > self.preempt)
+            .map_or(self.objects.len(), |i| self.next_object + i);
+
+        // Drawn back to front so earlier objects end up on top
+        for object in self.objects[self.next_object..visible_end].iter().rev() {
+            let y = CATCHER_Y - (object.start_time - time) / self.preempt * CATCHER_Y;
+            self.render_object(playfield, g, object, y);
+        }
+
+        self.render_catcher(playfield, g);
+        self.render_combo(c, g, glyphs);
+    }
+}
+
+impl CatchPlayfield {
+    fn set_held(&mut self, action: CatchAction, held: bool) {
+        match action {
+            CatchAction::Left => self.left_held = held,
+            CatchAction::Right => self.right_held = held,
+            CatchAction::Dash => self.catcher.dashing = held,
+        }
+    }
+
+    fn render_object(&self, c: Context, g: &mut G2d, object: &CatchObject, y: Scalar) {
+        let (radius, color) = match object.kind {
+            CatchObjectKind::Fruit => (self.object_radius, FRUIT_COLOR),
+            CatchObjectKind::Droplet => (self.object_radius * 0.5, DROPLET_COLOR),
+            CatchObjectKind::TinyDroplet => (self.object_radius * 0.25, DROPLET_COLOR),
+            CatchObjectKind::Banana => (self.object_radius * 0.8, BANANA_COLOR),
+        };
+
+        let rect = [object.x - radius, y - radius, radius * 2.0, radius * 2.0];
+        ellipse(color, rect, c.transform, g);
+
+        if object.hyper_dash_target.is_some() {
+            Ellipse::new_border(HYPER_DASH_COLOR, 3.0).draw(rect, &c.draw_state, c.transform, g);
+        }
+    }
+
+    fn render_catcher(&self, c: Context, g: &mut G2d) {
+        let width = self.catcher.width();
+
+        let color = if self.catcher.is_hyper_dashing() {
+            HYPER_DASH_COLOR
+        } else if self.catcher.dashing {
+            [1.0, 0.8, 0.8, 1.0]
+        } else {
+            [1.0, 1.0, 1.0, 1.0]
+        };
+
+        rectangle(
+            color,
+            [self.catcher.x - width / 2.0, CATCHER_Y, width, 8.0],
+            c.transform,
+            g,
+        );
+        rectangle(
+            [color[0], color[1], color[2], 0.3],
+            [
+                self.catcher.x - width / 4.0,
+                CATCHER_Y + 8.0,
+                width / 2.0,
+                30.0,
+            ],
+            c.transform,
+            g,
+        );
+    }
+
+    fn render_combo(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts) {
+        let [_, win_height] = c.get_view_size();
+
+        Text::new_color([1.0, 1.0, 1.0, 1.0], 24)
+            .draw(
+                &format!("{}x", self.score.combo),
+                glyphs,
+                &c.draw_state,
+                c.transform.trans(20.0, win_height - 20.0),
+                g,
+            )
+            .unwrap();
+    }
+}
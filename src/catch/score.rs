@@ -0,0 +1,64 @@
+use super::objects::CatchObjectKind;
+
+// Classic scoring: fruits are worth more the higher the combo, droplets give a flat amount
+// and bananas are pure bonus
+#[derive(Default)]
+pub struct CatchScore {
+    pub score: u64,
+    pub combo: u32,
+    pub max_combo: u32,
+    pub fruits: u32,
+    pub droplets: u32,
+    pub tiny_droplets: u32,
+    pub bananas: u32,
+    pub misses: u32,
+    pub tiny_droplet_misses: u32,
+}
+
+impl CatchScore {
+    pub fn add_result(&mut self, kind: CatchObjectKind, caught: bool) {
+        match kind {
+            CatchObjectKind::Fruit | CatchObjectKind::Droplet if !caught => {
+                self.misses += 1;
+                self.combo = 0;
+            }
+            CatchObjectKind::Fruit => {
+                self.fruits += 1;
+                self.score += 300 + 300 * self.combo as u64 / 25;
+                self.add_combo();
+            }
+            CatchObjectKind::Droplet => {
+                self.droplets += 1;
+                self.score += 100;
+                self.add_combo();
+            }
+            // Tiny droplets count towards accuracy but never touch the combo
+            CatchObjectKind::TinyDroplet if caught => {
+                self.tiny_droplets += 1;
+                self.score += 10;
+            }
+            CatchObjectKind::TinyDroplet => self.tiny_droplet_misses += 1,
+            CatchObjectKind::Banana if caught => {
+                self.bananas += 1;
+                self.score += 1000;
+            }
+            CatchObjectKind::Banana => {}
+        }
+    }
+
+    fn add_combo(&mut self) {
+        self.combo += 1;
+        self.max_combo = self.max_combo.max(self.combo);
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        let caught = self.fruits + self.droplets + self.tiny_droplets;
+        let total = caught + self.misses + self.tiny_droplet_misses;
+
+        if total == 0 {
+            1.0
+        } else {
+            caught as f64 / total as f64
+        }
+    }
+}
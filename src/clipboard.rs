@@ -0,0 +1,298 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+// How long a paste waits for the program holding the clipboard to hand it over
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const PASTE_TIMEOUT: Duration = Duration::from_millis(500);
+
+enum Request {
+    Copy(String),
+    Paste(Sender<Option<String>>),
+}
+
+// The system clipboard, started on first use
+static SYSTEM: OnceLock<Option<Sender<Request>>> = OnceLock::new();
+// Without a system clipboard copy and paste only work within the game
+static LOCAL: Mutex<String> = Mutex::new(String::new());
+
+pub fn copy(text: &str) {
+    match system() {
+        Some(system) => {
+            let _ = system.send(Request::Copy(text.to_string()));
+        }
+        None => *LOCAL.lock().unwrap() = text.to_string(),
+    }
+}
+
+// Text on its way from whatever holds the clipboard, which can take a few frames
+pub enum Paste {
+    Ready(String),
+    Waiting(Receiver<Option<String>>),
+}
+
+impl Paste {
+    // The text once it arrived. Empty when nothing holds text, or it didn't answer in time
+    pub fn poll(&mut self) -> Option<String> {
+        match self {
+            Paste::Ready(text) => Some(std::mem::take(text)),
+            Paste::Waiting(answer) => match answer.try_recv() {
+                Ok(text) => Some(text.unwrap_or_default()),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => Some(String::new()),
+            },
+        }
+    }
+}
+
+pub fn paste() -> Paste {
+    let Some(system) = system() else {
+        return Paste::Ready(LOCAL.lock().unwrap().clone());
+    };
+
+    let (reply, answer) = mpsc::channel();
+    if system.send(Request::Paste(reply)).is_err() {
+        return Paste::Ready(String::new());
+    }
+    Paste::Waiting(answer)
+}
+
+fn system() -> Option<&'static Sender<Request>> {
+    SYSTEM.get_or_init(start).as_ref()
+}
+
+// X11 asks whoever holds the clipboard for its text each time something pastes, so it's served
+// from a thread of its own for as long as the game runs
+fn start() -> Option<Sender<Request>> {
+    let (sender, requests) = mpsc::channel();
+    let (ready_sender, ready) = mpsc::channel();
+
+    thread::spawn(move || {
+        if let Err(e) = serve(requests, &ready_sender) {
+            let _ = ready_sender.send(Err(e));
+        }
+    });
+
+    match ready.recv() {
+        Ok(Ok(())) => Some(sender),
+        Ok(Err(e)) => {
+            println!(
+                "Could not reach the system clipboard, copying within the game only: {}",
+                e
+            );
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn serve(requests: Receiver<Request>, ready: &Sender<Result<(), String>>) -> Result<(), String> {
+    use std::os::raw::{c_int, c_long, c_uchar, c_ulong};
+    use std::time::Instant;
+    use std::{mem, ptr, slice};
+    use x11_dl::xlib::{self, XEvent, XSelectionEvent, Xlib};
+
+    // A paste still waiting for its text. Each gets a window of its own to receive it on, so a
+    // late answer to one that timed out can't be taken for a newer one's
+    struct Pasting {
+        window: xlib::Window,
+        reply: Sender<Option<String>>,
+        since: Instant,
+    }
+
+    // X servers are polled for events between requests
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    let xlib = Xlib::open().map_err(|e| e.to_string())?;
+
+    unsafe {
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        if display.is_null() {
+            return Err(String::from("no X display"));
+        }
+
+        // Selections are owned by windows, this one is never shown
+        let root = (xlib.XDefaultRootWindow)(display);
+        let window = (xlib.XCreateSimpleWindow)(display, root, 0, 0, 1, 1, 0, 0, 0);
+        let atom = |name: &std::ffi::CStr| (xlib.XInternAtom)(display, name.as_ptr(), xlib::False);
+        let clipboard = atom(c"CLIPBOARD");
+        let utf8 = atom(c"UTF8_STRING");
+        let targets = atom(c"TARGETS");
+        // Where pasted text gets put for us to read
+        let property = atom(c"BETTER_OSU_CLIPBOARD");
+
+        let _ = ready.send(Ok(()));
+
+        let mut owned: Option<String> = None;
+        let mut pasting: Vec<Pasting> = Vec::new();
+
+        loop {
+            let first = match requests.recv_timeout(POLL_INTERVAL) {
+                Ok(request) => Some(request),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    (xlib.XCloseDisplay)(display);
+                    return Ok(());
+                }
+            };
+
+            for request in first.into_iter().chain(requests.try_iter()) {
+                match request {
+                    Request::Copy(text) => {
+                        (xlib.XSetSelectionOwner)(display, clipboard, window, xlib::CurrentTime);
+                        owned = ((xlib.XGetSelectionOwner)(display, clipboard) == window)
+                            .then_some(text);
+                    }
+                    Request::Paste(reply) => match &owned {
+                        Some(text) => {
+                            let _ = reply.send(Some(text.clone()));
+                        }
+                        None => {
+                            let window =
+                                (xlib.XCreateSimpleWindow)(display, root, 0, 0, 1, 1, 0, 0, 0);
+                            (xlib.XConvertSelection)(
+                                display,
+                                clipboard,
+                                utf8,
+                                property,
+                                window,
+                                xlib::CurrentTime,
+                            );
+                            pasting.push(Pasting {
+                                window,
+                                reply,
+                                since: Instant::now(),
+                            });
+                        }
+                    },
+                }
+            }
+
+            // Dropping the reply tells the paste it's not coming
+            pasting.retain(|paste| {
+                let waiting = paste.since.elapsed() < PASTE_TIMEOUT;
+                if !waiting {
+                    (xlib.XDestroyWindow)(display, paste.window);
+                }
+                waiting
+            });
+            (xlib.XFlush)(display);
+
+            while (xlib.XPending)(display) > 0 {
+                let mut event: XEvent = mem::zeroed();
+                (xlib.XNextEvent)(display, &mut event);
+
+                match event.get_type() {
+                    xlib::SelectionClear => owned = None,
+                    xlib::SelectionRequest => {
+                        let request = event.selection_request;
+                        // Old programs leave the property for the owner to choose
+                        let target_property = if request.property == 0 {
+                            request.target
+                        } else {
+                            request.property
+                        };
+
+                        let answered = match &owned {
+                            Some(_) if request.target == targets => {
+                                let supported = [targets, utf8];
+                                (xlib.XChangeProperty)(
+                                    display,
+                                    request.requestor,
+                                    target_property,
+                                    xlib::XA_ATOM,
+                                    32,
+                                    xlib::PropModeReplace,
+                                    supported.as_ptr() as *const c_uchar,
+                                    supported.len() as c_int,
+                                );
+                                true
+                            }
+                            Some(text) if request.target == utf8 => {
+                                (xlib.XChangeProperty)(
+                                    display,
+                                    request.requestor,
+                                    target_property,
+                                    utf8,
+                                    8,
+                                    xlib::PropModeReplace,
+                                    text.as_ptr(),
+                                    text.len() as c_int,
+                                );
+                                true
+                            }
+                            _ => false,
+                        };
+
+                        let mut notify = XEvent::from(XSelectionEvent {
+                            type_: xlib::SelectionNotify,
+                            serial: 0,
+                            send_event: xlib::True,
+                            display,
+                            requestor: request.requestor,
+                            selection: request.selection,
+                            target: request.target,
+                            property: if answered { target_property } else { 0 },
+                            time: request.time,
+                        });
+                        (xlib.XSendEvent)(display, request.requestor, xlib::False, 0, &mut notify);
+                        (xlib.XFlush)(display);
+                    }
+                    xlib::SelectionNotify => {
+                        let Some(index) = pasting
+                            .iter()
+                            .position(|paste| paste.window == event.selection.requestor)
+                        else {
+                            continue;
+                        };
+                        let Pasting { window, reply, .. } = pasting.remove(index);
+                        if event.selection.property == 0 {
+                            (xlib.XDestroyWindow)(display, window);
+                            let _ = reply.send(None);
+                            continue;
+                        }
+
+                        let (mut actual_type, mut format) = (0, 0);
+                        let (mut count, mut remaining): (c_ulong, c_ulong) = (0, 0);
+                        let mut data = ptr::null_mut();
+                        (xlib.XGetWindowProperty)(
+                            display,
+                            window,
+                            property,
+                            0,
+                            c_long::MAX / 4,
+                            xlib::True,
+                            xlib::AnyPropertyType as c_ulong,
+                            &mut actual_type,
+                            &mut format,
+                            &mut count,
+                            &mut remaining,
+                            &mut data,
+                        );
+
+                        // Text too big to send in one go comes in parts, which one line
+                        // doesn't need
+                        let text =
+                            (!data.is_null() && actual_type == utf8 && format == 8).then(|| {
+                                let bytes = slice::from_raw_parts(data, count as usize);
+                                String::from_utf8_lossy(bytes).into_owned()
+                            });
+                        if !data.is_null() {
+                            (xlib.XFree)(data as *mut _);
+                        }
+                        (xlib.XDestroyWindow)(display, window);
+                        let _ = reply.send(text);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn serve(_requests: Receiver<Request>, _ready: &Sender<Result<(), String>>) -> Result<(), String> {
+    Err(String::from("only X11 is supported"))
+}
@@ -0,0 +1,244 @@
+use crate::settings::Settings;
+use crate::skin::Skin;
+use graphics::math::{add, mul_scalar, square_len, sub, Vec2d};
+use graphics::{ellipse, Context, Ellipse, Image, Transformed};
+use piston_window::{
+    Button, Flip, G2d, G2dTexture, G2dTextureContext, ImageSize, Texture, TextureSettings,
+};
+use std::collections::{HashSet, VecDeque};
+use std::f64::consts::TAU;
+use std::time::{Duration, Instant};
+
+// Skin images are made for a 768 pixel tall window
+const SKIN_HEIGHT: f64 = 768.0;
+const CURSOR_RADIUS: f64 = 20.0;
+const PRESSED_SCALE: f64 = 1.3;
+const ROTATION_PERIOD: f64 = 10.0;
+
+const TRAIL_FADE: Duration = Duration::from_millis(150);
+const LONG_TRAIL_FADE: Duration = Duration::from_millis(300);
+// Distance between two parts of the long trail, in window pixels at the default size
+const LONG_TRAIL_SPACING: f64 = 3.0;
+const RIPPLE_DURATION: Duration = Duration::from_millis(500);
+const RIPPLE_RADIUS: f64 = 60.0;
+
+const CURSOR_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CursorTrail {
+    Off,
+    // A trail image wherever the cursor was seen
+    Sprites,
+    // Trail images filling the whole path in between too
+    Long,
+}
+
+// Drawn in place of the system cursor, with the current skin's cursor, cursormiddle,
+// cursortrail and cursor-ripple images when it has them
+pub struct Cursor {
+    cursor_tex: Option<G2dTexture>,
+    middle_tex: Option<G2dTexture>,
+    trail_tex: Option<G2dTexture>,
+    ripple_tex: Option<G2dTexture>,
+    size: f64,
+    rotate: bool,
+    trail_kind: CursorTrail,
+    ripples_enabled: bool,
+    position: Option<Vec2d>,
+    // Buttons held down, key repeat presses them again without releasing
+    held: HashSet<Button>,
+    trail: VecDeque<(Vec2d, Instant)>,
+    ripples: VecDeque<(Vec2d, Instant)>,
+    created_at: Instant,
+}
+
+impl Cursor {
+    pub fn new(tex_ctx: &mut G2dTextureContext, skin: &Skin, settings: &Settings) -> Self {
+        let mut load = |name: &str| {
+            skin.image(name).and_then(|path| {
+                Texture::from_path(tex_ctx, path, Flip::None, &TextureSettings::new()).ok()
+            })
+        };
+
+        Self {
+            cursor_tex: load("cursor"),
+            middle_tex: load("cursormiddle"),
+            trail_tex: load("cursortrail"),
+            ripple_tex: load("cursor-ripple"),
+            size: settings.cursor_size,
+            rotate: settings.cursor_rotate,
+            trail_kind: settings.cursor_trail,
+            ripples_enabled: settings.cursor_ripples,
+            position: None,
+            held: HashSet::new(),
+            trail: VecDeque::new(),
+            ripples: VecDeque::new(),
+            created_at: Instant::now(),
+        }
+    }
+
+    pub fn move_to(&mut self, position: Vec2d) {
+        let now = Instant::now();
+
+        match (self.trail_kind, self.position) {
+            (CursorTrail::Off, _) => {}
+            (CursorTrail::Long, Some(last)) => {
+                let spacing = LONG_TRAIL_SPACING * self.size;
+                let distance = square_len(sub(position, last)).sqrt();
+                let steps = (distance / spacing).floor() as usize;
+
+                for step in 1..=steps {
+                    let progress = step as f64 * spacing / distance;
+                    self.trail
+                        .push_back((add(last, mul_scalar(sub(position, last), progress)), now));
+                }
+            }
+            _ => self.trail.push_back((position, now)),
+        }
+
+        self.position = Some(position);
+    }
+
+    // Clicks and hit keys make the cursor grow and leave a ripple
+    pub fn press(&mut self, button: Button) {
+        if !self.held.insert(button) {
+            return;
+        }
+
+        if let (true, Some(position)) = (self.ripples_enabled, self.position) {
+            self.ripples.push_back((position, Instant::now()));
+        }
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.held.remove(&button);
+    }
+
+    pub fn render(&mut self, c: Context, g: &mut G2d) {
+        let Some(position) = self.position else {
+            return;
+        };
+
+        let [_, win_height] = c.get_view_size();
+        let scale = self.size * win_height / SKIN_HEIGHT;
+
+        let trail_fade = match self.trail_kind {
+            CursorTrail::Long => LONG_TRAIL_FADE,
+            _ => TRAIL_FADE,
+        };
+        while self
+            .trail
+            .front()
+            .is_some_and(|(_, at)| at.elapsed() > trail_fade)
+        {
+            self.trail.pop_front();
+        }
+        while self
+            .ripples
+            .front()
+            .is_some_and(|(_, at)| at.elapsed() > RIPPLE_DURATION)
+        {
+            self.ripples.pop_front();
+        }
+
+        for (point, at) in &self.ripples {
+            let progress = at.elapsed().as_secs_f64() / RIPPLE_DURATION.as_secs_f64();
+            let alpha = (1.0 - progress) as f32 * 0.6;
+            let ripple_scale = scale * (0.2 + progress * 0.8);
+
+            match &self.ripple_tex {
+                Some(tex) => draw_centered(c, g, tex, *point, ripple_scale, 0.0, alpha),
+                None => Ellipse::new_border([1.0, 1.0, 1.0, alpha], 2.0).draw(
+                    circle_rect(*point, RIPPLE_RADIUS * ripple_scale),
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                ),
+            }
+        }
+
+        for (point, at) in &self.trail {
+            let alpha = (1.0 - at.elapsed().as_secs_f64() / trail_fade.as_secs_f64()) as f32;
+
+            match &self.trail_tex {
+                Some(tex) => draw_centered(c, g, tex, *point, scale, 0.0, alpha),
+                None => ellipse(
+                    [
+                        CURSOR_COLOR[0],
+                        CURSOR_COLOR[1],
+                        CURSOR_COLOR[2],
+                        alpha * 0.5,
+                    ],
+                    circle_rect(*point, CURSOR_RADIUS * scale * 0.5),
+                    c.transform,
+                    g,
+                ),
+            }
+        }
+
+        let cursor_scale = if !self.held.is_empty() {
+            scale * PRESSED_SCALE
+        } else {
+            scale
+        };
+        let rotation = if self.rotate {
+            self.created_at.elapsed().as_secs_f64() / ROTATION_PERIOD * TAU
+        } else {
+            0.0
+        };
+
+        match &self.cursor_tex {
+            Some(tex) => draw_centered(c, g, tex, position, cursor_scale, rotation, 1.0),
+            None => {
+                let rect = circle_rect(position, CURSOR_RADIUS * cursor_scale);
+                ellipse([1.0, 1.0, 1.0, 0.3], rect, c.transform, g);
+                Ellipse::new_border(CURSOR_COLOR, 3.0 * cursor_scale).draw(
+                    rect,
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+        }
+
+        // The middle stays the same size and never rotates
+        match &self.middle_tex {
+            Some(tex) => draw_centered(c, g, tex, position, scale, 0.0, 1.0),
+            None => ellipse(
+                [1.0, 1.0, 1.0, 1.0],
+                circle_rect(position, 3.0 * scale),
+                c.transform,
+                g,
+            ),
+        }
+    }
+}
+
+fn draw_centered(
+    c: Context,
+    g: &mut G2d,
+    tex: &G2dTexture,
+    position: Vec2d,
+    scale: f64,
+    rotation: f64,
+    alpha: f32,
+) {
+    let (width, height) = tex.get_size();
+    let transform = c
+        .transform
+        .trans(position[0], position[1])
+        .rot_rad(rotation)
+        .scale(scale, scale)
+        .trans(-(width as f64) / 2.0, -(height as f64) / 2.0);
+
+    Image::new_color([1.0, 1.0, 1.0, alpha]).draw(tex, &c.draw_state, transform, g);
+}
+
+fn circle_rect(center: Vec2d, radius: f64) -> [f64; 4] {
+    [
+        center[0] - radius,
+        center[1] - radius,
+        radius * 2.0,
+        radius * 2.0,
+    ]
+}
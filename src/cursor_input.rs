@@ -0,0 +1,434 @@
+use graphics::math::{add, mul_scalar, sub, Vec2d};
+use piston_window::GenericEvent;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputMode {
+    Mouse,
+    // Pen positions on a tablet mapped onto the window, like a tablet driver's absolute mode
+    Tablet,
+}
+
+// Part of the tablet mapped onto the window, in millimetres. Saved as
+// `TabletArea = width height x y rotation`, where x and y are the area's center
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TabletArea {
+    pub width: f64,
+    pub height: f64,
+    pub x: f64,
+    pub y: f64,
+    // Degrees, clockwise
+    pub rotation: f64,
+}
+
+impl TabletArea {
+    pub fn parse(value: &str) -> Option<Self> {
+        let values = value
+            .split_whitespace()
+            .map(|v| v.parse::<f64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        match values[..] {
+            [width, height, x, y, rotation] if width > 0.0 && height > 0.0 => Some(Self {
+                width,
+                height,
+                x,
+                y,
+                rotation,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_setting(self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.width, self.height, self.x, self.y, self.rotation
+        )
+    }
+
+    // Where a pen position ends up in a window of the given size, outside of it when the pen is
+    // outside of the area
+    pub fn map(&self, position: Vec2d, window_size: Vec2d) -> Vec2d {
+        let [dx, dy] = sub(position, [self.x, self.y]);
+
+        // Rotating the area clockwise turns pen movement the other way
+        let (sin, cos) = (-self.rotation.to_radians()).sin_cos();
+        let rotated = [dx * cos - dy * sin, dx * sin + dy * cos];
+
+        [
+            (rotated[0] / self.width + 0.5) * window_size[0],
+            (rotated[1] / self.height + 0.5) * window_size[1],
+        ]
+    }
+}
+
+impl Default for TabletArea {
+    // Whole active area of a small Wacom tablet
+    fn default() -> Self {
+        Self {
+            width: 152.0,
+            height: 95.0,
+            x: 76.0,
+            y: 47.5,
+            rotation: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    // System cursor position in the window
+    Cursor(Vec2d),
+    // Raw mouse movement, in pixels
+    Relative(Vec2d),
+    // Raw pen position, in millimetres from the tablet's top left corner. Piston doesn't report
+    // these, they come from the raw input thread or an input script
+    Absolute(Vec2d),
+    Resize(Vec2d),
+}
+
+impl InputEvent {
+    pub fn from_event<E: GenericEvent>(e: &E) -> Option<Self> {
+        if let Some(position) = e.mouse_cursor_args() {
+            Some(InputEvent::Cursor(position))
+        } else if let Some(motion) = e.mouse_relative_args() {
+            Some(InputEvent::Relative(motion))
+        } else {
+            e.resize_args()
+                .map(|args| InputEvent::Resize(args.window_size))
+        }
+    }
+}
+
+// Synthetic input replayed from a file, one `<milliseconds> <kind> <x> <y>` event per line with
+// kind being cursor, relative or absolute. Times count from when the script is loaded
+pub struct InputScript {
+    events: VecDeque<(f64, InputEvent)>,
+    started: Instant,
+}
+
+impl InputScript {
+    pub fn load<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut events = VecDeque::new();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let [time, kind, x, y] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+                println!("Ignoring invalid input event \"{}\"", line);
+                continue;
+            };
+
+            let (Ok(time), Ok(x), Ok(y)) = (time.parse(), x.parse(), y.parse()) else {
+                println!("Ignoring invalid input event \"{}\"", line);
+                continue;
+            };
+
+            let event = match kind {
+                "cursor" => InputEvent::Cursor([x, y]),
+                "relative" => InputEvent::Relative([x, y]),
+                "absolute" => InputEvent::Absolute([x, y]),
+                _ => {
+                    println!("Ignoring input event of unknown kind \"{}\"", kind);
+                    continue;
+                }
+            };
+
+            events.push_back((time, event));
+        }
+
+        Some(Self {
+            events,
+            started: Instant::now(),
+        })
+    }
+
+    // Next event whose time has come
+    pub fn next_event(&mut self) -> Option<InputEvent> {
+        let elapsed = self.started.elapsed().as_secs_f64() * 1000.0;
+
+        match self.events.front() {
+            Some((time, _)) if *time <= elapsed => self.events.pop_front().map(|(_, e)| e),
+            _ => None,
+        }
+    }
+}
+
+// Gameplay cursor, driven by whichever input the player uses instead of the system cursor. Every
+// input goes through `feed`, so any source of events can move it
+pub struct CursorInput {
+    mode: InputMode,
+    sensitivity: f64,
+    area: TabletArea,
+    confine: bool,
+    window_size: Vec2d,
+    position: Vec2d,
+    // Until a tablet reports a pen position the system cursor keeps moving the cursor
+    tablet_seen: bool,
+}
+
+impl CursorInput {
+    pub fn new(
+        mode: InputMode,
+        sensitivity: f64,
+        area: TabletArea,
+        confine: bool,
+        window_size: Vec2d,
+    ) -> Self {
+        Self {
+            mode,
+            sensitivity,
+            area,
+            confine,
+            window_size,
+            position: mul_scalar(window_size, 0.5),
+            tablet_seen: false,
+        }
+    }
+
+    // Returns the new cursor position when the event moved it
+    pub fn feed(&mut self, event: InputEvent) -> Option<Vec2d> {
+        let position = match event {
+            InputEvent::Resize(window_size) => {
+                self.window_size = window_size;
+                return None;
+            }
+            InputEvent::Cursor(position) if !self.uses_relative() && !self.tablet_seen => position,
+            InputEvent::Relative(motion) if self.uses_relative() => {
+                add(self.position, mul_scalar(motion, self.sensitivity))
+            }
+            InputEvent::Absolute(position) if self.mode == InputMode::Tablet => {
+                self.tablet_seen = true;
+                self.area.map(position, self.window_size)
+            }
+            _ => return None,
+        };
+
+        self.position = if self.confine {
+            [
+                position[0].clamp(0.0, self.window_size[0]),
+                position[1].clamp(0.0, self.window_size[1]),
+            ]
+        } else {
+            position
+        };
+
+        Some(self.position)
+    }
+
+    pub fn position(&self) -> Vec2d {
+        self.position
+    }
+
+    // Raw movement only keeps coming at the edges of the window while the system cursor is held
+    // in it
+    pub fn wants_capture(&self) -> bool {
+        self.uses_relative()
+    }
+
+    // Scaled mouse movement can't follow the system cursor, it adds up raw motion instead
+    fn uses_relative(&self) -> bool {
+        self.mode == InputMode::Mouse && self.sensitivity != 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston_window::{Event, Input, Motion, ResizeArgs};
+
+    const WINDOW: Vec2d = [1280.0, 720.0];
+
+    fn assert_near(actual: Vec2d, expected: Vec2d) {
+        let distance = sub(actual, expected);
+        assert!(
+            distance[0].abs() < 1e-9 && distance[1].abs() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn events_come_from_piston_input() {
+        let cursor = Event::Input(Input::Move(Motion::MouseCursor([3.0, 4.0])), None);
+        let relative = Event::Input(Input::Move(Motion::MouseRelative([-1.0, 2.0])), None);
+        let resize = Event::Input(
+            Input::Resize(ResizeArgs {
+                window_size: [800.0, 600.0],
+                draw_size: [800, 600],
+            }),
+            None,
+        );
+
+        assert_eq!(
+            InputEvent::from_event(&cursor),
+            Some(InputEvent::Cursor([3.0, 4.0]))
+        );
+        assert_eq!(
+            InputEvent::from_event(&relative),
+            Some(InputEvent::Relative([-1.0, 2.0]))
+        );
+        assert_eq!(
+            InputEvent::from_event(&resize),
+            Some(InputEvent::Resize([800.0, 600.0]))
+        );
+    }
+
+    #[test]
+    fn tablet_areas_are_offset_to_their_center() {
+        let area = TabletArea::default();
+        assert_near(area.map([76.0, 47.5], WINDOW), [640.0, 360.0]);
+        assert_near(area.map([0.0, 0.0], WINDOW), [0.0, 0.0]);
+        assert_near(area.map([152.0, 95.0], WINDOW), WINDOW);
+
+        // A 40x25mm area in the tablet's top right corner
+        let small = TabletArea {
+            width: 40.0,
+            height: 25.0,
+            x: 132.0,
+            y: 12.5,
+            rotation: 0.0,
+        };
+        assert_near(small.map([112.0, 0.0], WINDOW), [0.0, 0.0]);
+        assert_near(small.map([142.0, 18.75], WINDOW), [960.0, 540.0]);
+        // Beyond the area is beyond the window
+        assert_near(small.map([76.0, 47.5], WINDOW), [-1152.0, 1368.0]);
+    }
+
+    #[test]
+    fn rotated_areas_turn_pen_movement_the_other_way() {
+        let area = TabletArea {
+            rotation: 90.0,
+            ..TabletArea::default()
+        };
+
+        // The area's right edge is at the top once it's turned clockwise
+        assert_near(area.map([76.0, 47.5], WINDOW), [640.0, 360.0]);
+        assert_near(
+            area.map([76.0, 0.0], WINDOW),
+            [640.0 - 47.5 / 152.0 * 1280.0, 360.0],
+        );
+        assert_near(
+            area.map([86.0, 47.5], WINDOW),
+            [640.0, 360.0 - 10.0 / 95.0 * 720.0],
+        );
+    }
+
+    #[test]
+    fn tablet_areas_round_trip_through_settings() {
+        let area = TabletArea {
+            width: 100.0,
+            height: 62.5,
+            x: 76.0,
+            y: 47.5,
+            rotation: -15.0,
+        };
+        assert_eq!(TabletArea::parse(&area.to_setting()), Some(area));
+        assert_eq!(TabletArea::parse("100 62.5 76 47.5"), None);
+        assert_eq!(TabletArea::parse("0 62.5 76 47.5 0"), None);
+    }
+
+    #[test]
+    fn mice_follow_the_system_cursor_at_full_sensitivity() {
+        let mut input =
+            CursorInput::new(InputMode::Mouse, 1.0, TabletArea::default(), false, WINDOW);
+
+        assert_eq!(
+            input.feed(InputEvent::Cursor([10.0, 20.0])),
+            Some([10.0, 20.0])
+        );
+        assert_eq!(input.feed(InputEvent::Relative([5.0, 5.0])), None);
+        assert_eq!(input.feed(InputEvent::Absolute([0.0, 0.0])), None);
+        assert!(!input.wants_capture());
+    }
+
+    #[test]
+    fn sensitivity_scales_raw_motion() {
+        let mut input =
+            CursorInput::new(InputMode::Mouse, 2.5, TabletArea::default(), false, WINDOW);
+
+        assert_eq!(input.feed(InputEvent::Cursor([10.0, 20.0])), None);
+        assert_eq!(
+            input.feed(InputEvent::Relative([4.0, -2.0])),
+            Some([650.0, 355.0])
+        );
+        assert_eq!(
+            input.feed(InputEvent::Relative([4.0, -2.0])),
+            Some([660.0, 350.0])
+        );
+        assert!(input.wants_capture());
+    }
+
+    #[test]
+    fn confinement_keeps_the_cursor_in_the_window() {
+        let mut confined =
+            CursorInput::new(InputMode::Mouse, 2.0, TabletArea::default(), true, WINDOW);
+        let mut free =
+            CursorInput::new(InputMode::Mouse, 2.0, TabletArea::default(), false, WINDOW);
+
+        let motion = InputEvent::Relative([400.0, -200.0]);
+        assert_eq!(confined.feed(motion), Some([1280.0, 0.0]));
+        assert_eq!(free.feed(motion), Some([1440.0, -40.0]));
+
+        // Moving back starts from the edge rather than from where the mouse went
+        assert_eq!(
+            confined.feed(InputEvent::Relative([-10.0, 10.0])),
+            Some([1260.0, 20.0])
+        );
+    }
+
+    #[test]
+    fn tablets_take_over_from_the_system_cursor() {
+        let mut input =
+            CursorInput::new(InputMode::Tablet, 1.0, TabletArea::default(), true, WINDOW);
+
+        // Until a pen is seen there's only the system cursor
+        assert_eq!(
+            input.feed(InputEvent::Cursor([10.0, 20.0])),
+            Some([10.0, 20.0])
+        );
+        assert_eq!(
+            input.feed(InputEvent::Absolute([38.0, 23.75])),
+            Some([320.0, 180.0])
+        );
+        assert_eq!(input.feed(InputEvent::Cursor([10.0, 20.0])), None);
+        assert_eq!(input.feed(InputEvent::Relative([5.0, 5.0])), None);
+
+        // Off the area and confined to the window
+        assert_eq!(
+            input.feed(InputEvent::Absolute([160.0, -5.0])),
+            Some([1280.0, 0.0])
+        );
+    }
+
+    #[test]
+    fn resizing_changes_what_the_area_maps_to() {
+        let mut input =
+            CursorInput::new(InputMode::Tablet, 1.0, TabletArea::default(), true, WINDOW);
+        assert_eq!(input.position(), [640.0, 360.0]);
+
+        assert_eq!(input.feed(InputEvent::Resize([800.0, 600.0])), None);
+        assert_eq!(
+            input.feed(InputEvent::Absolute([152.0, 95.0])),
+            Some([800.0, 600.0])
+        );
+        assert_eq!(
+            input.feed(InputEvent::Absolute([76.0, 47.5])),
+            Some([400.0, 300.0])
+        );
+
+        // Confinement follows the new size too
+        assert_eq!(
+            input.feed(InputEvent::Absolute([200.0, 200.0])),
+            Some([800.0, 600.0])
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use crate::settings::Settings;
+use glutin::surface::{GlSurface, SwapInterval};
+use piston_window::{EventLoop, PistonWindow};
+use std::num::NonZeroU32;
+use winit::dpi::PhysicalSize;
+use winit::window::Fullscreen;
+
+// What "unlimited" frame rates are capped to, the event loop always needs some limit
+const UNLIMITED_FPS: u64 = 9999;
+// Refresh rate when the monitor doesn't say what it runs at
+const FALLBACK_REFRESH_RATE: u32 = 60;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DisplayMode {
+    Windowed,
+    // A window covering the monitor, at the desktop's resolution
+    Borderless,
+    // Switches the monitor to the chosen resolution and refresh rate
+    Fullscreen,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameLimiter {
+    Unlimited,
+    // Multiples of the monitor's refresh rate
+    Refresh2x,
+    Refresh4x,
+    Refresh8x,
+    Custom,
+}
+
+// Window size in pixels, and the refresh rate fullscreen switches the monitor to. Saved as
+// `Resolution = 1280x720@60`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+}
+
+impl Resolution {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (size, refresh_rate) = value.trim().split_once('@')?;
+        let (width, height) = size.split_once('x')?;
+
+        match (width.parse(), height.parse(), refresh_rate.parse()) {
+            (Ok(width), Ok(height), Ok(refresh_rate)) if width > 0 && height > 0 => Some(Self {
+                width,
+                height,
+                refresh_rate,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_setting(self) -> String {
+        format!("{}x{}@{}", self.width, self.height, self.refresh_rate)
+    }
+
+    pub fn label(self) -> String {
+        format!("{}x{} @ {}Hz", self.width, self.height, self.refresh_rate)
+    }
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            refresh_rate: FALLBACK_REFRESH_RATE,
+        }
+    }
+}
+
+// The window's side of the settings, kept around to only touch the window when one of them
+// changes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Display {
+    mode: DisplayMode,
+    resolution: Resolution,
+    vsync: bool,
+    frame_limiter: FrameLimiter,
+    custom_frame_limit: u32,
+}
+
+impl Display {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            mode: settings.display_mode,
+            resolution: settings.resolution,
+            vsync: settings.vsync,
+            frame_limiter: settings.frame_limiter,
+            custom_frame_limit: settings.custom_frame_limit,
+        }
+    }
+
+    // Leaves the window's size alone unless the mode or resolution changed since `previous`, so
+    // windows resized by hand stay that way
+    pub fn apply(&self, window: &mut PistonWindow, previous: Option<Display>) {
+        let winit_window = &window.window.window;
+        let resized = previous.is_none_or(|previous| {
+            (previous.mode, previous.resolution) != (self.mode, self.resolution)
+        });
+
+        match self.mode {
+            _ if !resized => {}
+            DisplayMode::Windowed => {
+                winit_window.set_fullscreen(None);
+                winit_window.set_inner_size(PhysicalSize::new(
+                    self.resolution.width,
+                    self.resolution.height,
+                ));
+            }
+            DisplayMode::Borderless => {
+                winit_window.set_fullscreen(Some(Fullscreen::Borderless(None)))
+            }
+            DisplayMode::Fullscreen => {
+                let video_mode = winit_window.current_monitor().and_then(|monitor| {
+                    monitor.video_modes().find(|mode| {
+                        let size = mode.size();
+                        Resolution {
+                            width: size.width,
+                            height: size.height,
+                            refresh_rate: hertz(mode.refresh_rate_millihertz()),
+                        } == self.resolution
+                    })
+                });
+
+                match video_mode {
+                    Some(video_mode) => {
+                        winit_window.set_fullscreen(Some(Fullscreen::Exclusive(video_mode)))
+                    }
+                    None => {
+                        println!(
+                            "The monitor can't show {}, using borderless fullscreen",
+                            self.resolution.label()
+                        );
+                        winit_window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+                    }
+                }
+            }
+        }
+
+        let refresh_rate = match self.mode {
+            DisplayMode::Fullscreen => self.resolution.refresh_rate,
+            _ => winit_window
+                .current_monitor()
+                .and_then(|monitor| monitor.refresh_rate_millihertz())
+                .map_or(FALLBACK_REFRESH_RATE, hertz),
+        };
+
+        let interval = if self.vsync {
+            SwapInterval::Wait(NonZeroU32::new(1).unwrap())
+        } else {
+            SwapInterval::DontWait
+        };
+        if let Err(e) = window
+            .window
+            .surface
+            .set_swap_interval(&window.window.ctx, interval)
+        {
+            println!("Could not change vsync: {}", e);
+        }
+
+        window.set_max_fps(max_fps(
+            self.frame_limiter,
+            self.custom_frame_limit,
+            refresh_rate,
+        ));
+    }
+}
+
+// Sizes and refresh rates the window's monitor can run at, biggest and fastest first
+pub fn resolutions(window: &PistonWindow) -> Vec<Resolution> {
+    let Some(monitor) = window.window.window.current_monitor() else {
+        return Vec::new();
+    };
+
+    let mut resolutions = monitor
+        .video_modes()
+        .map(|mode| Resolution {
+            width: mode.size().width,
+            height: mode.size().height,
+            refresh_rate: hertz(mode.refresh_rate_millihertz()),
+        })
+        .collect::<Vec<_>>();
+
+    resolutions.sort_by_key(|r| std::cmp::Reverse((r.width, r.height, r.refresh_rate)));
+    // Modes only differing in bit depth look the same here
+    resolutions.dedup();
+    resolutions
+}
+
+fn max_fps(limiter: FrameLimiter, custom_limit: u32, refresh_rate: u32) -> u64 {
+    let refresh_rate = refresh_rate.max(1) as u64;
+    match limiter {
+        FrameLimiter::Unlimited => UNLIMITED_FPS,
+        FrameLimiter::Refresh2x => refresh_rate * 2,
+        FrameLimiter::Refresh4x => refresh_rate * 4,
+        FrameLimiter::Refresh8x => refresh_rate * 8,
+        FrameLimiter::Custom => custom_limit.max(1) as u64,
+    }
+}
+
+// Monitors report things like 59.94Hz, which is 60 as far as anyone choosing it is concerned
+fn hertz(millihertz: u32) -> u32 {
+    (millihertz + 500) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolutions_round_trip_through_settings() {
+        let resolution = Resolution {
+            width: 1920,
+            height: 1080,
+            refresh_rate: 144,
+        };
+        assert_eq!(resolution.to_setting(), "1920x1080@144");
+        assert_eq!(Resolution::parse("1920x1080@144"), Some(resolution));
+
+        assert_eq!(Resolution::parse("1920x1080"), None);
+        assert_eq!(Resolution::parse("0x1080@60"), None);
+        assert_eq!(Resolution::parse("wide@60"), None);
+    }
+
+    #[test]
+    fn frame_limits_follow_the_refresh_rate() {
+        assert_eq!(max_fps(FrameLimiter::Refresh2x, 240, hertz(59_940)), 120);
+        assert_eq!(max_fps(FrameLimiter::Refresh8x, 240, 144), 1152);
+        assert_eq!(max_fps(FrameLimiter::Custom, 240, 144), 240);
+        assert_eq!(max_fps(FrameLimiter::Unlimited, 240, 144), UNLIMITED_FPS);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::animations::EasingType;
+use std::collections::HashMap;
+use std::fs;
+
+const EASINGS_FILE: &str = "easings.cfg";
+// Points curves get sampled into, enough for them to look the same
+const LOOKUP_TABLE_SIZE: usize = 256;
+
+// Easings of the menus' motion, which can be tuned in easings.cfg without rebuilding. One
+// `Name = easing` pair per line, anything `EasingType::parse` reads being an easing
+pub struct Easings {
+    easings: HashMap<String, EasingType>,
+}
+
+impl Easings {
+    pub fn load() -> Self {
+        let mut easings = HashMap::new();
+
+        // Most people never make one, the defaults are fine without it
+        let Ok(content) = fs::read_to_string(EASINGS_FILE) else {
+            return Self { easings };
+        };
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, value)) = line.split_once('=') else {
+                println!("Ignoring invalid easing \"{}\"", line);
+                continue;
+            };
+
+            match EasingType::parse(value) {
+                Some(easing_type @ (EasingType::CubicBezier(..) | EasingType::Keyframes(_))) => {
+                    easings.insert(
+                        name.trim().to_string(),
+                        easing_type.sampled(LOOKUP_TABLE_SIZE),
+                    );
+                }
+                Some(easing_type) => {
+                    easings.insert(name.trim().to_string(), easing_type);
+                }
+                None => println!("Ignoring invalid easing \"{}\"", value.trim()),
+            }
+        }
+
+        Self { easings }
+    }
+
+    pub fn get(&self, name: &str, default: EasingType) -> EasingType {
+        self.easings.get(name).cloned().unwrap_or(default)
+    }
+}
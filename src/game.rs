@@ -1,10 +1,467 @@
-struct Game {
+use crate::beatmap::{BeatmapInfo, TrackTiming};
+use crate::catch::CatchPlayfield;
+use crate::input_queue::{InputKind, InputQueue};
+use crate::key_bindings::{self, GameAction, KeyBindings};
+use crate::mania::ManiaPlayfield;
+use crate::music_manager::MusicManager;
+use crate::osu::OsuPlayfield;
+use crate::ruleset::{Ruleset, RulesetContext};
+use crate::settings::Settings;
+use crate::skin::Skin;
+use crate::taiko::TaikoPlayfield;
+use crate::text::{Align, Fonts, RichText};
+use crate::video::VideoPlayer;
+use crate::viewport;
+use graphics::math::Vec2d;
+use graphics::{image, rectangle, Context};
+use piston_window::*;
+use rosu_pp::{Beatmap, GameMode};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const BACKGROUND_DIM: f32 = 0.7;
+const QUICK_RETRY_HOLD: Duration = Duration::from_millis(500);
+// Skipping the intro lands this long before the first object
+const SKIP_LEAD_IN: f64 = 2000.0;
+const HUD_MARGIN: f64 = 12.0;
+
+// Something the main loop has to do for the scene
+pub enum SceneAction {
+    Retry(PathBuf),
+}
+
+// Gameplay screen whatever the ruleset, so the main loop doesn't have to know which one runs
+pub trait Scene {
+    // Texture uploads have to happen outside of draw_2d, so this runs on render events first
+    fn prepare_render(&mut self, tex_ctx: &mut G2dTextureContext, music_mgr: &MusicManager);
+
+    fn render(&mut self, c: Context, g: &mut G2d, glyphs: &mut Fonts, music_mgr: &MusicManager);
+
+    // `arrived` is when a button event happened, which can be well before the window reported it
+    fn event(&mut self, e: &Event, arrived: Instant, music_mgr: &mut MusicManager);
+
+    fn next_action(&mut self) -> Option<SceneAction>;
+
+    // Position of the gameplay cursor in the window, which doesn't have to follow the system one
+    fn cursor_moved(&mut self, position: Vec2d, arrived: Instant, music_mgr: &MusicManager);
+}
+
+pub fn start<P: AsRef<Path>>(
+    map_path: P,
+    tex_ctx: &mut G2dTextureContext,
+    settings: &Settings,
+    skin: &Skin,
+    music_mgr: &mut MusicManager,
+) -> Box<dyn Scene> {
+    let map_path = map_path.as_ref();
+    let beatmap = Beatmap::from_path(map_path).unwrap();
+    let ctx = RulesetContext { settings, skin };
+
+    // Only standard maps can be converted to other modes
+    let mode = match beatmap.mode {
+        GameMode::Osu => settings.ruleset,
+        mode => mode,
+    };
+
+    match mode {
+        GameMode::Osu => Box::new(Game::<OsuPlayfield>::new(
+            map_path, &beatmap, tex_ctx, &ctx, music_mgr,
+        )),
+        GameMode::Taiko => Box::new(Game::<TaikoPlayfield>::new(
+            map_path, &beatmap, tex_ctx, &ctx, music_mgr,
+        )),
+        GameMode::Catch => Box::new(Game::<CatchPlayfield>::new(
+            map_path, &beatmap, tex_ctx, &ctx, music_mgr,
+        )),
+        GameMode::Mania => Box::new(Game::<ManiaPlayfield>::new(
+            map_path, &beatmap, tex_ctx, &ctx, music_mgr,
+        )),
+    }
+}
+
+pub struct Game<R: Ruleset> {
     state: GameState,
-    map: String,
+    map_path: PathBuf,
+    map: BeatmapInfo,
+    background_tex: G2dTexture,
+    video: Option<VideoPlayer>,
+    ruleset: R,
+    inputs: InputQueue<R::Action>,
+    window_size: Vec2d,
+    // Plays in a 4:3 area in the middle of the window instead of all of it
+    letterbox: bool,
+    bindings: KeyBindings,
+    hud_visible: bool,
+    retry_held_since: Option<Instant>,
+    // Where skipping the intro goes to, None if the map starts right away
+    skip_target: Option<f64>,
+    track: MapTrack,
+    actions: VecDeque<SceneAction>,
 }
 
+// The map's audio. Without it the map still plays, in silence
+struct MapTrack {
+    playing: bool,
+}
+
+impl MapTrack {
+    fn start(path: &Path, beatmap: &Beatmap, music_mgr: &mut MusicManager) -> Self {
+        let result = music_mgr.play_track(path, Duration::ZERO, TrackTiming::from_beatmap(beatmap));
+        if let Err(e) = &result {
+            println!("Could not play {:?}: {}", path, e);
+        }
+
+        Self {
+            playing: result.is_ok(),
+        }
+    }
+}
+
+// Maps end when their track does, or when the clock passed the last object. A track that never
+// played has nothing to run out, so only the objects count then
+fn is_map_over(ruleset: &impl Ruleset, track: &MapTrack, music_mgr: &MusicManager) -> bool {
+    ruleset.is_finished() || (track.playing && music_mgr.is_finished())
+}
+
+#[derive(Debug, Eq, PartialEq)]
 enum GameState {
     Ongoing,
     Paused,
     Finished,
 }
+
+impl<R: Ruleset> Game<R> {
+    pub fn new(
+        map_path: &Path,
+        beatmap: &Beatmap,
+        tex_ctx: &mut G2dTextureContext,
+        ctx: &RulesetContext,
+        music_mgr: &mut MusicManager,
+    ) -> Self {
+        let map = BeatmapInfo::from_path(map_path).unwrap();
+        println!(
+            "Playing {} - {} [{}] ({:?}, {:.2} stars)",
+            map.artist,
+            map.title,
+            map.version,
+            R::MODE,
+            R::stars(beatmap)
+        );
+        let background_tex = map
+            .background
+            .as_ref()
+            .and_then(|background| {
+                Texture::from_path(
+                    tex_ctx,
+                    map.file(background),
+                    Flip::None,
+                    &TextureSettings::new(),
+                )
+                .ok()
+            })
+            .unwrap_or_else(|| {
+                Texture::from_path(
+                    tex_ctx,
+                    "assets/background.jpg",
+                    Flip::None,
+                    &TextureSettings::new(),
+                )
+                .unwrap()
+            });
+
+        let video = match &map.video {
+            Some(video) if ctx.settings.background_video => {
+                VideoPlayer::open(map.file(&video.filename), video.offset)
+            }
+            _ => None,
+        };
+
+        let ruleset = R::new(beatmap, ctx);
+
+        let skip_target = beatmap
+            .hit_objects
+            .first()
+            .map(|object| object.start_time - SKIP_LEAD_IN)
+            .filter(|target| *target > 0.0);
+
+        let track = MapTrack::start(&map.file(&map.audio_filename), beatmap, music_mgr);
+
+        Self {
+            state: GameState::Ongoing,
+            map_path: map_path.to_path_buf(),
+            map,
+            background_tex,
+            video,
+            ruleset,
+            inputs: InputQueue::new(),
+            window_size: [0.0, 0.0],
+            letterbox: ctx.settings.letterbox,
+            bindings: ctx.settings.key_bindings.clone(),
+            hud_visible: true,
+            retry_held_since: None,
+            skip_target,
+            track,
+            actions: VecDeque::new(),
+        }
+    }
+
+    fn render_background(&self, c: Context, g: &mut G2d) {
+        let [win_width, win_height] = c.get_view_size();
+        let (back_w, back_h) = self.background_tex.get_size();
+
+        let scale = f64::max(win_width / back_w as f64, win_height / back_h as f64);
+
+        let transform = c
+            .transform
+            .trans(
+                (win_width - back_w as f64 * scale) / 2.0,
+                (win_height - back_h as f64 * scale) / 2.0,
+            )
+            .scale(scale, scale);
+
+        image(&self.background_tex, transform, g);
+    }
+
+    // The part of a view the ruleset and HUD play in
+    fn play_area(&self, view_size: Vec2d) -> [f64; 4] {
+        if self.letterbox {
+            viewport::screen_rect(view_size)
+        } else {
+            [0.0, 0.0, view_size[0], view_size[1]]
+        }
+    }
+
+    // Skipping gives way to a ruleset playing on the same button, like the middle mania column
+    fn can_skip(&self, time: f64) -> bool {
+        let button = self.bindings.button(GameAction::SkipIntro);
+        self.skip_target.is_some_and(|target| time < target)
+            && self.ruleset.action_for(button).is_none()
+    }
+
+    // Judges queued inputs in the order they arrived, with whatever was missed before each of
+    // them judged first
+    fn process_inputs(&mut self) {
+        while let Some(input) = self.inputs.pop() {
+            self.ruleset.update(input.time);
+
+            match input.kind {
+                InputKind::Press(action) => self.ruleset.press(action, input.time),
+                InputKind::Release(action) => self.ruleset.release(action, input.time),
+                InputKind::CursorMoved([x, y]) => {
+                    let [area_x, area_y, area_w, area_h] = self.play_area(self.window_size);
+                    self.ruleset
+                        .cursor_moved([x - area_x, y - area_y], [area_w, area_h]);
+                }
+            }
+        }
+    }
+
+    fn toggle_pause(&mut self, music_mgr: &mut MusicManager) {
+        self.process_inputs();
+
+        match self.state {
+            GameState::Ongoing => {
+                music_mgr.pause();
+                self.state = GameState::Paused;
+            }
+            GameState::Paused => {
+                music_mgr.resume();
+                self.state = GameState::Ongoing;
+            }
+            GameState::Finished => {}
+        }
+    }
+
+    fn render_hud(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, time: f64) {
+        let [win_width, win_height] = c.get_view_size();
+
+        if self.can_skip(time) {
+            let text = format!(
+                "Press {} to skip",
+                key_bindings::button_name(self.bindings.button(GameAction::SkipIntro))
+            );
+            RichText::new(&text, 22, [1.0, 1.0, 1.0, 0.8])
+                .align(Align::Right)
+                .draw(glyphs, c, g, [win_width - 40.0, win_height - 66.0]);
+        }
+
+        if !self.hud_visible {
+            return;
+        }
+
+        let score = self.ruleset.score();
+
+        // Right aligned, so they don't move around as the numbers change width
+        RichText::new(&format!("{:08}", score.score), 28, [1.0, 1.0, 1.0, 1.0])
+            .align(Align::Right)
+            .draw(glyphs, c, g, [win_width - HUD_MARGIN, 8.0]);
+
+        RichText::new(
+            &format!("{:.2}%", score.accuracy * 100.0),
+            18,
+            [1.0, 1.0, 1.0, 1.0],
+        )
+        .align(Align::Right)
+        .draw(glyphs, c, g, [win_width - HUD_MARGIN, 45.0]);
+    }
+
+    fn print_results(&self) {
+        let score = self.ruleset.score();
+
+        println!("Finished {} [{}]", self.map.title, self.map.version);
+        println!(
+            "Score: {} | Accuracy: {:.2}% | Max combo: {} | {}",
+            score.score,
+            score.accuracy * 100.0,
+            score.max_combo,
+            self.ruleset.judgement_counts(),
+        );
+    }
+}
+
+impl<R: Ruleset> Scene for Game<R> {
+    fn prepare_render(&mut self, tex_ctx: &mut G2dTextureContext, music_mgr: &MusicManager) {
+        if let Some(video) = &mut self.video {
+            video.update(music_mgr.position(), tex_ctx);
+
+            if video.failed() {
+                self.video = None;
+            }
+        }
+    }
+
+    fn render(&mut self, c: Context, g: &mut G2d, glyphs: &mut Fonts, music_mgr: &MusicManager) {
+        match &self.video {
+            Some(video) if video.is_visible() => video.render(c, g),
+            _ => self.render_background(c, g),
+        }
+
+        let [win_width, win_height] = c.get_view_size();
+        rectangle(
+            [0.0, 0.0, 0.0, BACKGROUND_DIM],
+            [0.0, 0.0, win_width, win_height],
+            c.transform,
+            g,
+        );
+
+        let area = self.play_area([win_width, win_height]);
+        let c = viewport::area_context(viewport::clip(c, area), area);
+        self.ruleset.render(c, g, glyphs, music_mgr.position());
+        self.render_hud(c, g, glyphs, music_mgr.position());
+    }
+
+    fn event(&mut self, e: &Event, arrived: Instant, music_mgr: &mut MusicManager) {
+        if let Some(args) = e.render_args() {
+            self.window_size = args.window_size;
+        }
+
+        let time = music_mgr.position_at(arrived);
+        let pressed = e.press_args();
+        let released = e.release_args();
+
+        // Actions of the game screen itself take precedence over the ruleset's
+        match pressed.and_then(|button| self.bindings.action_for(button, R::MODE)) {
+            Some(GameAction::Pause) => {
+                self.toggle_pause(music_mgr);
+                return;
+            }
+            Some(GameAction::QuickRetry) => {
+                self.retry_held_since.get_or_insert_with(Instant::now);
+                return;
+            }
+            Some(GameAction::ToggleHud) => {
+                self.hud_visible = !self.hud_visible;
+                return;
+            }
+            Some(GameAction::SkipIntro) if self.can_skip(time) => {
+                if self.state == GameState::Ongoing {
+                    self.process_inputs();
+                    music_mgr.seek(self.skip_target.unwrap());
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        if released.is_some_and(|button| button == self.bindings.button(GameAction::QuickRetry)) {
+            self.retry_held_since = None;
+        }
+
+        if self
+            .retry_held_since
+            .is_some_and(|since| since.elapsed() >= QUICK_RETRY_HOLD)
+        {
+            self.retry_held_since = None;
+            self.actions
+                .push_back(SceneAction::Retry(self.map_path.clone()));
+        }
+
+        if self.state != GameState::Ongoing {
+            return;
+        }
+
+        if let Some(action) = pressed.and_then(|b| self.ruleset.action_for(b)) {
+            self.inputs.push(InputKind::Press(action), time);
+        }
+
+        if let Some(action) = released.and_then(|b| self.ruleset.action_for(b)) {
+            self.inputs.push(InputKind::Release(action), time);
+        }
+
+        if e.update_args().is_some() {
+            self.process_inputs();
+            self.ruleset.update(time);
+
+            if is_map_over(&self.ruleset, &self.track, music_mgr) {
+                self.print_results();
+                self.state = GameState::Finished;
+            }
+        }
+    }
+
+    fn next_action(&mut self) -> Option<SceneAction> {
+        self.actions.pop_front()
+    }
+
+    fn cursor_moved(&mut self, position: Vec2d, arrived: Instant, music_mgr: &MusicManager) {
+        if self.state == GameState::Ongoing {
+            self.inputs.push(
+                InputKind::CursorMoved(position),
+                music_mgr.position_at(arrived),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_with_missing_audio_end_after_their_last_object() {
+        let osu = "osu file format v14\n\n[General]\nMode: 1\n\n[Difficulty]\nHPDrainRate:5\n\
+                   CircleSize:4\nOverallDifficulty:5\nApproachRate:5\nSliderMultiplier:1.4\n\
+                   SliderTickRate:1\n\n[TimingPoints]\n0,500,4,2,0,100,1,0\n\n[HitObjects]\n\
+                   256,192,1000,1,0\n256,192,1500,1,8\n";
+        let beatmap = Beatmap::from_bytes(osu.as_bytes()).unwrap();
+        let settings = Settings::default();
+        let skin = Skin::load(&settings.skin);
+        let ctx = RulesetContext {
+            settings: &settings,
+            skin: &skin,
+        };
+
+        let mut music_mgr = MusicManager::new();
+        let track = MapTrack::start(Path::new("missing.mp3"), &beatmap, &mut music_mgr);
+        let mut ruleset = TaikoPlayfield::new(&beatmap, &ctx);
+        assert!(!track.playing);
+        assert!(!is_map_over(&ruleset, &track, &music_mgr));
+
+        ruleset.update(1500.0);
+        assert!(!is_map_over(&ruleset, &track, &music_mgr));
+
+        ruleset.update(2000.0);
+        assert!(is_map_over(&ruleset, &track, &music_mgr));
+    }
+}
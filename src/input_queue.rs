@@ -0,0 +1,79 @@
+use graphics::math::Vec2d;
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputKind<A> {
+    Press(A),
+    Release(A),
+    CursorMoved(Vec2d),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedInput<A> {
+    pub kind: InputKind<A>,
+    // Position of the audio clock when the input arrived, in milliseconds
+    pub time: f64,
+}
+
+// Inputs get stamped the moment they arrive and wait here for the next update, so judging them
+// there still sees when they happened instead of when the update ran
+pub struct InputQueue<A> {
+    inputs: VecDeque<TimedInput<A>>,
+}
+
+impl<A> InputQueue<A> {
+    pub fn new() -> Self {
+        Self {
+            inputs: VecDeque::new(),
+        }
+    }
+
+    // Inputs from different sources can come in after ones that happened later
+    pub fn push(&mut self, kind: InputKind<A>, time: f64) {
+        let index = self.inputs.partition_point(|input| input.time <= time);
+        self.inputs.insert(index, TimedInput { kind, time });
+    }
+
+    // Oldest input first
+    pub fn pop(&mut self) -> Option<TimedInput<A>> {
+        self.inputs.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_come_out_in_the_order_they_happened() {
+        let mut queue = InputQueue::new();
+        // Stamped when they happened within a 16ms frame, a pen sample arriving late
+        queue.push(InputKind::Press(0), 1003.0);
+        queue.push(InputKind::Press(1), 1007.5);
+        queue.push(InputKind::CursorMoved([10.0, 20.0]), 1005.0);
+        queue.push(InputKind::Release(0), 1007.5);
+
+        let inputs = std::iter::from_fn(|| queue.pop()).collect::<Vec<_>>();
+        assert_eq!(
+            inputs,
+            [
+                TimedInput {
+                    kind: InputKind::Press(0),
+                    time: 1003.0
+                },
+                TimedInput {
+                    kind: InputKind::CursorMoved([10.0, 20.0]),
+                    time: 1005.0
+                },
+                TimedInput {
+                    kind: InputKind::Press(1),
+                    time: 1007.5
+                },
+                TimedInput {
+                    kind: InputKind::Release(0),
+                    time: 1007.5
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::settings;
+use piston_window::{Button, Key, MouseButton};
+use rosu_pp::GameMode;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum GameAction {
+    K1,
+    K2,
+    M1,
+    M2,
+    Pause,
+    // Restarts the map once held long enough
+    QuickRetry,
+    SkipIntro,
+    ToggleHud,
+    TogglePerfOverlay,
+    TaikoLeftKat,
+    TaikoLeftDon,
+    TaikoRightDon,
+    TaikoRightKat,
+}
+
+impl GameAction {
+    pub const ALL: [GameAction; 13] = [
+        GameAction::K1,
+        GameAction::K2,
+        GameAction::M1,
+        GameAction::M2,
+        GameAction::Pause,
+        GameAction::QuickRetry,
+        GameAction::SkipIntro,
+        GameAction::ToggleHud,
+        GameAction::TogglePerfOverlay,
+        GameAction::TaikoLeftKat,
+        GameAction::TaikoLeftDon,
+        GameAction::TaikoRightDon,
+        GameAction::TaikoRightKat,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GameAction::K1 => "Left key (K1)",
+            GameAction::K2 => "Right key (K2)",
+            GameAction::M1 => "Left mouse button (M1)",
+            GameAction::M2 => "Right mouse button (M2)",
+            GameAction::Pause => "Pause",
+            GameAction::QuickRetry => "Quick retry (hold)",
+            GameAction::SkipIntro => "Skip intro",
+            GameAction::ToggleHud => "Toggle HUD",
+            GameAction::TogglePerfOverlay => "Toggle performance overlay",
+            GameAction::TaikoLeftKat => "Taiko left rim (kat)",
+            GameAction::TaikoLeftDon => "Taiko left centre (don)",
+            GameAction::TaikoRightDon => "Taiko right centre (don)",
+            GameAction::TaikoRightKat => "Taiko right rim (kat)",
+        }
+    }
+
+    // Ruleset the action is played in, None for the game screen's own that work in all of them
+    pub fn ruleset(self) -> Option<GameMode> {
+        match self {
+            GameAction::K1 | GameAction::K2 | GameAction::M1 | GameAction::M2 => {
+                Some(GameMode::Osu)
+            }
+            GameAction::TaikoLeftKat
+            | GameAction::TaikoLeftDon
+            | GameAction::TaikoRightDon
+            | GameAction::TaikoRightKat => Some(GameMode::Taiko),
+            _ => None,
+        }
+    }
+
+    // Whether the two can be pressed in the same map, so can't share a button
+    fn shares_scope(self, other: GameAction) -> bool {
+        match (self.ruleset(), other.ruleset()) {
+            (Some(mode), Some(other_mode)) => mode == other_mode,
+            _ => true,
+        }
+    }
+
+    // Name in the settings file, saved as `KeyQuickRetry = Backquote`
+    fn setting_name(self) -> String {
+        format!("Key{:?}", self)
+    }
+
+    fn default_button(self) -> Button {
+        match self {
+            GameAction::K1 => Button::Keyboard(Key::Z),
+            GameAction::K2 => Button::Keyboard(Key::X),
+            GameAction::M1 => Button::Mouse(MouseButton::Left),
+            GameAction::M2 => Button::Mouse(MouseButton::Right),
+            GameAction::Pause => Button::Keyboard(Key::Escape),
+            GameAction::QuickRetry => Button::Keyboard(Key::Backquote),
+            GameAction::SkipIntro => Button::Keyboard(Key::Space),
+            GameAction::ToggleHud => Button::Keyboard(Key::Tab),
+            GameAction::TogglePerfOverlay => Button::Keyboard(Key::F11),
+            GameAction::TaikoLeftKat => Button::Keyboard(Key::D),
+            GameAction::TaikoLeftDon => Button::Keyboard(Key::F),
+            GameAction::TaikoRightDon => Button::Keyboard(Key::J),
+            GameAction::TaikoRightKat => Button::Keyboard(Key::K),
+        }
+    }
+}
+
+// Every action is bound to exactly one button. Rulesets can reuse each other's buttons, but no
+// button is bound twice within one, counting the game screen's actions
+#[derive(Clone)]
+pub struct KeyBindings {
+    buttons: HashMap<GameAction, Button>,
+}
+
+impl KeyBindings {
+    pub fn load(values: &HashMap<&str, &str>) -> Self {
+        let mut bindings = Self::default();
+
+        for action in GameAction::ALL {
+            let Some(value) = values.get(action.setting_name().as_str()) else {
+                continue;
+            };
+
+            match parse_button(value) {
+                Some(button) => {
+                    for swapped in bindings.bind(action, button) {
+                        println!(
+                            "{} was also bound to {:?}, moved it to {}",
+                            value,
+                            swapped,
+                            button_name(bindings.button(swapped))
+                        );
+                    }
+                }
+                None => println!("Ignoring invalid binding \"{}\" for {:?}", value, action),
+            }
+        }
+
+        bindings
+    }
+
+    pub fn save(&self) -> String {
+        GameAction::ALL
+            .iter()
+            .map(|action| {
+                format!(
+                    "{} = {}\n",
+                    action.setting_name(),
+                    button_name(self.button(*action))
+                )
+            })
+            .collect()
+    }
+
+    pub fn button(&self, action: GameAction) -> Button {
+        self.buttons[&action]
+    }
+
+    // The action the button does while playing the given ruleset
+    pub fn action_for(&self, button: Button, mode: GameMode) -> Option<GameAction> {
+        GameAction::ALL.into_iter().find(|action| {
+            self.buttons[action] == button && action.ruleset().is_none_or(|m| m == mode)
+        })
+    }
+
+    // Actions already using the button where they would clash get the one it replaces, and so on
+    // for whatever those then clash with, so nothing is ever bound twice. Returns the ones moved
+    pub fn bind(&mut self, action: GameAction, button: Button) -> Vec<GameAction> {
+        let Some(previous) = self.buttons.insert(action, button) else {
+            return Vec::new();
+        };
+
+        let mut moved = Vec::new();
+        let mut pending = vec![action];
+        while let Some(bound) = pending.pop() {
+            let taken = self.buttons[&bound];
+            let freed = if taken == button { previous } else { button };
+
+            for other in GameAction::ALL {
+                if other != action
+                    && !moved.contains(&other)
+                    && bound.shares_scope(other)
+                    && self.buttons[&other] == taken
+                {
+                    self.buttons.insert(other, freed);
+                    moved.push(other);
+                    pending.push(other);
+                }
+            }
+        }
+
+        moved
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            buttons: GameAction::ALL
+                .into_iter()
+                .map(|action| (action, action.default_button()))
+                .collect(),
+        }
+    }
+}
+
+pub fn button_name(button: Button) -> String {
+    match button {
+        Button::Keyboard(key) => format!("{:?}", key),
+        Button::Mouse(mouse_button) => format!("Mouse{:?}", mouse_button),
+        button => format!("{:?}", button),
+    }
+}
+
+// Mouse buttons are prefixed so they don't clash with the arrow keys
+pub fn parse_button(name: &str) -> Option<Button> {
+    match name.strip_prefix("Mouse") {
+        Some(mouse_button) => [
+            MouseButton::Left,
+            MouseButton::Right,
+            MouseButton::Middle,
+            MouseButton::X1,
+            MouseButton::X2,
+        ]
+        .into_iter()
+        .find(|button| format!("{:?}", button) == mouse_button)
+        .map(Button::Mouse),
+        None => settings::parse_key(name).map(Button::Keyboard),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bindings_only_clash_within_a_ruleset() {
+        let mut bindings = KeyBindings::default();
+        let [z, x] = [Button::Keyboard(Key::Z), Button::Keyboard(Key::X)];
+
+        // Taiko can use osu!'s keys, which stay where they are
+        assert_eq!(bindings.bind(GameAction::TaikoLeftDon, z), []);
+        assert_eq!(bindings.bind(GameAction::TaikoRightDon, x), []);
+        assert_eq!(bindings.button(GameAction::K1), z);
+        assert_eq!(bindings.button(GameAction::K2), x);
+        assert_eq!(bindings.action_for(z, GameMode::Osu), Some(GameAction::K1));
+        assert_eq!(
+            bindings.action_for(z, GameMode::Taiko),
+            Some(GameAction::TaikoLeftDon)
+        );
+        assert_eq!(bindings.action_for(z, GameMode::Mania), None);
+
+        // Within a ruleset they swap
+        assert_eq!(
+            bindings.bind(GameAction::TaikoLeftDon, x),
+            [GameAction::TaikoRightDon]
+        );
+        assert_eq!(bindings.button(GameAction::TaikoRightDon), z);
+    }
+
+    #[test]
+    fn game_screen_bindings_clash_with_every_ruleset() {
+        let mut bindings = KeyBindings::default();
+        let [z, escape] = [Button::Keyboard(Key::Z), Button::Keyboard(Key::Escape)];
+        bindings.bind(GameAction::TaikoLeftDon, z);
+
+        assert_eq!(
+            bindings.bind(GameAction::Pause, z),
+            [GameAction::K1, GameAction::TaikoLeftDon]
+        );
+        assert_eq!(bindings.button(GameAction::K1), escape);
+        assert_eq!(bindings.button(GameAction::TaikoLeftDon), escape);
+
+        // Taking it back moves pause to Z again, which pushes taiko off it in turn
+        assert_eq!(
+            bindings.bind(GameAction::K1, z),
+            [GameAction::Pause, GameAction::TaikoLeftDon]
+        );
+        assert_eq!(bindings.button(GameAction::Pause), escape);
+        assert_eq!(bindings.button(GameAction::TaikoLeftDon), z);
+    }
+}
@@ -0,0 +1,73 @@
+use rand::Rng;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SONGS_DIRECTORY: &str = "Songs";
+
+// A folder of difficulties sharing the same song, like in osu!'s Songs directory
+pub struct BeatmapSet {
+    pub maps: Vec<PathBuf>,
+}
+
+pub struct Library {
+    sets: Vec<BeatmapSet>,
+}
+
+impl Library {
+    pub fn scan<P: AsRef<Path>>(songs_directory: P) -> Self {
+        let mut sets = Vec::new();
+
+        let Ok(entries) = fs::read_dir(songs_directory.as_ref()) else {
+            println!("No songs directory found at {:?}", songs_directory.as_ref());
+            return Self { sets };
+        };
+
+        for entry in entries.flatten() {
+            let Ok(files) = fs::read_dir(entry.path()) else {
+                continue;
+            };
+
+            let mut maps = files
+                .flatten()
+                .map(|file| file.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "osu"))
+                .collect::<Vec<_>>();
+
+            if !maps.is_empty() {
+                maps.sort();
+                sets.push(BeatmapSet { maps });
+            }
+        }
+
+        sets.sort_by(|a, b| a.maps[0].cmp(&b.maps[0]));
+        println!("Found {} beatmap sets", sets.len());
+
+        Self { sets }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    pub fn set(&self, index: usize) -> &BeatmapSet {
+        &self.sets[index]
+    }
+
+    // Avoids picking the same set twice in a row when there's a choice
+    pub fn random_set_index(&self, current: Option<usize>) -> Option<usize> {
+        match self.sets.len() {
+            0 => None,
+            1 => Some(0),
+            len => {
+                let mut rng = rand::thread_rng();
+
+                loop {
+                    let index = rng.gen_range(0..len);
+                    if Some(index) != current {
+                        return Some(index);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,49 +1,162 @@
-use animations::AnimationsManager;
+use animations::{AnimationsManager, WallClock};
+use cursor::Cursor;
+use cursor_input::{CursorInput, InputEvent, InputScript};
+use display::Display;
+use easings::Easings;
 use fps_counter::FPSCounter;
+use game::SceneAction;
+use key_bindings::GameAction;
+use library::Library;
 use music_manager::MusicManager;
+use perf_overlay::{PerfOverlay, PerfStats};
 use piston::WindowSettings;
 use piston_window::*;
-use std::time::Duration;
+use raw_input::RawInput;
+use rosu_pp::GameMode;
+use settings::Settings;
+use skin::Skin;
+use std::time::Instant;
+use text::Fonts;
+use viewport::Viewport;
 mod menu;
 
-use crate::animations::{Animation, AnimationType, EasingType};
 use menu::main_menu::MainMenu;
+use menu::options::OptionsMenu;
+use menu::MenuAction;
 
+mod animatable;
 mod animations;
+mod beatmap;
+mod catch;
+mod clipboard;
+mod cursor;
+mod cursor_input;
+mod display;
+mod easings;
+mod game;
+mod input_queue;
+mod key_bindings;
+mod library;
+mod mania;
 mod music_manager;
-
-const INITIAL_WIDTH: f64 = 1280.0;
-const INITIAL_HEIGHT: f64 = 720.0;
+mod osu;
+mod perf_overlay;
+mod raw_input;
+mod ruleset;
+mod settings;
+mod skin;
+mod slider_path;
+mod taiko;
+mod text;
+mod video;
+mod viewport;
 
 fn main() {
-    let window_size = [INITIAL_WIDTH, INITIAL_HEIGHT];
-    let mut window: PistonWindow = WindowSettings::new("better_osu", window_size)
-        .exit_on_esc(true)
-        .controllers(false)
-        .vsync(false)
-        .build()
-        .unwrap();
-
-    window.set_max_fps(9999);
+    let mut settings = Settings::load();
+
+    let mut window: PistonWindow = WindowSettings::new(
+        "better_osu",
+        [settings.resolution.width, settings.resolution.height],
+    )
+    .exit_on_esc(false)
+    .controllers(false)
+    .vsync(false)
+    .build()
+    .unwrap();
+
+    // Applied again whenever the options change any of it
+    let mut display = Display::from_settings(&settings);
+    display.apply(&mut window, None);
+    let window_size = [window.size().width, window.size().height];
+    // Replaced by our own cursor
+    window.window.window.set_cursor_visible(false);
     //println!("{}", window.get_event_settings().max_fps);
 
     let mut fps_counter = FPSCounter::new();
     let mut fps = 0;
 
+    let skin = Skin::load(&settings.skin);
+    let library = Library::scan(library::SONGS_DIRECTORY);
+
     let mut music_mgr = MusicManager::new();
     music_mgr.set_volume(0.1);
-    music_mgr.play_file("welcome.mp3");
 
+    let easings = Easings::load();
     let mut animations_manager = AnimationsManager::new();
+    animations_manager.set_time_scale(settings.animation_speed);
+    let animation_clock = WallClock::new();
 
-    let mut glyphs = window.load_font("assets/Roboto-Regular.ttf").unwrap();
+    let mut viewport = Viewport::new(window_size, settings.ui_scale);
+    // The UI scale slider would move away from the cursor while it's dragged
+    let mut mouse_held = false;
+    let mut perf_overlay = PerfOverlay::new();
+
+    let mut glyphs = Fonts::load(&mut window);
     let mut tex_ctx = window.create_texture_context();
+    let mut cursor = Cursor::new(&mut tex_ctx, &skin, &settings);
+
+    // Times gameplay presses by when they happened rather than when the window reports them, and
+    // reads tablets
+    let mut raw_input = RawInput::start();
+    let mut focused = true;
+
+    let mut cursor_input = CursorInput::new(
+        settings.input_mode,
+        settings.cursor_sensitivity,
+        settings.tablet_area,
+        settings.confine_cursor,
+        window_size,
+    );
 
-    let in_menu = true;
-    let mut menu = MainMenu::new(&mut tex_ctx);
+    // A beatmap given on the command line is played straight away
+    let mut game = std::env::args()
+        .nth(1)
+        .map(|map_path| game::start(map_path, &mut tex_ctx, &settings, &skin, &mut music_mgr));
+
+    // A second one replays scripted input events, to play without a tablet or mouse
+    let mut input_script = std::env::args().nth(2).and_then(|path| {
+        let script = InputScript::load(&path);
+        if script.is_none() {
+            println!("Could not read input script {}", path);
+        }
+        script
+    });
+
+    if let Some(game) = &mut game {
+        if cursor_input.wants_capture() {
+            window.set_capture_cursor(true);
+        }
+        game.cursor_moved(cursor_input.position(), Instant::now(), &music_mgr);
+    }
+
+    let in_menu = game.is_none();
+    let mut menu = MainMenu::new(&mut tex_ctx, &easings);
+    let mut options: Option<OptionsMenu> = None;
+
+    if in_menu {
+        menu.start_music(&library, &mut music_mgr);
+    }
 
     while let Some(e) = window.next() {
+        let started = Instant::now();
+        let arrived = e
+            .button_args()
+            .and_then(|args| raw_input.as_mut()?.arrival(&args))
+            .unwrap_or(started);
+
+        if e.press_args().is_some() || e.mouse_cursor_args().is_some() {
+            perf_overlay.input();
+        }
+        if e.press_args() == Some(settings.key_bindings.button(GameAction::TogglePerfOverlay)) {
+            perf_overlay.toggle();
+        }
+
+        // The options menu scrolls its own lists
         e.mouse_scroll(|[_horizontal, vertical]| {
+            if options.is_some() {
+                return;
+            }
+
             let mut new_vol = music_mgr.volume();
 
             if vertical.is_sign_positive() {
@@ -57,42 +170,191 @@ fn main() {
         });
 
         e.update(|_| {
-            animations_manager.tick();
+            animations_manager.tick(&animation_clock);
         });
 
-        menu.event(&e, &mut animations_manager);
+        // Nothing is watching the menus while the window is in the background
+        e.focus(|now_focused| {
+            focused = now_focused;
+            animations_manager.set_paused(!focused);
+            if let Some(raw_input) = &mut raw_input {
+                raw_input.clear();
+            }
+        });
+
+        viewport.event(&e);
+        if let Some(Button::Mouse(_)) = e.press_args() {
+            mouse_held = true;
+        }
+        if let Some(Button::Mouse(_)) = e.release_args() {
+            mouse_held = false;
+        }
+        // Menus get mouse positions in their own units
+        let ui_e = viewport.ui_event(&e);
+
+        if let Some(options_menu) = &mut options {
+            options_menu.event(&ui_e, &mut settings, &mut animations_manager);
+            animations_manager.set_time_scale(settings.animation_speed);
+            if !mouse_held {
+                viewport.set_ui_scale(settings.ui_scale);
+
+                let changed = Display::from_settings(&settings);
+                if changed != display {
+                    changed.apply(&mut window, Some(display));
+                    display = changed;
+                }
+            }
+
+            if options_menu.is_closed() {
+                options = None;
+            }
+        } else if in_menu {
+            menu.event(&ui_e, &mut animations_manager, &library, &mut music_mgr);
+
+            while let Some(action) = menu.next_action() {
+                match action {
+                    MenuAction::Options => {
+                        options = Some(OptionsMenu::new(
+                            &easings,
+                            &settings,
+                            display::resolutions(&window),
+                        ))
+                    }
+                    MenuAction::Exit => window.set_should_close(true),
+                    action => println!("{:?} is not available yet", action),
+                }
+            }
+        }
+
+        let mut cursor_moves = InputEvent::from_event(&e)
+            .and_then(|event| cursor_input.feed(event))
+            .map(|position| (position, started))
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        if let Some(script) = &mut input_script {
+            while let Some(event) = script.next_event() {
+                cursor_moves.extend(cursor_input.feed(event).map(|position| (position, started)));
+            }
+        }
+
+        // Pens keep moving over other windows
+        if let Some(raw_input) = &mut raw_input {
+            while let Some((position, at)) = raw_input.next_pen() {
+                if focused {
+                    let moved = cursor_input.feed(InputEvent::Absolute(position));
+                    cursor_moves.extend(moved.map(|position| (position, at)));
+                }
+            }
+        }
+        let cursor_moved = cursor_moves.last().map(|(position, _)| *position);
+
+        // Menus follow the system cursor, gameplay the one from the input layer
+        if let Some(position) = if game.is_some() {
+            cursor_moved
+        } else {
+            e.mouse_cursor_args()
+        } {
+            cursor.move_to(position);
+        }
+
+        let is_click = |button: Button| {
+            matches!(button, Button::Mouse(_))
+                || matches!(
+                    settings.key_bindings.action_for(button, GameMode::Osu),
+                    Some(GameAction::K1 | GameAction::K2)
+                )
+        };
+        if let Some(button) = e.press_args().filter(|b| is_click(*b)) {
+            cursor.press(button);
+        }
+        if let Some(button) = e.release_args().filter(|b| is_click(*b)) {
+            cursor.release(button);
+        }
+
+        if let Some(game) = &mut game {
+            game.event(&e, arrived, &mut music_mgr);
+
+            for (position, at) in &cursor_moves {
+                game.cursor_moved(*position, *at, &music_mgr);
+            }
+
+            while let Some(action) = game.next_action() {
+                match action {
+                    SceneAction::Retry(map_path) => {
+                        *game =
+                            game::start(map_path, &mut tex_ctx, &settings, &skin, &mut music_mgr);
+                        game.cursor_moved(cursor_input.position(), Instant::now(), &music_mgr);
+                    }
+                }
+            }
+
+            if e.render_args().is_some() {
+                game.prepare_render(&mut tex_ctx, &music_mgr);
+            }
+        }
+
+        if e.update_args().is_some() {
+            perf_overlay.updated(started.elapsed());
+        }
 
         window.draw_2d(&e, |c, g, device| {
+            let draw_started = Instant::now();
             fps = fps_counter.tick();
             clear([0.0, 0.0, 0.0, 1.0], g);
             //println!("{}", fps);
+            let ui = viewport.ui_context(c);
 
             if in_menu {
-                menu.render(c, g, &mut glyphs, &mut animations_manager);
+                menu.render(ui, g, &mut glyphs, &mut animations_manager, &music_mgr);
             }
 
-            Text::new_color([1.0, 1.0, 1.0, 1.0], 18)
-                .draw(
-                    &(String::from("Volume : ") + &music_mgr.volume().to_string()),
-                    &mut glyphs,
-                    &c.draw_state,
-                    c.transform.trans(20.0, 30.0),
-                    g,
-                )
-                .unwrap();
-
-            Text::new_color([1.0, 1.0, 1.0, 1.0], 18)
-                .draw(
-                    &format!("FPS: {}", fps),
-                    &mut glyphs,
-                    &c.draw_state,
-                    c.transform.trans(20.0, 60.0),
-                    g,
-                )
-                .unwrap();
+            if let Some(options_menu) = &mut options {
+                options_menu.render(ui, g, &mut glyphs, &settings, &mut animations_manager);
+            }
 
-            // Update glyphs before rendering.
-            glyphs.factory.encoder.flush(device);
+            if let Some(game) = &mut game {
+                game.render(c, g, &mut glyphs, &music_mgr);
+            }
+
+            text::draw_sharp(
+                &mut glyphs,
+                ui,
+                g,
+                &(String::from("Volume : ") + &music_mgr.volume().to_string()),
+                18,
+                [1.0, 1.0, 1.0, 1.0],
+                [20.0, 30.0],
+            );
+
+            text::draw_sharp(
+                &mut glyphs,
+                ui,
+                g,
+                &format!("FPS: {}", fps),
+                18,
+                [1.0, 1.0, 1.0, 1.0],
+                [20.0, 60.0],
+            );
+
+            perf_overlay.render(
+                ui,
+                g,
+                &mut glyphs,
+                PerfStats {
+                    animations: animations_manager.count(),
+                    audio_drift: music_mgr.audio_drift(),
+                },
+            );
+
+            cursor.render(c, g);
+
+            // Update glyphs and uploaded textures before rendering.
+            glyphs.flush(device);
+            tex_ctx.encoder.flush(device);
+            perf_overlay.drawn(draw_started.elapsed());
         });
     }
+
+    settings.save();
 }
@@ -0,0 +1,5 @@
+mod objects;
+mod playfield;
+mod score;
+
+pub use playfield::ManiaPlayfield;
@@ -0,0 +1,44 @@
+use crate::settings::MAX_MANIA_KEYS;
+use rosu_pp::parse::HitObjectKind;
+use rosu_pp::{Beatmap, GameMode};
+
+#[derive(Clone, Debug)]
+pub struct ManiaObject {
+    pub column: usize,
+    pub start_time: f64,
+    // Set for hold notes
+    pub end_time: Option<f64>,
+}
+
+// Native mania maps are read as they are, anything else goes through rosu-pp's mania
+// conversion, which picks the key count and turns sliders and spinners into holds
+pub fn convert(map: &Beatmap) -> (usize, Vec<ManiaObject>) {
+    let converted = map.convert_mode(GameMode::Mania);
+    let keys = (converted.cs.round() as usize).clamp(1, MAX_MANIA_KEYS);
+
+    // The column is encoded in the x position, split evenly across the playfield
+    let column_width = 512.0 / keys as f32;
+
+    let objects = converted
+        .hit_objects
+        .iter()
+        .map(|obj| {
+            let column = ((obj.pos.x / column_width).floor().max(0.0) as usize).min(keys - 1);
+
+            let end_time = match obj.kind {
+                HitObjectKind::Hold { end_time } | HitObjectKind::Spinner { end_time } => {
+                    Some(end_time)
+                }
+                _ => None,
+            };
+
+            ManiaObject {
+                column,
+                start_time: obj.start_time,
+                end_time,
+            }
+        })
+        .collect();
+
+    (keys, objects)
+}
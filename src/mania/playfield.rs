@@ -0,0 +1,328 @@
+use super::objects::{self, ManiaObject};
+use super::score::{HitResult, HitWindows, ManiaScore};
+use crate::ruleset::{Ruleset, RulesetContext, ScoreSummary};
+use crate::skin::ManiaSkin;
+use crate::text::Fonts;
+use crate::viewport::{self, SCREEN_SIZE};
+use graphics::{rectangle, Context, Text, Transformed};
+use piston_window::{Button, G2d, Key};
+use rosu_pp::{Beatmap, GameMode};
+
+// Time notes take to scroll the whole screen at scroll speed 1
+const MAX_SCROLL_TIME: f64 = 11485.0;
+// Releasing a hold note is judged more leniently than hitting it
+const RELEASE_LENIENCE: f64 = 1.5;
+const JUDGEMENT_DISPLAY_TIME: f64 = 300.0;
+const NOTE_HEIGHT: f64 = 12.0;
+
+struct Column {
+    objects: Vec<ManiaObject>,
+    // Everything before this has been judged
+    next_object: usize,
+    key: Key,
+    pressed: bool,
+    // The head of the hold note at `next_object` was hit and its tail is still to come
+    holding: bool,
+}
+
+pub struct ManiaPlayfield {
+    columns: Vec<Column>,
+    hit_windows: HitWindows,
+    score: ManiaScore,
+    skin: ManiaSkin,
+    // How long notes are on screen before reaching the judgement line
+    scroll_time: f64,
+    last_judgement: Option<(HitResult, f64)>,
+}
+
+impl Ruleset for ManiaPlayfield {
+    // Index of the column
+    type Action = usize;
+
+    const MODE: GameMode = GameMode::Mania;
+
+    fn new(map: &Beatmap, ctx: &RulesetContext) -> Self {
+        let (keys, objects) = objects::convert(map);
+        println!("Loaded {} mania objects on {}K", objects.len(), keys);
+
+        // Hold notes are judged twice, on press and on release
+        let total_judgements = objects
+            .iter()
+            .map(|object| if object.end_time.is_some() { 2 } else { 1 })
+            .sum();
+
+        let layout = ctx.settings.mania_layout(keys);
+        let mut columns = layout
+            .into_iter()
+            .map(|key| Column {
+                objects: Vec::new(),
+                next_object: 0,
+                key,
+                pressed: false,
+                holding: false,
+            })
+            .collect::<Vec<_>>();
+
+        for object in objects {
+            columns[object.column].objects.push(object);
+        }
+
+        Self {
+            columns,
+            hit_windows: HitWindows::new(map.od as f64, map.mode != GameMode::Mania),
+            score: ManiaScore::new(total_judgements),
+            skin: ctx.skin.mania(keys),
+            scroll_time: MAX_SCROLL_TIME / ctx.settings.mania_scroll_speed as f64,
+            last_judgement: None,
+        }
+    }
+
+    fn action_for(&self, button: Button) -> Option<Self::Action> {
+        match button {
+            Button::Keyboard(key) => self.columns.iter().position(|column| column.key == key),
+            _ => None,
+        }
+    }
+
+    // Key repeat sends presses again while a key is held, only the first one counts
+    fn press(&mut self, column: usize, time: f64) {
+        if self.columns[column].pressed {
+            return;
+        }
+        self.columns[column].pressed = true;
+
+        let Some(object) = self.columns[column]
+            .objects
+            .get(self.columns[column].next_object)
+        else {
+            return;
+        };
+
+        let Some(result) = self.hit_windows.result_for(time - object.start_time) else {
+            return;
+        };
+
+        if object.end_time.is_some() && result != HitResult::Miss {
+            self.columns[column].holding = true;
+            self.judge_hit(result, time);
+        } else {
+            // Missing the head of a hold note loses its tail too
+            if object.end_time.is_some() {
+                self.judge_hit(HitResult::Miss, time);
+            }
+            self.judge(column, result, time);
+        }
+    }
+
+    fn release(&mut self, column: usize, time: f64) {
+        self.columns[column].pressed = false;
+
+        if !self.columns[column].holding {
+            return;
+        }
+
+        let current = &self.columns[column];
+        let Some(end_time) = current.objects[current.next_object].end_time else {
+            return;
+        };
+
+        let result = self
+            .hit_windows
+            .result_for((time - end_time) / RELEASE_LENIENCE)
+            .unwrap_or(HitResult::Miss);
+
+        self.judge(column, result, time);
+    }
+
+    fn update(&mut self, time: f64) {
+        for column in 0..self.columns.len() {
+            while let Some(object) = self.columns[column]
+                .objects
+                .get(self.columns[column].next_object)
+            {
+                match object.end_time {
+                    // Holding all the way through the end counts as a perfect release
+                    Some(end_time) if self.columns[column].holding => {
+                        if time < end_time {
+                            break;
+                        }
+
+                        self.judge(column, HitResult::Max, time);
+                    }
+                    end_time => {
+                        if time - object.start_time <= self.hit_windows.miss_window() {
+                            break;
+                        }
+
+                        if end_time.is_some() {
+                            self.judge_hit(HitResult::Miss, time);
+                        }
+                        self.judge(column, HitResult::Miss, time);
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.columns
+            .iter()
+            .all(|column| column.next_object >= column.objects.len())
+    }
+
+    fn score(&self) -> ScoreSummary {
+        ScoreSummary {
+            score: self.score.score,
+            max_combo: self.score.max_combo,
+            accuracy: self.score.accuracy(),
+        }
+    }
+
+    fn judgement_counts(&self) -> String {
+        let [max, great, good, ok, meh, miss] = self.score.counts;
+        format!(
+            "{} / {} / {} / {} / {} / {}",
+            max, great, good, ok, meh, miss
+        )
+    }
+
+    fn render(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, time: f64) {
+        let [win_width, win_height] = c.get_view_size();
+
+        // Skin sizes are in osu!'s 640x480 screen, horizontally centered on wider windows
+        let [screen_x, _, _, screen_height] = viewport::screen_rect([win_width, win_height]);
+        let scale = screen_height / SCREEN_SIZE[1];
+        let stage_x = screen_x + self.skin.column_start * scale;
+        let hit_y = self.skin.hit_position * scale;
+        let note_height = NOTE_HEIGHT * scale;
+
+        for (i, column) in self.columns.iter().enumerate() {
+            let x = stage_x + self.skin.column_x(i) * scale;
+            let width = self.skin.column_widths[i] * scale;
+            let note_color = self.skin.note_colors[i];
+
+            rectangle(
+                self.skin.column_colors[i],
+                [x, 0.0, width, win_height],
+                c.transform,
+                g,
+            );
+
+            if column.pressed {
+                let light = [note_color[0], note_color[1], note_color[2], 0.3];
+                rectangle(light, [x, hit_y, width, win_height - hit_y], c.transform, g);
+            }
+
+            let y_at = |object_time: f64| hit_y - (object_time - time) / self.scroll_time * hit_y;
+
+            for (j, object) in column.objects[column.next_object..].iter().enumerate() {
+                if object.start_time - time > self.scroll_time {
+                    break;
+                }
+
+                // A held note's head stays on the judgement line until it's released
+                let head_y = if j == 0 && column.holding {
+                    hit_y
+                } else {
+                    y_at(object.start_time)
+                };
+
+                if let Some(end_time) = object.end_time {
+                    let tail_y = y_at(end_time);
+                    let body_color = [note_color[0], note_color[1], note_color[2], 0.6];
+
+                    rectangle(
+                        body_color,
+                        [
+                            x + width * 0.1,
+                            tail_y,
+                            width * 0.8,
+                            (head_y - tail_y).max(0.0),
+                        ],
+                        c.transform,
+                        g,
+                    );
+                    rectangle(
+                        note_color,
+                        [x, tail_y - note_height, width, note_height],
+                        c.transform,
+                        g,
+                    );
+                }
+
+                rectangle(
+                    note_color,
+                    [x, head_y - note_height, width, note_height],
+                    c.transform,
+                    g,
+                );
+            }
+        }
+
+        let stage_width = self.skin.stage_width() * scale;
+        rectangle(
+            [1.0, 1.0, 1.0, 0.8],
+            [stage_x, hit_y, stage_width, 2.0],
+            c.transform,
+            g,
+        );
+
+        if let Some((result, judged_at)) = self.last_judgement {
+            if time - judged_at < JUDGEMENT_DISPLAY_TIME {
+                let (text, color) = match result {
+                    HitResult::Max => ("MAX", [0.8, 0.9, 1.0, 1.0]),
+                    HitResult::Great => ("300", [1.0, 0.85, 0.3, 1.0]),
+                    HitResult::Good => ("200", [0.5, 1.0, 0.5, 1.0]),
+                    HitResult::Ok => ("100", [0.4, 0.7, 1.0, 1.0]),
+                    HitResult::Meh => ("50", [0.7, 0.7, 0.7, 1.0]),
+                    HitResult::Miss => ("MISS", [1.0, 0.2, 0.2, 1.0]),
+                };
+
+                Text::new_color(color, 28)
+                    .draw(
+                        text,
+                        glyphs,
+                        &c.draw_state,
+                        c.transform
+                            .trans(stage_x + stage_width / 2.0 - 24.0, win_height * 0.4),
+                        g,
+                    )
+                    .unwrap();
+            }
+        }
+
+        self.render_combo(c, g, glyphs, stage_x + stage_width / 2.0);
+    }
+}
+
+impl ManiaPlayfield {
+    fn judge_hit(&mut self, result: HitResult, time: f64) {
+        self.score.add_hit(result);
+        self.last_judgement = Some((result, time));
+    }
+
+    // Judges the last part of the current object in the column and moves on to the next one
+    fn judge(&mut self, column: usize, result: HitResult, time: f64) {
+        self.judge_hit(result, time);
+
+        let column = &mut self.columns[column];
+        column.next_object += 1;
+        column.holding = false;
+    }
+
+    fn render_combo(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, stage_center: f64) {
+        let [_, win_height] = c.get_view_size();
+
+        if self.score.combo > 0 {
+            Text::new_color([1.0, 1.0, 1.0, 1.0], 24)
+                .draw(
+                    &self.score.combo.to_string(),
+                    glyphs,
+                    &c.draw_state,
+                    c.transform.trans(stage_center - 12.0, win_height * 0.3),
+                    g,
+                )
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,143 @@
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HitResult {
+    Max,
+    Great,
+    Good,
+    Ok,
+    Meh,
+    Miss,
+}
+
+impl HitResult {
+    fn value(self) -> u64 {
+        match self {
+            HitResult::Max => 320,
+            HitResult::Great => 300,
+            HitResult::Good => 200,
+            HitResult::Ok => 100,
+            HitResult::Meh => 50,
+            HitResult::Miss => 0,
+        }
+    }
+}
+
+pub struct HitWindows {
+    // MAX, 300, 200, 100, 50 and miss, in that order
+    windows: [f64; 6],
+}
+
+impl HitWindows {
+    // osu!stable's windows: converts get fixed ones that only loosen on low OD
+    pub fn new(od: f64, converted: bool) -> Self {
+        let windows = if converted {
+            let (great, good) = if od > 4.0 { (34.0, 67.0) } else { (47.0, 77.0) };
+            [16.0, great, good, 97.0, 121.0, 158.0]
+        } else {
+            [
+                16.0,
+                64.0 - 3.0 * od,
+                97.0 - 3.0 * od,
+                127.0 - 3.0 * od,
+                151.0 - 3.0 * od,
+                188.0 - 3.0 * od,
+            ]
+        };
+
+        Self { windows }
+    }
+
+    // None when the hit is too early to count for anything
+    pub fn result_for(&self, offset: f64) -> Option<HitResult> {
+        let offset = offset.abs();
+        let results = [
+            HitResult::Max,
+            HitResult::Great,
+            HitResult::Good,
+            HitResult::Ok,
+            HitResult::Meh,
+            HitResult::Miss,
+        ];
+
+        self.windows
+            .iter()
+            .zip(results)
+            .find(|(window, _)| offset <= **window)
+            .map(|(_, result)| result)
+    }
+
+    // Past this an object can't be hit anymore
+    pub fn miss_window(&self) -> f64 {
+        self.windows[5]
+    }
+}
+
+const MAX_SCORE: f64 = 1_000_000.0;
+
+// osu!stable's mania ScoreV1: half of the million is split across judgements by their value,
+// the other half is a bonus that drains on bad hits and builds back up on perfect ones
+pub struct ManiaScore {
+    pub score: u64,
+    pub combo: u32,
+    pub max_combo: u32,
+    // Count of each result, in the same order as the hit windows
+    pub counts: [u32; 6],
+    total_judgements: u32,
+    bonus: f64,
+    base_score: f64,
+    bonus_score: f64,
+}
+
+impl ManiaScore {
+    pub fn new(total_judgements: u32) -> Self {
+        Self {
+            score: 0,
+            combo: 0,
+            max_combo: 0,
+            counts: [0; 6],
+            total_judgements: total_judgements.max(1),
+            bonus: 100.0,
+            base_score: 0.0,
+            bonus_score: 0.0,
+        }
+    }
+
+    pub fn add_hit(&mut self, result: HitResult) {
+        let (index, bonus_value, bonus_change) = match result {
+            HitResult::Max => (0, 32.0, 2.0),
+            HitResult::Great => (1, 32.0, 1.0),
+            HitResult::Good => (2, 16.0, -8.0),
+            HitResult::Ok => (3, 8.0, -24.0),
+            HitResult::Meh => (4, 4.0, -44.0),
+            HitResult::Miss => (5, 0.0, -100.0),
+        };
+
+        self.counts[index] += 1;
+
+        if result == HitResult::Miss {
+            self.combo = 0;
+        } else {
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+        }
+
+        self.bonus = (self.bonus + bonus_change).clamp(0.0, 100.0);
+
+        let per_judgement = MAX_SCORE * 0.5 / self.total_judgements as f64;
+        self.base_score += per_judgement * result.value() as f64 / 320.0;
+        self.bonus_score += per_judgement * bonus_value * self.bonus.sqrt() / 320.0;
+
+        self.score = (self.base_score + self.bonus_score).round() as u64;
+    }
+
+    // MAX and 300 are worth the same towards accuracy
+    pub fn accuracy(&self) -> f64 {
+        let [max, great, good, ok, meh, miss] = self.counts.map(|count| count as f64);
+        let total = max + great + good + ok + meh + miss;
+
+        if total == 0.0 {
+            1.0
+        } else {
+            (300.0 * (max + great) + 200.0 * good + 100.0 * ok + 50.0 * meh) / (300.0 * total)
+        }
+    }
+}
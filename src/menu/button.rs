@@ -1,14 +1,41 @@
+use graphics::math::{Matrix2d, Vec2d};
 use piston::{input, GenericEvent};
 use piston_window::MouseButton;
 use std::collections::VecDeque;
 
+#[derive(Clone, Debug, PartialEq)]
 pub(crate) enum Layout {
-    Rectangle { x: f64, y: f64, dx: f64, dy: f64 },
-    Circle { x: f64, y: f64, radius: f64 },
+    Rectangle {
+        x: f64,
+        y: f64,
+        dx: f64,
+        dy: f64,
+    },
+    Circle {
+        x: f64,
+        y: f64,
+        radius: f64,
+    },
+    RoundedRectangle {
+        x: f64,
+        y: f64,
+        dx: f64,
+        dy: f64,
+        radius: f64,
+    },
+    // Corners in order, either way around
+    Polygon {
+        points: Vec<Vec2d>,
+    },
+    // Another layout drawn with a transform, like a context's but without the view part
+    Transformed {
+        layout: Box<Layout>,
+        transform: Matrix2d,
+    },
 }
 
 impl Layout {
-    fn mouse_inside(&self, [mouse_x, mouse_y]: [f64; 2]) -> bool {
+    pub fn mouse_inside(&self, [mouse_x, mouse_y]: [f64; 2]) -> bool {
         match self {
             Layout::Rectangle { x, y, dx, dy } => {
                 mouse_x >= *x && mouse_x <= *x + *dx && mouse_y >= *y && mouse_y <= *y + *dy
@@ -16,10 +43,75 @@ impl Layout {
             Layout::Circle { x, y, radius } => {
                 ((x - mouse_x).powi(2) + (y - mouse_y).powi(2)).sqrt() <= *radius
             }
+            Layout::RoundedRectangle {
+                x,
+                y,
+                dx,
+                dy,
+                radius,
+            } => {
+                // Distance to the rectangle shrunk by the radius, which is zero along the edges
+                let radius = radius.min(dx / 2.0).min(dy / 2.0).max(0.0);
+                let nearest_x = mouse_x.clamp(x + radius, x + dx - radius);
+                let nearest_y = mouse_y.clamp(y + radius, y + dy - radius);
+                (mouse_x - nearest_x).powi(2) + (mouse_y - nearest_y).powi(2) <= radius.powi(2)
+            }
+            Layout::Polygon { points } => {
+                // Even-odd rule, counting edges crossed by a ray going right
+                let mut inside = false;
+                for (i, [x1, y1]) in points.iter().copied().enumerate() {
+                    let [x2, y2] = points[(i + 1) % points.len()];
+                    if (y1 > mouse_y) != (y2 > mouse_y)
+                        && mouse_x < x1 + (mouse_y - y1) / (y2 - y1) * (x2 - x1)
+                    {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+            Layout::Transformed { layout, transform } => {
+                invert_position(*transform, [mouse_x, mouse_y])
+                    .is_some_and(|position| layout.mouse_inside(position))
+            }
         }
     }
 }
 
+// Where a transformed position came from, none for transforms squashing everything flat
+fn invert_position([[a, b, c], [d, e, f]]: Matrix2d, [x, y]: Vec2d) -> Option<Vec2d> {
+    let determinant = a * e - b * d;
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+
+    let (x, y) = (x - c, y - f);
+    Some([(e * x - b * y) / determinant, (a * y - d * x) / determinant])
+}
+
+// Hit regions of everything taking the mouse, added back to front as they get drawn. Buttons
+// registered here only see the cursor where nothing added after them covers it
+#[derive(Default)]
+pub(crate) struct HitTest {
+    regions: Vec<Layout>,
+}
+
+impl HitTest {
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    pub fn add(&mut self, layout: Layout) -> usize {
+        self.regions.push(layout);
+        self.regions.len() - 1
+    }
+
+    pub fn topmost(&self, position: Vec2d) -> Option<usize> {
+        self.regions
+            .iter()
+            .rposition(|layout| layout.mouse_inside(position))
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum ButtonEvent {
     Click,
@@ -44,6 +136,10 @@ pub(crate) struct Button {
     pressed: bool,
     toggleable: bool,
     toggle_state: bool,
+    // Has keyboard focus, Enter and Space activate it
+    focused: bool,
+    // Where it is in a hit test
+    region: Option<usize>,
 }
 
 impl Button {
@@ -55,13 +151,30 @@ impl Button {
             pressed: false,
             toggleable,
             toggle_state: false,
+            focused: false,
+            region: None,
         }
     }
 
     pub fn event<E: GenericEvent>(&mut self, layout: &Layout, e: &E) {
-        if let Some(pos) = e.mouse_cursor_args() {
-            let inside = layout.mouse_inside(pos);
+        self.handle_event(e.mouse_cursor_args().map(|pos| layout.mouse_inside(pos)), e);
+    }
+
+    // Adds the button to a hit test, none takes it out of this one
+    pub fn set_region(&mut self, hit_test: &mut HitTest, layout: Option<Layout>) {
+        self.region = layout.map(|layout| hit_test.add(layout));
+    }
+
+    // Like `event`, but only on top of everything else in the hit test
+    pub fn event_in<E: GenericEvent>(&mut self, hit_test: &HitTest, e: &E) {
+        let inside = e
+            .mouse_cursor_args()
+            .map(|pos| self.region.is_some() && hit_test.topmost(pos) == self.region);
+        self.handle_event(inside, e);
+    }
 
+    fn handle_event<E: GenericEvent>(&mut self, cursor_inside: Option<bool>, e: &E) {
+        if let Some(inside) = cursor_inside {
             if inside {
                 if !self.cursor_inside {
                     self.cursor_inside = true;
@@ -83,15 +196,9 @@ impl Button {
 
         if let Some(input::Button::Mouse(MouseButton::Left)) = e.release_args() {
             self.pressed = false;
-            if self.cursor_inside {
-                if self.toggleable {
-                    self.toggle_state = !self.toggle_state;
-                    println!("Toggle state: {}", self.toggle_state);
-                    self.events
-                        .push_back(ButtonEvent::Toggle(self.toggle_state));
-                } else {
-                    self.events.push_back(ButtonEvent::Click);
-                }
+            // Only presses that started on the button count, not ones that were dragged onto it
+            if self.cursor_inside && self.was_inside {
+                self.activate();
             } else if self.was_inside {
                 self.events.push_back(ButtonEvent::Cancel);
             }
@@ -107,7 +214,178 @@ impl Button {
         }
     }
 
+    // Same events as a click, for the keyboard
+    pub fn activate(&mut self) {
+        if self.toggleable {
+            self.toggle_state = !self.toggle_state;
+            println!("Toggle state: {}", self.toggle_state);
+            self.events
+                .push_back(ButtonEvent::Toggle(self.toggle_state));
+        } else {
+            self.events.push_back(ButtonEvent::Click);
+        }
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    pub fn toggle_state(&self) -> bool {
+        self.toggle_state
+    }
+
+    pub fn set_toggle_state(&mut self, toggle_state: bool) {
+        self.toggle_state = toggle_state;
+    }
+
     pub fn next_event(&mut self) -> Option<ButtonEvent> {
         self.events.pop_front()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::Transformed;
+    use piston::input::{ButtonArgs, ButtonState, Event, Input, Motion};
+
+    fn move_to(position: Vec2d) -> Event {
+        Event::Input(Input::Move(Motion::MouseCursor(position)), None)
+    }
+
+    fn mouse(state: ButtonState) -> Event {
+        Event::Input(
+            Input::Button(ButtonArgs {
+                state,
+                button: input::Button::Mouse(MouseButton::Left),
+                scancode: None,
+            }),
+            None,
+        )
+    }
+
+    #[test]
+    fn rounded_rectangles_miss_their_corners() {
+        let layout = Layout::RoundedRectangle {
+            x: 0.0,
+            y: 0.0,
+            dx: 100.0,
+            dy: 40.0,
+            radius: 10.0,
+        };
+
+        assert!(layout.mouse_inside([50.0, 20.0]));
+        assert!(layout.mouse_inside([0.0, 20.0]));
+        assert!(layout.mouse_inside([97.0, 37.0]));
+        assert!(!layout.mouse_inside([1.0, 1.0]));
+        assert!(!layout.mouse_inside([99.0, 39.0]));
+        assert!(!layout.mouse_inside([101.0, 20.0]));
+    }
+
+    #[test]
+    fn polygons_use_their_edges() {
+        // Leaning like the menu buttons
+        let layout = Layout::Polygon {
+            points: vec![[20.0, 0.0], [120.0, 0.0], [100.0, 80.0], [0.0, 80.0]],
+        };
+
+        assert!(layout.mouse_inside([60.0, 40.0]));
+        assert!(layout.mouse_inside([5.0, 75.0]));
+        assert!(layout.mouse_inside([115.0, 5.0]));
+        assert!(!layout.mouse_inside([5.0, 5.0]));
+        assert!(!layout.mouse_inside([115.0, 75.0]));
+
+        // Concave ones too
+        let layout = Layout::Polygon {
+            points: vec![
+                [0.0, 0.0],
+                [30.0, 0.0],
+                [30.0, 30.0],
+                [20.0, 30.0],
+                [20.0, 10.0],
+                [10.0, 10.0],
+                [10.0, 30.0],
+                [0.0, 30.0],
+            ],
+        };
+        assert!(layout.mouse_inside([5.0, 20.0]));
+        assert!(!layout.mouse_inside([15.0, 20.0]));
+    }
+
+    #[test]
+    fn transformed_layouts_follow_rotation_and_scale() {
+        let layout = Layout::Transformed {
+            layout: Box::new(Layout::Rectangle {
+                x: 0.0,
+                y: 0.0,
+                dx: 10.0,
+                dy: 2.0,
+            }),
+            transform: graphics::math::identity()
+                .trans(100.0, 100.0)
+                .rot_deg(90.0)
+                .scale(2.0, 2.0),
+        };
+
+        // Now pointing down from (100, 100), 20 long and 4 wide
+        assert!(layout.mouse_inside([98.0, 110.0]));
+        assert!(layout.mouse_inside([97.0, 119.0]));
+        assert!(!layout.mouse_inside([110.0, 101.0]));
+        assert!(!layout.mouse_inside([98.0, 121.0]));
+
+        let flat = Layout::Transformed {
+            layout: Box::new(Layout::Circle {
+                x: 0.0,
+                y: 0.0,
+                radius: 5.0,
+            }),
+            transform: graphics::math::identity().scale(0.0, 1.0),
+        };
+        assert!(!flat.mouse_inside([0.0, 0.0]));
+    }
+
+    #[test]
+    fn only_the_topmost_button_gets_the_cursor() {
+        let mut hit_test = HitTest::default();
+        let mut below = Button::new(false);
+        let mut above = Button::new(false);
+        let mut hidden = Button::new(false);
+
+        let square = |x| Layout::Rectangle {
+            x,
+            y: 0.0,
+            dx: 20.0,
+            dy: 20.0,
+        };
+        below.set_region(&mut hit_test, Some(square(0.0)));
+        above.set_region(&mut hit_test, Some(square(10.0)));
+        hidden.set_region(&mut hit_test, None);
+        assert_eq!(hit_test.topmost([15.0, 5.0]), Some(1));
+        assert_eq!(hit_test.topmost([5.0, 5.0]), Some(0));
+        assert_eq!(hit_test.topmost([50.0, 5.0]), None);
+
+        for e in [
+            move_to([15.0, 5.0]),
+            mouse(ButtonState::Press),
+            mouse(ButtonState::Release),
+        ] {
+            for button in [&mut below, &mut above, &mut hidden] {
+                button.event_in(&hit_test, &e);
+            }
+        }
+
+        assert_eq!(below.next_event(), None);
+        assert_eq!(hidden.next_event(), None);
+        assert_eq!(above.next_event(), Some(ButtonEvent::MouseEnter));
+        assert_eq!(above.next_event(), Some(ButtonEvent::Press));
+        assert_eq!(above.next_event(), Some(ButtonEvent::Click));
+
+        // Uncovered part of the one below
+        below.event_in(&hit_test, &move_to([5.0, 5.0]));
+        assert_eq!(below.next_event(), Some(ButtonEvent::MouseEnter));
+    }
+}
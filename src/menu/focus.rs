@@ -0,0 +1,245 @@
+use super::button::{Button, Layout};
+use graphics::types::Color;
+use graphics::{Context, Ellipse, Line, Rectangle, Transformed};
+use piston::input;
+use piston_window::{G2d, GenericEvent, Key};
+
+const FOCUS_RING_COLOR: Color = [1.0, 0.85, 0.3, 0.9];
+const FOCUS_RING_WIDTH: f64 = 1.5;
+// Between the ring and what it surrounds
+const FOCUS_RING_GAP: f64 = 3.0;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Navigation {
+    Next,
+    Previous,
+    Up,
+    Down,
+    Left,
+    Right,
+    Activate,
+    Back,
+}
+
+// Turns key presses into navigation, remembering shift for Shift-Tab
+pub(crate) struct KeyboardNavigation {
+    shift_held: bool,
+}
+
+impl KeyboardNavigation {
+    pub fn new() -> Self {
+        Self { shift_held: false }
+    }
+
+    pub fn event<E: GenericEvent>(&mut self, e: &E) -> Option<Navigation> {
+        if let Some(input::Button::Keyboard(Key::LShift | Key::RShift)) = e.release_args() {
+            self.shift_held = false;
+        }
+
+        let input::Button::Keyboard(key) = e.press_args()? else {
+            return None;
+        };
+
+        match key {
+            Key::LShift | Key::RShift => {
+                self.shift_held = true;
+                None
+            }
+            Key::Tab if self.shift_held => Some(Navigation::Previous),
+            Key::Tab => Some(Navigation::Next),
+            Key::Up => Some(Navigation::Up),
+            Key::Down => Some(Navigation::Down),
+            Key::Left => Some(Navigation::Left),
+            Key::Right => Some(Navigation::Right),
+            Key::Return | Key::NumPadEnter | Key::Space => Some(Navigation::Activate),
+            Key::Escape => Some(Navigation::Back),
+            _ => None,
+        }
+    }
+}
+
+// Moves focus to the next or previous button, wrapping around. With nothing focused yet it starts
+// from the first or last one
+pub(crate) fn move_focus(buttons: &mut [&mut Button], backwards: bool) {
+    if buttons.is_empty() {
+        return;
+    }
+
+    let count = buttons.len();
+    let next = match buttons.iter().position(|button| button.is_focused()) {
+        Some(focused) if backwards => (focused + count - 1) % count,
+        Some(focused) => (focused + 1) % count,
+        None if backwards => count - 1,
+        None => 0,
+    };
+
+    for (i, button) in buttons.iter_mut().enumerate() {
+        button.set_focused(i == next);
+    }
+}
+
+pub(crate) fn activate_focused(buttons: &mut [&mut Button]) {
+    if let Some(button) = buttons.iter_mut().find(|button| button.is_focused()) {
+        button.activate();
+    }
+}
+
+pub(crate) fn clear_focus(buttons: &mut [&mut Button]) {
+    for button in buttons {
+        button.set_focused(false);
+    }
+}
+
+pub(crate) fn is_mouse_press<E: GenericEvent>(e: &E) -> bool {
+    matches!(e.press_args(), Some(input::Button::Mouse(_)))
+}
+
+pub(crate) fn draw_focus_ring(c: Context, g: &mut G2d, layout: &Layout) {
+    match layout {
+        Layout::Rectangle { x, y, dx, dy } => {
+            Rectangle::new_border(FOCUS_RING_COLOR, FOCUS_RING_WIDTH).draw(
+                grow([*x, *y, *dx, *dy]),
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+        Layout::RoundedRectangle {
+            x,
+            y,
+            dx,
+            dy,
+            radius,
+        } => {
+            Rectangle::new_round_border(
+                FOCUS_RING_COLOR,
+                radius + FOCUS_RING_GAP,
+                FOCUS_RING_WIDTH,
+            )
+            .draw(grow([*x, *y, *dx, *dy]), &c.draw_state, c.transform, g);
+        }
+        Layout::Circle { x, y, radius } => {
+            let radius = radius + FOCUS_RING_GAP;
+            Ellipse::new_border(FOCUS_RING_COLOR, FOCUS_RING_WIDTH).draw(
+                [x - radius, y - radius, radius * 2.0, radius * 2.0],
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+        // Right on the edges, polygons don't grow as simply
+        Layout::Polygon { points } => {
+            for (i, [x1, y1]) in points.iter().copied().enumerate() {
+                let [x2, y2] = points[(i + 1) % points.len()];
+                Line::new(FOCUS_RING_COLOR, FOCUS_RING_WIDTH / 2.0).draw(
+                    [x1, y1, x2, y2],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+            }
+        }
+        Layout::Transformed { layout, transform } => {
+            draw_focus_ring(c.append_transform(*transform), g, layout);
+        }
+    }
+}
+
+fn grow([x, y, dx, dy]: [f64; 4]) -> [f64; 4] {
+    [
+        x - FOCUS_RING_GAP,
+        y - FOCUS_RING_GAP,
+        dx + FOCUS_RING_GAP * 2.0,
+        dy + FOCUS_RING_GAP * 2.0,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::menu::button::ButtonEvent;
+    use piston::input::{ButtonArgs, ButtonState, Event, Input};
+
+    fn key_event(key: Key, state: ButtonState) -> Event {
+        Event::Input(
+            Input::Button(ButtonArgs {
+                state,
+                button: input::Button::Keyboard(key),
+                scancode: None,
+            }),
+            None,
+        )
+    }
+
+    fn focused(buttons: &[&mut Button]) -> Option<usize> {
+        buttons.iter().position(|button| button.is_focused())
+    }
+
+    #[test]
+    fn focus_wraps_around_both_ways() {
+        let mut owned = [Button::new(false), Button::new(false), Button::new(false)];
+        let mut buttons = owned.iter_mut().collect::<Vec<_>>();
+
+        move_focus(&mut buttons, true);
+        assert_eq!(focused(&buttons), Some(2));
+        move_focus(&mut buttons, false);
+        assert_eq!(focused(&buttons), Some(0));
+        move_focus(&mut buttons, true);
+        assert_eq!(focused(&buttons), Some(2));
+
+        clear_focus(&mut buttons);
+        move_focus(&mut buttons, false);
+        assert_eq!(focused(&buttons), Some(0));
+    }
+
+    #[test]
+    fn activation_matches_a_click() {
+        let mut owned = [Button::new(false), Button::new(true)];
+        let mut buttons = owned.iter_mut().collect::<Vec<_>>();
+
+        // Nothing focused, nothing happens
+        activate_focused(&mut buttons);
+        move_focus(&mut buttons, false);
+        activate_focused(&mut buttons);
+        move_focus(&mut buttons, false);
+        activate_focused(&mut buttons);
+        activate_focused(&mut buttons);
+
+        assert_eq!(owned[0].next_event(), Some(ButtonEvent::Click));
+        assert_eq!(owned[0].next_event(), None);
+        assert_eq!(owned[1].next_event(), Some(ButtonEvent::Toggle(true)));
+        assert_eq!(owned[1].next_event(), Some(ButtonEvent::Toggle(false)));
+    }
+
+    #[test]
+    fn shift_tab_goes_backwards() {
+        let mut navigation = KeyboardNavigation::new();
+
+        assert_eq!(
+            navigation.event(&key_event(Key::Tab, ButtonState::Press)),
+            Some(Navigation::Next)
+        );
+        assert_eq!(
+            navigation.event(&key_event(Key::LShift, ButtonState::Press)),
+            None
+        );
+        assert_eq!(
+            navigation.event(&key_event(Key::Tab, ButtonState::Press)),
+            Some(Navigation::Previous)
+        );
+        navigation.event(&key_event(Key::LShift, ButtonState::Release));
+        assert_eq!(
+            navigation.event(&key_event(Key::Tab, ButtonState::Press)),
+            Some(Navigation::Next)
+        );
+        // Releases and other keys aren't navigation
+        assert_eq!(
+            navigation.event(&key_event(Key::Return, ButtonState::Release)),
+            None
+        );
+        assert_eq!(
+            navigation.event(&key_event(Key::A, ButtonState::Press)),
+            None
+        );
+    }
+}
@@ -1,16 +1,26 @@
+use super::button::Button;
+use super::focus::{self, KeyboardNavigation, Navigation};
+use super::menu_button::MenuAction;
 use super::middle_menu_bar::MiddleMenuBar;
+use super::now_playing::NowPlaying;
 use crate::animations::AnimationsManager;
+use crate::easings::Easings;
+use crate::library::Library;
+use crate::music_manager::MusicManager;
+use crate::text::Fonts;
 use graphics::{image, Context};
 use piston_window::*;
 
 pub struct MainMenu {
     background_tex: G2dTexture,
     middle_menu_bar: MiddleMenuBar,
+    now_playing: NowPlaying,
     last_mouse_coords: [f64; 2],
+    navigation: KeyboardNavigation,
 }
 
 impl MainMenu {
-    pub fn new(tex_ctx: &mut G2dTextureContext) -> Self {
+    pub fn new(tex_ctx: &mut G2dTextureContext, easings: &Easings) -> Self {
         let background_tex = Texture::from_path(
             tex_ctx,
             "assets/background.jpg",
@@ -19,12 +29,14 @@ impl MainMenu {
         )
         .unwrap();
 
-        let middle_menu_bar = MiddleMenuBar::new(tex_ctx);
+        let middle_menu_bar = MiddleMenuBar::new(tex_ctx, easings);
 
         Self {
             background_tex,
             middle_menu_bar,
+            now_playing: NowPlaying::new(),
             last_mouse_coords: [0.0, 0.0],
+            navigation: KeyboardNavigation::new(),
         }
     }
 
@@ -32,8 +44,9 @@ impl MainMenu {
         &mut self,
         c: Context,
         g: &mut G2d,
-        glyphs: &mut Glyphs,
+        glyphs: &mut Fonts,
         anim_mgr: &mut AnimationsManager,
+        music_mgr: &MusicManager,
     ) {
         // Render background
         let [win_width, win_height] = c.get_view_size();
@@ -79,14 +92,55 @@ impl MainMenu {
         // Render menu bar
         self.middle_menu_bar
             .render(c, g, glyphs, win_width, win_height, anim_mgr);
+
+        self.now_playing.render(c, g, glyphs, music_mgr);
+    }
+
+    pub fn start_music(&mut self, library: &Library, music_mgr: &mut MusicManager) {
+        self.now_playing.start(library, music_mgr);
     }
 
-    pub fn event<E: GenericEvent>(&mut self, e: &E, anim_mgr: &mut AnimationsManager) {
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        e: &E,
+        anim_mgr: &mut AnimationsManager,
+        library: &Library,
+        music_mgr: &mut MusicManager,
+    ) {
         if let Some(coords) = e.mouse_cursor_args() {
             self.last_mouse_coords = coords;
         }
 
-        self.middle_menu_bar.event(e, anim_mgr);
+        if focus::is_mouse_press(e) {
+            focus::clear_focus(&mut self.focusable_buttons());
+        }
+
+        if let Some(navigation) = self.navigation.event(e) {
+            let mut buttons = self.focusable_buttons();
+            match navigation {
+                Navigation::Next | Navigation::Down | Navigation::Right => {
+                    focus::move_focus(&mut buttons, false)
+                }
+                Navigation::Previous | Navigation::Up | Navigation::Left => {
+                    focus::move_focus(&mut buttons, true)
+                }
+                Navigation::Activate => focus::activate_focused(&mut buttons),
+                Navigation::Back => self.middle_menu_bar.back(anim_mgr),
+            }
+        }
+
+        self.now_playing.event(e, library, music_mgr);
+        self.middle_menu_bar.event(e, anim_mgr, music_mgr);
+    }
+
+    fn focusable_buttons(&mut self) -> Vec<&mut Button> {
+        let mut buttons = self.middle_menu_bar.focusable_buttons();
+        buttons.extend(self.now_playing.focusable_buttons());
+        buttons
+    }
+
+    pub fn next_action(&mut self) -> Option<MenuAction> {
+        self.middle_menu_bar.next_action()
     }
 
     fn map_range(a: f64, a_min: f64, a_max: f64, b_min: f64, b_max: f64) -> f64 {
@@ -0,0 +1,263 @@
+use super::button::{Button, ButtonEvent, ButtonState, Layout};
+use super::focus;
+use crate::animatable::{Angle, Animatable, LinearColor, Transform2d};
+use crate::animations::{Animation, AnimationType, AnimationsManager, Timeline};
+use crate::text::{Align, Fonts, RichText};
+use graphics::math::{self, Scalar};
+use graphics::types::Color;
+use graphics::{polygon, Context};
+use piston_window::{G2d, GenericEvent};
+use std::time::Duration;
+
+const LABEL_SIZE: u32 = 22;
+// How far the top edge leans right, as a fraction of the height
+const SKEW: f64 = 0.25;
+const HOVER_SCALE: f64 = 1.06;
+// Radians, tipping the button back a little under the cursor
+const HOVER_LEAN: f64 = -0.03;
+const PRESSED_SCALE: f64 = 0.96;
+const HOVER_STIFFNESS: f64 = 400.0;
+const HOVER_DAMPING: f64 = 22.0;
+const FILL_HALF_LIFE: Duration = Duration::from_millis(40);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MenuAction {
+    Solo,
+    Multiplayer,
+    Edit,
+    Options,
+    Exit,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum MenuButtonKind {
+    Play,
+    Edit,
+    Options,
+    Exit,
+    Solo,
+    Multiplayer,
+    Back,
+}
+
+// One of the coloured buttons fanning out of the osu! logo
+pub(crate) struct MenuButton {
+    pub kind: MenuButtonKind,
+    label: &'static str,
+    color: [f32; 4],
+    button: Button,
+    layout: Option<Layout>,
+    last_state: ButtonState,
+    // Grows and leans around the button's middle while hovered
+    hover: Transform2d,
+    hover_animation: Option<Animation<Transform2d>>,
+    fill: Color,
+    fill_animation: Option<Animation<LinearColor>>,
+}
+
+impl MenuButton {
+    pub fn new(kind: MenuButtonKind) -> Self {
+        let (label, color) = match kind {
+            MenuButtonKind::Play => ("Play", [0.40, 0.27, 0.80, 1.0]),
+            MenuButtonKind::Edit => ("Edit", [0.93, 0.67, 0.0, 1.0]),
+            MenuButtonKind::Options => ("Options", [0.20, 0.60, 0.87, 1.0]),
+            MenuButtonKind::Exit => ("Exit", [0.93, 0.27, 0.60, 1.0]),
+            MenuButtonKind::Solo => ("Solo", [0.40, 0.27, 0.80, 1.0]),
+            MenuButtonKind::Multiplayer => ("Multi", [0.93, 0.67, 0.0, 1.0]),
+            MenuButtonKind::Back => ("Back", [0.27, 0.27, 0.33, 1.0]),
+        };
+
+        Self {
+            kind,
+            label,
+            color,
+            button: Button::new(false),
+            layout: None,
+            last_state: ButtonState::Normal,
+            hover: Transform2d::IDENTITY,
+            hover_animation: None,
+            fill: color,
+            fill_animation: None,
+        }
+    }
+
+    // Leaning parallelograms, so neighbouring buttons still fit together
+    fn outline([x, y, dx, dy]: [Scalar; 4]) -> Layout {
+        let skew = dy * SKEW;
+        Layout::Polygon {
+            points: vec![
+                [x + skew, y],
+                [x + dx + skew, y],
+                [x + dx, y + dy],
+                [x, y + dy],
+            ],
+        }
+    }
+
+    // Where the button is clickable when laid out in the rectangle, following the hover
+    pub fn hit_layout(&self, rect: [Scalar; 4]) -> Layout {
+        Layout::Transformed {
+            layout: Box::new(Self::outline(Self::centred(rect))),
+            transform: self.transform(rect).apply(math::identity()),
+        }
+    }
+
+    // Hidden buttons don't take any input
+    pub fn set_layout(&mut self, layout: Option<Layout>) {
+        self.layout = layout;
+    }
+
+    pub fn event<E: GenericEvent>(&mut self, e: &E) {
+        if let Some(layout) = &self.layout {
+            self.button.event(layout, e);
+        }
+    }
+
+    pub fn button_mut(&mut self) -> &mut Button {
+        &mut self.button
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.button.is_focused()
+    }
+
+    pub fn next_event(&mut self) -> Option<ButtonEvent> {
+        self.button.next_event()
+    }
+
+    // Follows the button's state, once per frame before it's drawn
+    pub fn animate(&mut self, anim_mgr: &mut AnimationsManager) {
+        if let Some(hover) = self.hover_animation.and_then(|a| anim_mgr.get(a)) {
+            self.hover = hover;
+        }
+        if let Some(fill) = self.fill_animation.and_then(|a| anim_mgr.get(a)) {
+            self.fill = fill.0;
+        }
+
+        let state = self.button.state();
+        if state == self.last_state {
+            return;
+        }
+
+        let (scale, lean, brightness) = match state {
+            ButtonState::Normal => (1.0, 0.0, 1.0),
+            ButtonState::Hovered => (HOVER_SCALE, HOVER_LEAN, 1.2),
+            ButtonState::Pressed => (PRESSED_SCALE, HOVER_LEAN, 0.8),
+        };
+        self.last_state = state;
+        let hover = Transform2d {
+            rotation: Angle(lean),
+            scale: [scale, scale],
+            ..Transform2d::IDENTITY
+        };
+        let [r, g, b, a] = self.color;
+        let fill = LinearColor([
+            (r * brightness).min(1.0),
+            (g * brightness).min(1.0),
+            (b * brightness).min(1.0),
+            a,
+        ]);
+
+        follow(
+            anim_mgr,
+            &mut self.hover_animation,
+            &hover,
+            AnimationType::spring(&self.hover, &hover, HOVER_STIFFNESS, HOVER_DAMPING),
+        );
+        follow(
+            anim_mgr,
+            &mut self.fill_animation,
+            &fill,
+            AnimationType::decay(&LinearColor(self.fill), &fill, FILL_HALF_LIFE),
+        );
+    }
+
+    pub fn render(
+        &self,
+        c: Context,
+        g: &mut G2d,
+        glyphs: &mut Fonts,
+        rect: [Scalar; 4],
+        alpha: f32,
+    ) {
+        let mut color = self.fill;
+        color[3] *= alpha;
+
+        // Drawn around the middle, where the hover scales and turns it from
+        let c = Context {
+            transform: self.transform(rect).apply(c.transform),
+            ..c
+        };
+        let [x, y, dx, dy] = Self::centred(rect);
+
+        let outline = Self::outline([x, y, dx, dy]);
+        if let Layout::Polygon { points } = &outline {
+            polygon(color, points, c.transform, g);
+        }
+        if self.button.is_focused() {
+            focus::draw_focus_ring(c, g, &outline);
+        }
+
+        // Centred between the leaning edges
+        let label =
+            RichText::new(self.label, LABEL_SIZE, [1.0, 1.0, 1.0, alpha]).align(Align::Centre);
+        let [_, label_height] = label.size(glyphs);
+        label.draw(
+            glyphs,
+            c,
+            g,
+            [x + (dx + dy * SKEW) / 2.0, y + (dy - label_height) / 2.0],
+        );
+    }
+
+    fn transform(&self, [x, y, dx, dy]: [Scalar; 4]) -> Transform2d {
+        Transform2d {
+            translation: [x + dx / 2.0, y + dy / 2.0],
+            ..self.hover
+        }
+    }
+
+    // The rectangle moved so its middle is at the origin
+    fn centred([_, _, dx, dy]: [Scalar; 4]) -> [Scalar; 4] {
+        [-dx / 2.0, -dy / 2.0, dx, dy]
+    }
+}
+
+// Moves a running animation towards the target, keeping its speed, or starts a new one
+fn follow<T: Animatable>(
+    anim_mgr: &mut AnimationsManager,
+    animation: &mut Option<Animation<T>>,
+    target: &T,
+    start: AnimationType,
+) {
+    if animation.is_some_and(|animation| anim_mgr.retarget(animation, target)) {
+        return;
+    }
+
+    if let Some(animation) = animation.take() {
+        anim_mgr.remove(animation);
+    }
+    *animation = Some(anim_mgr.play(Timeline::Tween(start)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_areas_grow_and_lean_with_the_hover() {
+        let mut button = MenuButton::new(MenuButtonKind::Play);
+        let rect = [0.0, 0.0, 100.0, 40.0];
+        // Just past the bottom right corner
+        let point = [102.0, 39.0];
+        assert!(!button.hit_layout(rect).mouse_inside(point));
+
+        button.hover = Transform2d {
+            rotation: Angle(HOVER_LEAN),
+            scale: [HOVER_SCALE, HOVER_SCALE],
+            ..Transform2d::IDENTITY
+        };
+        assert!(button.hit_layout(rect).mouse_inside(point));
+        assert!(button.hit_layout(rect).mouse_inside([50.0, 20.0]));
+    }
+}
@@ -1,27 +1,79 @@
-use super::button::{Button, ButtonState, Layout};
-use crate::animations::{Animation, AnimationType, AnimationsManager, EasingType};
+use super::button::{Button, ButtonState, HitTest, Layout};
+use super::focus;
+use super::menu_button::{MenuAction, MenuButton, MenuButtonKind};
+use super::visualiser::Visualiser;
+use crate::animatable::Animatable;
+use crate::animations::{Animation, AnimationType, AnimationsManager, EasingType, Timeline};
+use crate::easings::Easings;
 use crate::menu::button::ButtonEvent;
-use graphics::math::{Matrix2d, Scalar};
-use graphics::{image, Context, Text};
+use crate::music_manager::MusicManager;
+use crate::text::Fonts;
+use graphics::math::{self, Matrix2d, Scalar};
+use graphics::{image, rectangle, Context};
 use piston_window::{
-    Flip, G2d, G2dTexture, G2dTextureContext, GenericEvent, Glyphs, ImageSize, Texture,
-    TextureSettings, Transformed,
+    Flip, G2d, G2dTexture, G2dTextureContext, GenericEvent, ImageSize, Texture, TextureSettings,
+    Transformed,
 };
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const BEAT_PULSE: f64 = 0.04;
+const KIAI_FLASH_ALPHA: f32 = 0.15;
+const KIAI_FLASH_WIDTH: f64 = 0.2;
+const VISUALISER_ALPHA: f32 = 0.2;
+const VISUALISER_KIAI_ALPHA: f32 = 0.35;
+
+// Fractions of the window width
+const LOGO_FANNED_OFFSET: f64 = -0.2;
+const MENU_BUTTON_WIDTH: f64 = 0.12;
+// Fraction of the window height
+const MENU_BUTTON_HEIGHT: f64 = 0.12;
+const MENU_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+const MENU_SLIDE_DURATION: Duration = Duration::from_millis(400);
+// Between the logo and the buttons starting to move when the menu opens or closes
+const MENU_SLIDE_STAGGER: Duration = Duration::from_millis(100);
+// Fractions of the window width per second
+const LOGO_EXIT_SPEED: f64 = 2.5;
+const LOGO_HOVER_STIFFNESS: f64 = 300.0;
+const LOGO_HOVER_DAMPING: f64 = 20.0;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MenuState {
+    Logo,
+    TopLevel,
+    Play,
+    // The logo flies off before the game closes
+    Exiting,
+}
 
 pub(crate) struct MiddleMenuBar {
     osu_button_tex: G2dTexture,
     osu_button: Button,
-    osu_btn_transform: Option<Matrix2d>,
     osu_btn_circle: Option<Layout>,
-    osu_btn_animation_id: Option<u32>,
+    osu_btn_animation: Option<Animation<f64>>,
     osu_btn_current_ratio: f64,
     osu_btn_last_state: ButtonState,
-    test: bool,
+    visualiser: Visualiser,
+    beat_pulse: f64,
+    kiai: bool,
+    kiai_flash: f32,
+    kiai_flash_left: bool,
+    menu_state: MenuState,
+    menu_buttons: Vec<MenuButton>,
+    menu_buttons_reveal: f64,
+    // The logo can end up over the buttons sliding out from behind it
+    hit_test: HitTest,
+    osu_btn_offset: f64,
+    // Moves the logo offset and the buttons reveal together
+    menu_animation: Option<Animation<(f64, f64)>>,
+    last_interaction: Instant,
+    actions: VecDeque<MenuAction>,
+    slide_easing: EasingType,
+    beat_easing: EasingType,
 }
 
 impl MiddleMenuBar {
-    pub fn new(tex_ctx: &mut G2dTextureContext) -> Self {
+    pub fn new(tex_ctx: &mut G2dTextureContext, easings: &Easings) -> Self {
         let osu_button_tex = Texture::from_path(
             tex_ctx,
             "assets/osu.png",
@@ -32,13 +84,26 @@ impl MiddleMenuBar {
 
         Self {
             osu_button_tex,
-            osu_button: Button::new(true),
-            osu_btn_transform: None,
+            osu_button: Button::new(false),
             osu_btn_circle: None,
-            osu_btn_animation_id: None,
+            osu_btn_animation: None,
             osu_btn_current_ratio: 0.35,
             osu_btn_last_state: ButtonState::Normal,
-            test: false,
+            visualiser: Visualiser::new(),
+            beat_pulse: 1.0,
+            kiai: false,
+            kiai_flash: 0.0,
+            kiai_flash_left: true,
+            menu_state: MenuState::Logo,
+            menu_buttons: Vec::new(),
+            menu_buttons_reveal: 0.0,
+            hit_test: HitTest::default(),
+            osu_btn_offset: 0.0,
+            menu_animation: None,
+            last_interaction: Instant::now(),
+            actions: VecDeque::new(),
+            slide_easing: easings.get("MenuSlide", EasingType::ExpoOut),
+            beat_easing: easings.get("BeatPulse", EasingType::QuadOut),
         }
     }
 
@@ -46,55 +111,340 @@ impl MiddleMenuBar {
         &mut self,
         c: Context,
         g: &mut G2d,
-        glyphs: &mut Glyphs,
+        glyphs: &mut Fonts,
         win_width: Scalar,
         win_height: Scalar,
         anim_mgr: &mut AnimationsManager,
     ) {
+        if self.kiai_flash > 0.0 {
+            let flash_width = win_width * KIAI_FLASH_WIDTH;
+            let flash_x = if self.kiai_flash_left {
+                0.0
+            } else {
+                win_width - flash_width
+            };
+
+            rectangle(
+                [1.0, 1.0, 1.0, self.kiai_flash],
+                [flash_x, 0.0, flash_width, win_height],
+                c.transform,
+                g,
+            );
+        }
+
         let (osu_btn_trans, osu_btn_circle) =
-            self.calc_osu_btn_transform(c, win_width, win_height, anim_mgr);
-        self.osu_btn_transform = Some(osu_btn_trans);
+            self.calc_osu_btn_transform(win_width, win_height, anim_mgr);
+
+        self.hit_test.clear();
+        if let Layout::Circle { x, y, radius } = osu_btn_circle {
+            self.render_menu_buttons(c, g, glyphs, [x, y], radius, anim_mgr);
+
+            let alpha = if self.kiai {
+                VISUALISER_KIAI_ALPHA
+            } else {
+                VISUALISER_ALPHA
+            };
+
+            self.visualiser
+                .render(c, g, [x, y], radius * self.beat_pulse, alpha);
+        }
+
+        if self.osu_button.is_focused() {
+            focus::draw_focus_ring(c, g, &osu_btn_circle);
+        }
+
+        // The texture's own circle, so the hit area pulses along with the beat
+        let (osu_w, osu_h) = self.osu_button_tex.get_size();
+        let hit_layout = Layout::Transformed {
+            layout: Box::new(Layout::Circle {
+                x: osu_w as f64 / 2.0,
+                y: osu_h as f64 / 2.0,
+                radius: osu_w as f64 * 0.9 / 2.0,
+            }),
+            transform: osu_btn_trans,
+        };
+        self.osu_button
+            .set_region(&mut self.hit_test, Some(hit_layout));
         self.osu_btn_circle = Some(osu_btn_circle);
 
-        image(&self.osu_button_tex, osu_btn_trans, g);
+        image(
+            &self.osu_button_tex,
+            math::multiply(c.transform, osu_btn_trans),
+            g,
+        );
+    }
 
-        if self.test {
-            Text::new_color([1.0, 1.0, 1.0, 1.0], 18)
-                .draw(
-                    "aaaaaaaa",
-                    glyphs,
-                    &c.draw_state,
-                    c.transform.trans(20.0, 120.0),
+    // The buttons slide out from behind the logo as they are revealed
+    fn render_menu_buttons(
+        &mut self,
+        c: Context,
+        g: &mut G2d,
+        glyphs: &mut Fonts,
+        [logo_x, logo_y]: [Scalar; 2],
+        logo_radius: Scalar,
+        anim_mgr: &mut AnimationsManager,
+    ) {
+        let [win_width, win_height] = c.get_view_size();
+        let interactive = matches!(self.menu_state, MenuState::TopLevel | MenuState::Play)
+            && self.menu_buttons_reveal > 0.0;
+
+        let width = win_width * MENU_BUTTON_WIDTH;
+        let height = win_height * MENU_BUTTON_HEIGHT;
+        // The logo's own hit circle already covers its radius
+        let start = logo_radius;
+
+        for (i, menu_button) in self.menu_buttons.iter_mut().enumerate() {
+            let x = logo_x + (start + i as f64 * width) * self.menu_buttons_reveal;
+            let y = logo_y - height / 2.0;
+
+            menu_button.animate(anim_mgr);
+            let outline = menu_button.hit_layout([x, y, width, height]);
+            menu_button
+                .button_mut()
+                .set_region(&mut self.hit_test, interactive.then_some(outline));
+
+            if self.menu_buttons_reveal > 0.0 {
+                menu_button.render(
+                    c,
                     g,
-                )
-                .unwrap();
+                    glyphs,
+                    [x, y, width, height],
+                    self.menu_buttons_reveal as f32,
+                );
+            }
+        }
+    }
+
+    // The logo, then whichever buttons are out
+    pub fn focusable_buttons(&mut self) -> Vec<&mut Button> {
+        let mut buttons = vec![&mut self.osu_button];
+        if matches!(self.menu_state, MenuState::TopLevel | MenuState::Play) {
+            buttons.extend(self.menu_buttons.iter_mut().map(MenuButton::button_mut));
+        }
+        buttons
+    }
+
+    // One level up, the same as the Back button and then the idle timeout
+    pub fn back(&mut self, anim_mgr: &mut AnimationsManager) {
+        match self.menu_state {
+            MenuState::Play => self.set_menu_state(MenuState::TopLevel, anim_mgr),
+            MenuState::TopLevel => self.set_menu_state(MenuState::Logo, anim_mgr),
+            MenuState::Logo | MenuState::Exiting => {}
+        }
+    }
+
+    pub fn next_action(&mut self) -> Option<MenuAction> {
+        self.actions.pop_front()
+    }
+
+    fn set_menu_state(&mut self, state: MenuState, anim_mgr: &mut AnimationsManager) {
+        if state == self.menu_state {
+            return;
+        }
+
+        self.menu_state = state;
+
+        // Keyboard focus moves on to the new buttons instead of getting lost with the old ones
+        let keyboard_focus =
+            self.osu_button.is_focused() || self.menu_buttons.iter().any(MenuButton::is_focused);
+        if keyboard_focus {
+            for menu_button in &mut self.menu_buttons {
+                menu_button.button_mut().set_focused(false);
+            }
+            self.osu_button.set_focused(state == MenuState::Logo);
+        }
+
+        let timeline = match state {
+            // Keep the current buttons around so they can slide back behind the logo, they are
+            // dropped once the animation is over
+            MenuState::Logo => Timeline::Parallel(vec![
+                Timeline::Sequence(vec![
+                    Timeline::Delay(MENU_SLIDE_STAGGER),
+                    self.slide(self.osu_btn_offset, 0.0),
+                ]),
+                self.slide(self.menu_buttons_reveal, 0.0),
+            ]),
+            // Same values as in the other states, logo offset then reveal
+            MenuState::Exiting => Timeline::Parallel(vec![
+                Timeline::Tween(AnimationType::Velocity {
+                    start_values: Box::new([self.osu_btn_offset]),
+                    per_second: Box::new([LOGO_EXIT_SPEED]),
+                    // Far enough right for the logo to be out of the window
+                    condition: |values| values[0] > 0.9,
+                }),
+                Timeline::Tween(AnimationType::decay(
+                    &self.menu_buttons_reveal,
+                    &0.0,
+                    Duration::from_millis(60),
+                )),
+            ]),
+            MenuState::TopLevel | MenuState::Play => {
+                let kinds: &[MenuButtonKind] = if state == MenuState::TopLevel {
+                    &[
+                        MenuButtonKind::Play,
+                        MenuButtonKind::Edit,
+                        MenuButtonKind::Options,
+                        MenuButtonKind::Exit,
+                    ]
+                } else {
+                    &[
+                        MenuButtonKind::Solo,
+                        MenuButtonKind::Multiplayer,
+                        MenuButtonKind::Back,
+                    ]
+                };
+
+                self.menu_buttons = kinds.iter().copied().map(MenuButton::new).collect();
+                self.menu_buttons_reveal = 0.0;
+                self.menu_buttons[0]
+                    .button_mut()
+                    .set_focused(keyboard_focus);
+
+                // The logo moves aside first and the buttons fan out from behind it
+                Timeline::Parallel(vec![
+                    self.slide(self.osu_btn_offset, LOGO_FANNED_OFFSET),
+                    Timeline::Sequence(vec![
+                        Timeline::Delay(MENU_SLIDE_STAGGER),
+                        self.slide(0.0, 1.0),
+                    ]),
+                ])
+            }
+        };
+
+        Self::restart_animation(anim_mgr, &mut self.menu_animation, timeline);
+    }
+
+    fn slide(&self, from: f64, to: f64) -> Timeline {
+        Timeline::Tween(AnimationType::timed(
+            &from,
+            &to,
+            MENU_SLIDE_DURATION,
+            self.slide_easing.clone(),
+        ))
+    }
+
+    fn restart_animation<T: Animatable>(
+        anim_mgr: &mut AnimationsManager,
+        animation: &mut Option<Animation<T>>,
+        timeline: Timeline,
+    ) {
+        if let Some(animation) = animation.take() {
+            anim_mgr.remove(animation);
+        }
+
+        *animation = Some(anim_mgr.play(timeline));
+    }
+
+    fn handle_logo_click(&mut self, anim_mgr: &mut AnimationsManager) {
+        match self.menu_state {
+            MenuState::Logo => self.set_menu_state(MenuState::TopLevel, anim_mgr),
+            // Clicking the logo again goes one step deeper, like in the real game
+            MenuState::TopLevel => self.set_menu_state(MenuState::Play, anim_mgr),
+            MenuState::Play => self.actions.push_back(MenuAction::Solo),
+            MenuState::Exiting => {}
         }
     }
 
-    pub fn event<E: GenericEvent>(&mut self, e: &E, anim_mgr: &mut AnimationsManager) {
-        if let Some(circle) = &self.osu_btn_circle {
-            self.osu_button.event(circle, e);
+    fn handle_menu_button_click(&mut self, kind: MenuButtonKind, anim_mgr: &mut AnimationsManager) {
+        match kind {
+            MenuButtonKind::Play => self.set_menu_state(MenuState::Play, anim_mgr),
+            MenuButtonKind::Back => self.set_menu_state(MenuState::TopLevel, anim_mgr),
+            MenuButtonKind::Edit => self.actions.push_back(MenuAction::Edit),
+            MenuButtonKind::Options => self.actions.push_back(MenuAction::Options),
+            MenuButtonKind::Exit => self.set_menu_state(MenuState::Exiting, anim_mgr),
+            MenuButtonKind::Solo => self.actions.push_back(MenuAction::Solo),
+            MenuButtonKind::Multiplayer => self.actions.push_back(MenuAction::Multiplayer),
+        }
+    }
+
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        e: &E,
+        anim_mgr: &mut AnimationsManager,
+        music_mgr: &MusicManager,
+    ) {
+        self.osu_button.event_in(&self.hit_test, e);
+        for menu_button in &mut self.menu_buttons {
+            menu_button.button_mut().event_in(&self.hit_test, e);
+        }
+
+        if e.mouse_cursor_args().is_some() || e.press_args().is_some() {
+            self.last_interaction = Instant::now();
         }
 
-        if e.update_args().is_some() {
-            if let Some(id) = self.osu_btn_animation_id {
-                if let Some(a) = anim_mgr.get(id) {
-                    self.osu_btn_current_ratio = a.get_current_values()[0];
+        if let Some(args) = e.update_args() {
+            self.update_beat(music_mgr);
+            self.visualiser
+                .update(args.dt, &music_mgr.recent_samples(1024));
+
+            if let Some(ratio) = self.osu_btn_animation.and_then(|a| anim_mgr.get(a)) {
+                self.osu_btn_current_ratio = ratio;
+            }
+
+            if let Some((offset, reveal)) = self.menu_animation.and_then(|a| anim_mgr.get(a)) {
+                self.osu_btn_offset = offset;
+                self.menu_buttons_reveal = reveal;
+            }
+
+            if self
+                .menu_animation
+                .is_some_and(|a| anim_mgr.take_finished(a))
+            {
+                match self.menu_state {
+                    MenuState::Logo => self.menu_buttons.clear(),
+                    MenuState::Exiting => self.actions.push_back(MenuAction::Exit),
+                    _ => {}
                 }
             }
 
             while let Some(btn_event) = self.osu_button.next_event() {
                 println!("{:?}", btn_event);
-                if btn_event == ButtonEvent::Press {
-                    self.test = !self.test;
+                if btn_event == ButtonEvent::Click {
+                    self.handle_logo_click(anim_mgr);
                 }
             }
+
+            let mut clicked = Vec::new();
+            for menu_button in &mut self.menu_buttons {
+                while let Some(btn_event) = menu_button.next_event() {
+                    if btn_event == ButtonEvent::Click {
+                        clicked.push(menu_button.kind);
+                    }
+                }
+            }
+
+            for kind in clicked {
+                self.handle_menu_button_click(kind, anim_mgr);
+            }
+
+            if matches!(self.menu_state, MenuState::TopLevel | MenuState::Play)
+                && self.last_interaction.elapsed() > MENU_IDLE_TIMEOUT
+            {
+                self.set_menu_state(MenuState::Logo, anim_mgr);
+            }
         }
     }
 
+    fn update_beat(&mut self, music_mgr: &MusicManager) {
+        let time = music_mgr.position();
+        let beat = music_mgr.timing().beat_at(time);
+
+        // Kick outwards on the beat, then settle back until the next one
+        let fade = 1.0 - self.beat_easing.apply(beat.progress);
+        self.beat_pulse = 1.0 + BEAT_PULSE * fade;
+
+        self.kiai = music_mgr.timing().kiai_at(time);
+        self.kiai_flash_left = beat.index % 2 == 0;
+        self.kiai_flash = if self.kiai {
+            KIAI_FLASH_ALPHA * fade as f32
+        } else {
+            0.0
+        };
+    }
+
+    // The transform is from the texture to the window, without the view part of the context
     fn calc_osu_btn_transform(
         &mut self,
-        c: Context,
         win_width: Scalar,
         win_height: Scalar,
         anim_mgr: &mut AnimationsManager,
@@ -102,20 +452,26 @@ impl MiddleMenuBar {
         let (osu_w, osu_h) = self.osu_button_tex.get_size();
 
         if self.osu_btn_last_state != self.osu_button.state() {
-            if let Some(id) = self.osu_btn_animation_id {
-                anim_mgr.remove(id);
-            }
+            let target = match self.osu_button.state() {
+                ButtonState::Normal => 0.35,
+                ButtonState::Hovered => 0.38,
+                ButtonState::Pressed => 0.40,
+            };
 
-            self.osu_btn_animation_id = Some(anim_mgr.add(AnimationType::Timed {
-                duration: Duration::from_millis(150),
-                start_values: Box::new([self.osu_btn_current_ratio]),
-                end_values: Box::new([match self.osu_button.state() {
-                    ButtonState::Normal => 0.35,
-                    ButtonState::Hovered => 0.38,
-                    ButtonState::Pressed => 0.40,
-                }]),
-                easing_type: EasingType::CubicInOut,
-            }));
+            // A spring still moving keeps its speed instead of starting over from rest
+            let retargeted = self
+                .osu_btn_animation
+                .is_some_and(|animation| anim_mgr.retarget(animation, &target));
+
+            if !retargeted {
+                let hover = Timeline::Tween(AnimationType::spring(
+                    &self.osu_btn_current_ratio,
+                    &target,
+                    LOGO_HOVER_STIFFNESS,
+                    LOGO_HOVER_DAMPING,
+                ));
+                Self::restart_animation(anim_mgr, &mut self.osu_btn_animation, hover);
+            }
 
             self.osu_btn_last_state = self.osu_button.state();
         }
@@ -124,14 +480,22 @@ impl MiddleMenuBar {
         let new_width = osu_w as f64 * scale;
         let new_height = osu_h as f64 * scale;
 
+        let center_x = win_width / 2.0 + win_width * self.osu_btn_offset;
+
         let coords = (
-            win_width / 2.0 - new_width / 2.0,
+            center_x - new_width / 2.0,
             win_height / 2.0 - new_height / 2.0,
         );
 
-        let transform = c.transform.trans(coords.0, coords.1).scale(scale, scale);
+        let pulse_offset = (self.beat_pulse - 1.0) / 2.0;
+        let transform = math::identity()
+            .trans(
+                coords.0 - new_width * pulse_offset,
+                coords.1 - new_height * pulse_offset,
+            )
+            .scale(scale * self.beat_pulse, scale * self.beat_pulse);
         let circle = Layout::Circle {
-            x: win_width / 2.0,
+            x: center_x,
             y: win_height / 2.0,
             radius: osu_w as f64 * 0.9 / 2.0 * scale,
         };
@@ -1,3 +1,12 @@
 mod button;
+mod focus;
 pub mod main_menu;
+mod menu_button;
 mod middle_menu_bar;
+mod now_playing;
+pub mod options;
+mod text_field;
+mod visualiser;
+mod widgets;
+
+pub use menu_button::MenuAction;
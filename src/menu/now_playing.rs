@@ -0,0 +1,355 @@
+use super::button::{Button, ButtonEvent, ButtonState, Layout};
+use super::focus;
+use crate::beatmap::{BeatmapInfo, TrackTiming};
+use crate::library::Library;
+use crate::music_manager::MusicManager;
+use crate::text::{Fonts, RichText, Span};
+use graphics::math::Scalar;
+use graphics::{rectangle, Context, Rectangle, Text, Transformed};
+use piston_window::{G2d, GenericEvent};
+use rosu_pp::Beatmap;
+use std::time::Duration;
+
+const PANEL_WIDTH: f64 = 360.0;
+const PANEL_MARGIN: f64 = 10.0;
+const CONTROL_WIDTH: f64 = 44.0;
+const CONTROL_HEIGHT: f64 = 28.0;
+const CONTROL_RADIUS: f64 = 6.0;
+const SEEK_BAR_HEIGHT: f64 = 6.0;
+// Pressing previous further than this into a song restarts it instead
+const RESTART_THRESHOLD: f64 = 5000.0;
+// Sets tried in a row before giving up on the library and playing the menu theme
+const MAX_ATTEMPTS: usize = 5;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Control {
+    Previous,
+    PlayPause,
+    Next,
+    Stop,
+}
+
+// Menu jukebox playing random songs from the library, with the overlay controlling it
+pub(crate) struct NowPlaying {
+    history: Vec<usize>,
+    history_pos: usize,
+    current: Option<BeatmapInfo>,
+    controls: Vec<(Control, Button, Option<Layout>)>,
+    seek_bar: Button,
+    seek_bar_layout: Option<Layout>,
+    last_mouse_coords: [f64; 2],
+}
+
+impl NowPlaying {
+    pub fn new() -> Self {
+        let controls = [
+            Control::Previous,
+            Control::PlayPause,
+            Control::Next,
+            Control::Stop,
+        ]
+        .into_iter()
+        .map(|control| (control, Button::new(false), None))
+        .collect();
+
+        Self {
+            history: Vec::new(),
+            history_pos: 0,
+            current: None,
+            controls,
+            seek_bar: Button::new(false),
+            seek_bar_layout: None,
+            last_mouse_coords: [0.0, 0.0],
+        }
+    }
+
+    pub fn start(&mut self, library: &Library, music_mgr: &mut MusicManager) {
+        if library.is_empty() {
+            music_mgr.play_file("welcome.mp3");
+        } else {
+            self.next(library, music_mgr);
+        }
+    }
+
+    // Returns whether the set's song is playing
+    fn play_set(&mut self, index: usize, library: &Library, music_mgr: &mut MusicManager) -> bool {
+        let map_path = &library.set(index).maps[0];
+
+        let (Ok(info), Ok(beatmap)) = (
+            BeatmapInfo::from_path(map_path),
+            Beatmap::from_path(map_path),
+        ) else {
+            println!("Could not read {:?}", map_path);
+            return false;
+        };
+
+        // Menu music starts where the mapper wants the song previewed
+        let start = Duration::from_secs_f64(info.preview_time.unwrap_or(0.0) / 1000.0);
+        let audio_path = info.file(&info.audio_filename);
+        if let Err(e) =
+            music_mgr.play_track(&audio_path, start, TrackTiming::from_beatmap(&beatmap))
+        {
+            println!("Could not play {:?}: {}", audio_path, e);
+            return false;
+        }
+
+        println!("Now playing {} - {}", info.artist, info.title);
+        self.current = Some(info);
+        true
+    }
+
+    // Plays the set at the current history position. Sets that don't play get forgotten and a
+    // random one takes their place, until too many failed in a row
+    fn play_history(&mut self, library: &Library, music_mgr: &mut MusicManager) {
+        for _ in 0..MAX_ATTEMPTS {
+            let index = self.history[self.history_pos];
+            if self.play_set(index, library, music_mgr) {
+                return;
+            }
+
+            match library.random_set_index(Some(index)) {
+                Some(other) => self.history[self.history_pos] = other,
+                None => break,
+            }
+        }
+
+        println!("Nothing in the library would play, falling back to the menu theme");
+        self.history.clear();
+        self.history_pos = 0;
+        self.current = None;
+        music_mgr.play_file("welcome.mp3");
+    }
+
+    fn next(&mut self, library: &Library, music_mgr: &mut MusicManager) {
+        if self.history_pos + 1 < self.history.len() {
+            self.history_pos += 1;
+        } else {
+            let Some(index) = library.random_set_index(self.history.last().copied()) else {
+                return;
+            };
+
+            self.history.push(index);
+            self.history_pos = self.history.len() - 1;
+        }
+
+        self.play_history(library, music_mgr);
+    }
+
+    fn previous(&mut self, library: &Library, music_mgr: &mut MusicManager) {
+        if self.history_pos == 0 || music_mgr.position() > RESTART_THRESHOLD {
+            music_mgr.seek(0.0);
+            return;
+        }
+
+        self.history_pos -= 1;
+        self.play_history(library, music_mgr);
+    }
+
+    // Only the playback controls, the seek bar needs a mouse
+    pub fn focusable_buttons(&mut self) -> Vec<&mut Button> {
+        if self.current.is_none() {
+            return Vec::new();
+        }
+
+        self.controls
+            .iter_mut()
+            .map(|(_, button, _)| button)
+            .collect()
+    }
+
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        e: &E,
+        library: &Library,
+        music_mgr: &mut MusicManager,
+    ) {
+        if self.current.is_none() {
+            return;
+        }
+
+        if let Some(coords) = e.mouse_cursor_args() {
+            self.last_mouse_coords = coords;
+        }
+
+        for (_, button, layout) in &mut self.controls {
+            if let Some(layout) = layout {
+                button.event(layout, e);
+            }
+        }
+
+        if let Some(layout) = &self.seek_bar_layout {
+            self.seek_bar.event(layout, e);
+        }
+
+        if e.update_args().is_none() {
+            return;
+        }
+
+        let mut clicked = Vec::new();
+        for (control, button, _) in &mut self.controls {
+            while let Some(btn_event) = button.next_event() {
+                if btn_event == ButtonEvent::Click {
+                    clicked.push(*control);
+                }
+            }
+        }
+
+        for control in clicked {
+            match control {
+                Control::Previous => self.previous(library, music_mgr),
+                Control::PlayPause if music_mgr.is_paused() => music_mgr.resume(),
+                Control::PlayPause => music_mgr.pause(),
+                Control::Next => self.next(library, music_mgr),
+                Control::Stop => music_mgr.stop(),
+            }
+        }
+
+        while let Some(btn_event) = self.seek_bar.next_event() {
+            if let (ButtonEvent::Click, Some(Layout::Rectangle { x, dx, .. }), Some(duration)) =
+                (btn_event, &self.seek_bar_layout, music_mgr.duration())
+            {
+                let progress = ((self.last_mouse_coords[0] - x) / dx).clamp(0.0, 1.0);
+                music_mgr.seek(progress * duration);
+            }
+        }
+
+        if music_mgr.is_finished() {
+            self.next(library, music_mgr);
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        c: Context,
+        g: &mut G2d,
+        glyphs: &mut Fonts,
+        music_mgr: &MusicManager,
+    ) {
+        let Some(current) = &self.current else {
+            return;
+        };
+
+        let [win_width, _] = c.get_view_size();
+        let x = win_width - PANEL_WIDTH - PANEL_MARGIN;
+        let y = PANEL_MARGIN;
+
+        rectangle(
+            [0.0, 0.0, 0.0, 0.5],
+            [x, y, PANEL_WIDTH, 90.0],
+            c.transform,
+            g,
+        );
+
+        RichText::spans(vec![
+            Span::new(current.display_artist(), 16, [1.0, 1.0, 1.0, 0.6]),
+            Span::new(" - ", 16, [1.0, 1.0, 1.0, 0.6]),
+            Span::new(current.display_title(), 16, [1.0, 1.0, 1.0, 1.0]),
+        ])
+        .truncate(PANEL_WIDTH - 20.0)
+        .draw(glyphs, c, g, [x + 10.0, y + 6.0]);
+
+        for (i, (control, button, layout)) in self.controls.iter_mut().enumerate() {
+            let control_x = x + 10.0 + i as f64 * (CONTROL_WIDTH + 6.0);
+            let control_y = y + 34.0;
+
+            let label = match control {
+                Control::Previous => "|<",
+                Control::PlayPause if music_mgr.is_paused() => ">",
+                Control::PlayPause => "||",
+                Control::Next => ">|",
+                Control::Stop => "[]",
+            };
+
+            Self::render_control(
+                c,
+                g,
+                glyphs,
+                label,
+                button.state(),
+                button.is_focused(),
+                [control_x, control_y, CONTROL_WIDTH, CONTROL_HEIGHT],
+            );
+
+            *layout = Some(Layout::RoundedRectangle {
+                x: control_x,
+                y: control_y,
+                dx: CONTROL_WIDTH,
+                dy: CONTROL_HEIGHT,
+                radius: CONTROL_RADIUS,
+            });
+        }
+
+        let bar_x = x + 10.0;
+        let bar_y = y + 74.0;
+        let bar_width = PANEL_WIDTH - 20.0;
+
+        rectangle(
+            [1.0, 1.0, 1.0, 0.2],
+            [bar_x, bar_y, bar_width, SEEK_BAR_HEIGHT],
+            c.transform,
+            g,
+        );
+
+        if let Some(duration) = music_mgr.duration() {
+            let progress = (music_mgr.position() / duration).clamp(0.0, 1.0);
+
+            rectangle(
+                [1.0, 1.0, 1.0, 0.8],
+                [bar_x, bar_y, bar_width * progress, SEEK_BAR_HEIGHT],
+                c.transform,
+                g,
+            );
+        }
+
+        // Slightly taller than drawn so it's easier to hit
+        self.seek_bar_layout = Some(Layout::Rectangle {
+            x: bar_x,
+            y: bar_y - 4.0,
+            dx: bar_width,
+            dy: SEEK_BAR_HEIGHT + 8.0,
+        });
+    }
+
+    fn render_control(
+        c: Context,
+        g: &mut G2d,
+        glyphs: &mut Fonts,
+        label: &str,
+        state: ButtonState,
+        focused: bool,
+        [x, y, dx, dy]: [Scalar; 4],
+    ) {
+        let alpha = match state {
+            ButtonState::Normal => 0.15,
+            ButtonState::Hovered => 0.3,
+            ButtonState::Pressed => 0.45,
+        };
+
+        Rectangle::new_round([1.0, 1.0, 1.0, alpha], CONTROL_RADIUS).draw(
+            [x, y, dx, dy],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+        if focused {
+            let layout = Layout::RoundedRectangle {
+                x,
+                y,
+                dx,
+                dy,
+                radius: CONTROL_RADIUS,
+            };
+            focus::draw_focus_ring(c, g, &layout);
+        }
+
+        Text::new_color([1.0, 1.0, 1.0, 1.0], 16)
+            .draw(
+                label,
+                glyphs,
+                &c.draw_state,
+                c.transform.trans(x + dx / 2.0 - 8.0, y + dy / 2.0 + 6.0),
+                g,
+            )
+            .unwrap();
+    }
+}
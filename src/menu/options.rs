@@ -0,0 +1,637 @@
+use super::button::{Button, ButtonEvent, ButtonState, Layout};
+use super::focus::{self, KeyboardNavigation, Navigation};
+use super::menu_button::{MenuButton, MenuButtonKind};
+use super::widgets::{
+    Anchor, Direction, Size, Ui, Widget, WidgetEvent, CONTROL_HEIGHT, DIM_TEXT_COLOR,
+};
+use crate::animatable::PerceptualColor;
+use crate::animations::{Animation, AnimationType, AnimationsManager, EasingType, Timeline};
+use crate::cursor::CursorTrail;
+use crate::cursor_input::InputMode;
+use crate::display::{DisplayMode, FrameLimiter, Resolution};
+use crate::easings::Easings;
+use crate::key_bindings::{self, GameAction};
+use crate::settings::Settings;
+use crate::text::Fonts;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{rectangle, Context, Text, Transformed};
+use piston_window::{self, G2d, GenericEvent};
+use rosu_pp::GameMode;
+use std::time::Duration;
+
+const ROW_WIDTH: f64 = 520.0;
+const ROW_HEIGHT: f64 = 34.0;
+const ROW_SPACING: f64 = 4.0;
+const TEXT_SIZE: u32 = 18;
+const ROW_COLOR: Color = [1.0, 1.0, 1.0, 0.08];
+const ROW_HOVERED_COLOR: Color = [1.0, 1.0, 1.0, 0.16];
+const ROW_WAITING_COLOR: Color = [0.20, 0.60, 0.87, 0.6];
+const PROMPT_PULSE: Duration = Duration::from_millis(500);
+const ROW_FADE_HALF_LIFE: Duration = Duration::from_millis(40);
+const SECTION_SIZE: u32 = 24;
+const LIST_COLOR: Color = [0.0, 0.0, 0.0, 0.3];
+const RULESETS: [(GameMode, &str); 4] = [
+    (GameMode::Osu, "osu!"),
+    (GameMode::Taiko, "osu!taiko"),
+    (GameMode::Catch, "osu!catch"),
+    (GameMode::Mania, "osu!mania"),
+];
+const INPUT_MODES: [(InputMode, &str); 2] =
+    [(InputMode::Mouse, "Mouse"), (InputMode::Tablet, "Tablet")];
+const DISPLAY_MODES: [(DisplayMode, &str); 3] = [
+    (DisplayMode::Windowed, "Windowed"),
+    (DisplayMode::Borderless, "Borderless"),
+    (DisplayMode::Fullscreen, "Fullscreen"),
+];
+const FRAME_LIMITERS: [(FrameLimiter, &str); 5] = [
+    (FrameLimiter::Unlimited, "Unlimited"),
+    (FrameLimiter::Refresh2x, "2x refresh rate"),
+    (FrameLimiter::Refresh4x, "4x refresh rate"),
+    (FrameLimiter::Refresh8x, "8x refresh rate"),
+    (FrameLimiter::Custom, "Custom"),
+];
+const CURSOR_TRAILS: [(CursorTrail, &str); 3] = [
+    (CursorTrail::Off, "Off"),
+    (CursorTrail::Sprites, "Skin sprites"),
+    (CursorTrail::Long, "Long"),
+];
+
+struct BindingRow {
+    action: GameAction,
+    button: Button,
+    layout: Option<Layout>,
+    background: Color,
+    // Fades the background when the row gets hovered or starts waiting for a key
+    background_animation: Option<Animation<PerceptualColor>>,
+}
+
+// Settings screen. Key bindings are on the left, where clicking an action waits for the next key
+// or mouse button to bind it, everything else is in a scrolling list of widgets on the right
+pub struct OptionsMenu {
+    rows: Vec<BindingRow>,
+    settings_ui: Ui,
+    // What the resolution dropdown lists, in the same order
+    resolutions: Vec<Resolution>,
+    back_button: MenuButton,
+    navigation: KeyboardNavigation,
+    waiting_for: Option<GameAction>,
+    // Fades the waiting prompt in and out
+    prompt_animation: Option<Animation<f64>>,
+    prompt_easing: EasingType,
+    // Release of the button that was just bound, which shouldn't click anything
+    swallowed_release: Option<piston_window::Button>,
+    message: Option<String>,
+    closed: bool,
+}
+
+impl OptionsMenu {
+    pub fn new(easings: &Easings, settings: &Settings, mut resolutions: Vec<Resolution>) -> Self {
+        // A saved resolution the monitor doesn't list still shows as chosen
+        if !resolutions.contains(&settings.resolution) {
+            resolutions.insert(0, settings.resolution);
+        }
+
+        Self {
+            rows: GameAction::ALL
+                .into_iter()
+                .map(|action| BindingRow {
+                    action,
+                    button: Button::new(false),
+                    layout: None,
+                    background: ROW_COLOR,
+                    background_animation: None,
+                })
+                .collect(),
+            settings_ui: settings_ui(settings, &resolutions),
+            resolutions,
+            back_button: MenuButton::new(MenuButtonKind::Back),
+            navigation: KeyboardNavigation::new(),
+            waiting_for: None,
+            prompt_animation: None,
+            prompt_easing: easings.get("PromptPulse", EasingType::SineInOut),
+            swallowed_release: None,
+            message: None,
+            closed: false,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn event<E: GenericEvent>(
+        &mut self,
+        e: &E,
+        settings: &mut Settings,
+        anim_mgr: &mut AnimationsManager,
+    ) {
+        if let Some(action) = self.waiting_for {
+            if let Some(button) = e.press_args() {
+                self.waiting_for = None;
+                if let Some(animation) = self.prompt_animation.take() {
+                    anim_mgr.remove(animation);
+                }
+                self.swallowed_release = Some(button);
+
+                // Backs out of the prompt, keeping what was bound before
+                if button == piston_window::Button::Keyboard(piston_window::Key::Escape) {
+                    return;
+                }
+
+                let name = key_bindings::button_name(button);
+                let moved = settings.key_bindings.bind(action, button);
+                self.message = (!moved.is_empty()).then(|| {
+                    let moves = moved
+                        .iter()
+                        .map(|moved| {
+                            format!(
+                                "{} now uses {}",
+                                moved.label(),
+                                key_bindings::button_name(settings.key_bindings.button(*moved))
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    format!("{} was taken, {}", name, moves.join(", "))
+                });
+            }
+            return;
+        }
+
+        if e.release_args()
+            .is_some_and(|button| Some(button) == self.swallowed_release)
+        {
+            self.swallowed_release = None;
+            return;
+        }
+
+        if focus::is_mouse_press(e) {
+            focus::clear_focus(&mut self.focusable_buttons());
+        }
+
+        if let Some(navigation) = self.navigation.event(e) {
+            self.navigate(navigation);
+        }
+
+        self.settings_ui.event(e);
+        while let Some((id, event)) = self.settings_ui.next_event() {
+            apply_setting(settings, &self.resolutions, id, event);
+        }
+
+        for row in &mut self.rows {
+            if let Some(layout) = &row.layout {
+                row.button.event(layout, e);
+            }
+
+            while let Some(event) = row.button.next_event() {
+                if event == ButtonEvent::Click {
+                    self.waiting_for = Some(row.action);
+                    self.message = None;
+
+                    if let Some(animation) = self.prompt_animation.take() {
+                        anim_mgr.remove(animation);
+                    }
+                    self.prompt_animation = Some(anim_mgr.play(Timeline::Repeat {
+                        timeline: Box::new(Timeline::Tween(AnimationType::timed(
+                            &1.0,
+                            &0.3,
+                            PROMPT_PULSE,
+                            self.prompt_easing.clone(),
+                        ))),
+                        count: None,
+                        ping_pong: true,
+                    }));
+                }
+            }
+        }
+
+        self.back_button.event(e);
+        while let Some(event) = self.back_button.next_event() {
+            if event == ButtonEvent::Click {
+                self.closed = true;
+            }
+        }
+    }
+
+    // Focus goes down the bindings, through the settings list and on to the back button
+    fn focusable_buttons(&mut self) -> Vec<&mut Button> {
+        let mut buttons = self
+            .rows
+            .iter_mut()
+            .map(|row| &mut row.button)
+            .collect::<Vec<_>>();
+        buttons.extend(self.settings_ui.focusable_buttons());
+        buttons.push(self.back_button.button_mut());
+        buttons
+    }
+
+    fn navigate(&mut self, navigation: Navigation) {
+        if self.settings_ui.navigate(navigation) {
+            return;
+        }
+
+        let mut buttons = self.focusable_buttons();
+        match navigation {
+            Navigation::Next | Navigation::Down => focus::move_focus(&mut buttons, false),
+            Navigation::Previous | Navigation::Up => focus::move_focus(&mut buttons, true),
+            Navigation::Activate => focus::activate_focused(&mut buttons),
+            Navigation::Back => self.closed = true,
+            Navigation::Left | Navigation::Right => {}
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        c: Context,
+        g: &mut G2d,
+        glyphs: &mut Fonts,
+        settings: &Settings,
+        anim_mgr: &mut AnimationsManager,
+    ) {
+        let [win_width, win_height] = c.get_view_size();
+
+        rectangle(
+            [0.0, 0.0, 0.0, 0.75],
+            [0.0, 0.0, win_width, win_height],
+            c.transform,
+            g,
+        );
+
+        // Centred in the left half, the other one has the settings list
+        let x = (win_width / 2.0 - ROW_WIDTH) / 2.0;
+        let rows_height = self.rows.len() as f64 * (ROW_HEIGHT + ROW_SPACING);
+        let top = (win_height - rows_height) / 2.0;
+        let prompt_alpha = self
+            .prompt_animation
+            .and_then(|animation| anim_mgr.get(animation))
+            .map_or(1.0, |alpha| alpha as f32);
+
+        Text::new_color([1.0, 1.0, 1.0, 1.0], 28)
+            .draw(
+                "Key bindings",
+                glyphs,
+                &c.draw_state,
+                c.transform.trans(x, top - 30.0),
+                g,
+            )
+            .unwrap();
+
+        for (i, row) in self.rows.iter_mut().enumerate() {
+            let y = top + i as f64 * (ROW_HEIGHT + ROW_SPACING);
+            row.layout = Some(Layout::Rectangle {
+                x,
+                y,
+                dx: ROW_WIDTH,
+                dy: ROW_HEIGHT,
+            });
+
+            let waiting = self.waiting_for == Some(row.action);
+            let background = match (waiting, row.button.state()) {
+                (true, _) => ROW_WAITING_COLOR,
+                (false, ButtonState::Normal) => ROW_COLOR,
+                (false, _) => ROW_HOVERED_COLOR,
+            };
+            let current = row
+                .background_animation
+                .and_then(|animation| anim_mgr.get(animation))
+                .map_or(row.background, |color| color.0);
+
+            if background != row.background {
+                row.background = background;
+
+                let retargeted = row.background_animation.is_some_and(|animation| {
+                    anim_mgr.retarget(animation, &PerceptualColor(background))
+                });
+                if !retargeted {
+                    if let Some(animation) = row.background_animation.take() {
+                        anim_mgr.remove(animation);
+                    }
+                    row.background_animation =
+                        Some(anim_mgr.play(Timeline::Tween(AnimationType::decay(
+                            &PerceptualColor(current),
+                            &PerceptualColor(background),
+                            ROW_FADE_HALF_LIFE,
+                        ))));
+                }
+            }
+            rectangle(current, [x, y, ROW_WIDTH, ROW_HEIGHT], c.transform, g);
+            if row.button.is_focused() {
+                if let Some(layout) = &row.layout {
+                    focus::draw_focus_ring(c, g, layout);
+                }
+            }
+
+            let text_y = y + ROW_HEIGHT / 2.0 + TEXT_SIZE as f64 / 3.0;
+            Text::new_color([1.0, 1.0, 1.0, 1.0], TEXT_SIZE)
+                .draw(
+                    row.action.label(),
+                    glyphs,
+                    &c.draw_state,
+                    c.transform.trans(x + 12.0, text_y),
+                    g,
+                )
+                .unwrap();
+
+            let (binding, alpha) = if waiting {
+                ("Press a key...".to_string(), prompt_alpha)
+            } else {
+                let button = settings.key_bindings.button(row.action);
+                (key_bindings::button_name(button), 1.0)
+            };
+            let binding_width = glyphs.width(TEXT_SIZE, &binding).unwrap_or(0.0);
+
+            Text::new_color([1.0, 0.85, 0.3, alpha], TEXT_SIZE)
+                .draw(
+                    &binding,
+                    glyphs,
+                    &c.draw_state,
+                    c.transform
+                        .trans(x + ROW_WIDTH - binding_width - 12.0, text_y),
+                    g,
+                )
+                .unwrap();
+        }
+
+        if let Some(message) = &self.message {
+            Text::new_color([1.0, 0.6, 0.4, 1.0], TEXT_SIZE)
+                .draw(
+                    message,
+                    glyphs,
+                    &c.draw_state,
+                    c.transform.trans(x, top + rows_height + 24.0),
+                    g,
+                )
+                .unwrap();
+        }
+
+        self.settings_ui.render(c, g, glyphs);
+
+        let back_rect = [20.0, win_height - 80.0, 140.0, 60.0];
+        self.back_button.animate(anim_mgr);
+        self.back_button
+            .set_layout(Some(self.back_button.hit_layout(back_rect)));
+        self.back_button.render(c, g, glyphs, back_rect, 1.0);
+    }
+}
+
+fn settings_ui(settings: &Settings, resolutions: &[Resolution]) -> Ui {
+    let resolution_labels = resolutions.iter().map(|r| r.label()).collect::<Vec<_>>();
+    let resolution_labels = resolution_labels
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    let list = Widget::panel(
+        Some(Direction::Vertical),
+        vec![
+            Widget::label("General", 28),
+            Widget::checkbox(
+                "background_video",
+                "Play background videos",
+                settings.background_video,
+            ),
+            setting_row(
+                "Ruleset",
+                Widget::dropdown(
+                    "ruleset",
+                    &names(&RULESETS),
+                    selected(&RULESETS, settings.ruleset),
+                ),
+            ),
+            setting_row(
+                "Skin",
+                Widget::text_input("skin", &settings.skin, "Skin folder"),
+            ),
+            setting_row(
+                "Mania scroll speed",
+                Widget::slider(
+                    "mania_scroll_speed",
+                    settings.mania_scroll_speed as f64,
+                    1.0,
+                    40.0,
+                    1.0,
+                ),
+            ),
+            setting_row(
+                "Menu animation speed",
+                Widget::slider("animation_speed", settings.animation_speed, 0.25, 4.0, 0.05),
+            ),
+            Widget::label("Display", SECTION_SIZE),
+            setting_row(
+                "UI scale",
+                Widget::slider("ui_scale", settings.ui_scale, 0.5, 2.0, 0.05),
+            ),
+            Widget::checkbox("letterbox", "Letterbox gameplay to 4:3", settings.letterbox),
+            setting_row(
+                "Display mode",
+                Widget::dropdown(
+                    "display_mode",
+                    &names(&DISPLAY_MODES),
+                    selected(&DISPLAY_MODES, settings.display_mode),
+                ),
+            ),
+            setting_row(
+                "Resolution",
+                Widget::dropdown(
+                    "resolution",
+                    &resolution_labels,
+                    resolutions
+                        .iter()
+                        .position(|r| *r == settings.resolution)
+                        .unwrap_or(0),
+                ),
+            ),
+            Widget::checkbox("vsync", "VSync", settings.vsync),
+            setting_row(
+                "Frame limiter",
+                Widget::dropdown(
+                    "frame_limiter",
+                    &names(&FRAME_LIMITERS),
+                    selected(&FRAME_LIMITERS, settings.frame_limiter),
+                ),
+            ),
+            setting_row(
+                "Custom frame limit",
+                Widget::slider(
+                    "custom_frame_limit",
+                    settings.custom_frame_limit as f64,
+                    30.0,
+                    1000.0,
+                    10.0,
+                ),
+            ),
+            Widget::label("Input", SECTION_SIZE),
+            setting_row(
+                "Input mode",
+                Widget::dropdown(
+                    "input_mode",
+                    &names(&INPUT_MODES),
+                    selected(&INPUT_MODES, settings.input_mode),
+                ),
+            ),
+            setting_row(
+                "Mouse sensitivity",
+                Widget::slider(
+                    "cursor_sensitivity",
+                    settings.cursor_sensitivity,
+                    0.1,
+                    6.0,
+                    0.05,
+                ),
+            ),
+            Widget::checkbox(
+                "confine_cursor",
+                "Keep the cursor inside the window",
+                settings.confine_cursor,
+            ),
+            Widget::label("Cursor", SECTION_SIZE),
+            setting_row(
+                "Cursor size",
+                Widget::slider("cursor_size", settings.cursor_size, 0.5, 2.0, 0.05),
+            ),
+            setting_row(
+                "Cursor trail",
+                Widget::dropdown(
+                    "cursor_trail",
+                    &names(&CURSOR_TRAILS),
+                    selected(&CURSOR_TRAILS, settings.cursor_trail),
+                ),
+            ),
+            Widget::checkbox("cursor_rotate", "Rotate the cursor", settings.cursor_rotate),
+            Widget::checkbox(
+                "cursor_ripples",
+                "Show ripples on clicks",
+                settings.cursor_ripples,
+            ),
+            Widget::label("Skin, input and cursor changes apply after a restart", 14)
+                .color(DIM_TEXT_COLOR),
+        ],
+    )
+    .size(Size::Fill, Size::Auto)
+    .background(LIST_COLOR)
+    .padding(40.0)
+    .spacing(8.0);
+
+    Ui::new(
+        Widget::scroll(list)
+            .anchor(Anchor::Right)
+            .size(Size::Relative(0.5), Size::Fill),
+    )
+}
+
+fn names<T>(options: &[(T, &'static str)]) -> Vec<&'static str> {
+    options.iter().map(|(_, name)| *name).collect()
+}
+
+fn selected<T: PartialEq>(options: &[(T, &str)], value: T) -> usize {
+    options
+        .iter()
+        .position(|(option, _)| *option == value)
+        .unwrap_or(0)
+}
+
+// Name on the left, control on the right
+fn setting_row(name: &str, control: Widget) -> Widget {
+    Widget::panel(
+        Some(Direction::Horizontal),
+        vec![
+            Widget::label(name, TEXT_SIZE).size(Size::Fill, Size::Fill),
+            control,
+        ],
+    )
+    .size(Size::Fill, Size::Fixed(CONTROL_HEIGHT))
+}
+
+fn apply_setting(
+    settings: &mut Settings,
+    resolutions: &[Resolution],
+    id: &str,
+    event: WidgetEvent,
+) {
+    match (id, event) {
+        ("background_video", WidgetEvent::Toggle(on)) => settings.background_video = on,
+        ("ruleset", WidgetEvent::Select(index)) => settings.ruleset = RULESETS[index].0,
+        // A skin needs a name, an empty field keeps the last one
+        ("skin", WidgetEvent::TextChanged(skin)) if !skin.trim().is_empty() => {
+            settings.skin = skin.trim().to_string();
+        }
+        ("mania_scroll_speed", WidgetEvent::ValueChanged(speed)) => {
+            settings.mania_scroll_speed = speed as u32;
+        }
+        ("animation_speed", WidgetEvent::ValueChanged(speed)) => settings.animation_speed = speed,
+        ("ui_scale", WidgetEvent::ValueChanged(scale)) => settings.ui_scale = scale,
+        ("letterbox", WidgetEvent::Toggle(on)) => settings.letterbox = on,
+        ("display_mode", WidgetEvent::Select(index)) => {
+            settings.display_mode = DISPLAY_MODES[index].0;
+        }
+        ("resolution", WidgetEvent::Select(index)) => settings.resolution = resolutions[index],
+        ("vsync", WidgetEvent::Toggle(on)) => settings.vsync = on,
+        ("frame_limiter", WidgetEvent::Select(index)) => {
+            settings.frame_limiter = FRAME_LIMITERS[index].0;
+        }
+        ("custom_frame_limit", WidgetEvent::ValueChanged(limit)) => {
+            settings.custom_frame_limit = limit as u32;
+        }
+        ("input_mode", WidgetEvent::Select(index)) => settings.input_mode = INPUT_MODES[index].0,
+        ("cursor_sensitivity", WidgetEvent::ValueChanged(sensitivity)) => {
+            settings.cursor_sensitivity = sensitivity;
+        }
+        ("confine_cursor", WidgetEvent::Toggle(on)) => settings.confine_cursor = on,
+        ("cursor_size", WidgetEvent::ValueChanged(size)) => settings.cursor_size = size,
+        ("cursor_trail", WidgetEvent::Select(index)) => {
+            settings.cursor_trail = CURSOR_TRAILS[index].0;
+        }
+        ("cursor_rotate", WidgetEvent::Toggle(on)) => settings.cursor_rotate = on,
+        ("cursor_ripples", WidgetEvent::Toggle(on)) => settings.cursor_ripples = on,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston_window::{Button, ButtonArgs, ButtonState, Event, Input, Key};
+
+    fn key_event(key: Key, state: ButtonState) -> Event {
+        Event::Input(
+            Input::Button(ButtonArgs {
+                state,
+                button: Button::Keyboard(key),
+                scancode: None,
+            }),
+            None,
+        )
+    }
+
+    #[test]
+    fn escape_cancels_the_binding_prompt() {
+        let mut settings = Settings::default();
+        let mut anim_mgr = AnimationsManager::new();
+        let mut options = OptionsMenu::new(&Easings::load(), &settings, Vec::new());
+        let before = settings.key_bindings.button(GameAction::K1);
+
+        options.waiting_for = Some(GameAction::K1);
+        for state in [ButtonState::Press, ButtonState::Release] {
+            options.event(&key_event(Key::Escape, state), &mut settings, &mut anim_mgr);
+        }
+
+        assert_eq!(options.waiting_for, None);
+        assert_eq!(settings.key_bindings.button(GameAction::K1), before);
+        assert_eq!(
+            settings.key_bindings.button(GameAction::Pause),
+            Button::Keyboard(Key::Escape)
+        );
+        assert!(!options.is_closed());
+
+        options.waiting_for = Some(GameAction::K1);
+        options.event(
+            &key_event(Key::A, ButtonState::Press),
+            &mut settings,
+            &mut anim_mgr,
+        );
+        assert_eq!(
+            settings.key_bindings.button(GameAction::K1),
+            Button::Keyboard(Key::A)
+        );
+    }
+}
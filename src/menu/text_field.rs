@@ -0,0 +1,577 @@
+use crate::clipboard;
+use piston::input;
+use piston_window::{GenericEvent, Key};
+use std::ops::Range;
+
+const MAX_UNDO: usize = 100;
+
+// Held modifier keys, tracked for every event so they're right when a field gets focus
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Modifiers {
+    // Command on macOS
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl Modifiers {
+    pub fn event<E: GenericEvent>(&mut self, e: &E) {
+        if let Some(input::Button::Keyboard(key)) = e.press_args() {
+            self.set(key, true);
+        }
+        if let Some(input::Button::Keyboard(key)) = e.release_args() {
+            self.set(key, false);
+        }
+
+        // Releases while the window is in the background never arrive
+        if e.focus_args() == Some(false) {
+            *self = Self::default();
+        }
+    }
+
+    fn set(&mut self, key: Key, held: bool) {
+        match key {
+            Key::LCtrl | Key::RCtrl | Key::LGui | Key::RGui => self.ctrl = held,
+            Key::LShift | Key::RShift => self.shift = held,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum FieldEvent {
+    Changed,
+    Submit,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Edit {
+    Typing,
+    Deleting,
+}
+
+struct Snapshot {
+    text: String,
+    caret: usize,
+    anchor: usize,
+}
+
+// Editable single line of text. Positions are byte offsets, always on a char boundary
+pub(crate) struct TextField {
+    text: String,
+    caret: usize,
+    // Other end of the selection, the caret itself when nothing is selected
+    anchor: usize,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    // Runs of typing or deleting are undone together
+    last_edit: Option<Edit>,
+    // Where each char boundary is drawn, from the last layout
+    offsets: Vec<(usize, f64)>,
+    // How far the text is scrolled left to keep the caret in view
+    scroll: f64,
+    // Asked for on Ctrl+V, and put in on whichever event comes after it arrived
+    pasting: Option<clipboard::Paste>,
+}
+
+impl TextField {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            caret: text.len(),
+            anchor: text.len(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_edit: None,
+            offsets: Vec::new(),
+            scroll: 0.0,
+            pasting: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    pub fn selection(&self) -> Range<usize> {
+        self.caret.min(self.anchor)..self.caret.max(self.anchor)
+    }
+
+    pub fn scroll(&self) -> f64 {
+        self.scroll
+    }
+
+    // Replaces the selection, like typing over it
+    pub fn insert(&mut self, text: &str) {
+        // Each word typed is its own step, along with the space before it
+        if text.starts_with(char::is_whitespace) {
+            self.last_edit = None;
+        }
+        self.snapshot(Edit::Typing);
+
+        let selection = self.selection();
+        self.text.replace_range(selection.clone(), text);
+        self.caret = selection.start + text.len();
+        self.anchor = self.caret;
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.text.len();
+        self.last_edit = None;
+    }
+
+    // Moves the caret, keeping the anchor where it was to select what's in between
+    pub fn set_caret(&mut self, position: usize, extend: bool) {
+        self.caret = position.min(self.text.len());
+        if !extend {
+            self.anchor = self.caret;
+        }
+        self.last_edit = None;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo.pop() else {
+            return false;
+        };
+        let current = self.restore(snapshot);
+        self.redo.push(current);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo.pop() else {
+            return false;
+        };
+        let current = self.restore(snapshot);
+        self.undo.push(current);
+        true
+    }
+
+    pub fn copy(&self) {
+        let selection = self.selection();
+        if !selection.is_empty() {
+            clipboard::copy(&self.text[selection]);
+        }
+    }
+
+    // Goes in right away when the game itself holds the clipboard, on a later update otherwise
+    pub fn paste(&mut self) -> bool {
+        self.pasting = Some(clipboard::paste());
+        self.take_paste()
+    }
+
+    // For fields that lost focus, which shouldn't change under the user's nose
+    pub fn cancel_paste(&mut self) {
+        self.pasting = None;
+    }
+
+    fn take_paste(&mut self) -> bool {
+        let Some(pasted) = self.pasting.as_mut().and_then(clipboard::Paste::poll) else {
+            return false;
+        };
+        self.pasting = None;
+
+        // Only one line fits in a field
+        let pasted = pasted.lines().next().unwrap_or("").to_string();
+        if pasted.is_empty() && self.selection().is_empty() {
+            return false;
+        }
+
+        self.last_edit = None;
+        self.insert(&pasted);
+        self.last_edit = None;
+        true
+    }
+
+    fn delete(&mut self, range: Range<usize>) -> bool {
+        if range.is_empty() {
+            return false;
+        }
+
+        self.snapshot(Edit::Deleting);
+        self.text.replace_range(range.clone(), "");
+        self.caret = range.start;
+        self.anchor = range.start;
+        true
+    }
+
+    fn snapshot(&mut self, edit: Edit) {
+        if self.last_edit != Some(edit) {
+            self.undo.push(Snapshot {
+                text: self.text.clone(),
+                caret: self.caret,
+                anchor: self.anchor,
+            });
+            if self.undo.len() > MAX_UNDO {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+        self.last_edit = Some(edit);
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) -> Snapshot {
+        let current = Snapshot {
+            text: std::mem::replace(&mut self.text, snapshot.text),
+            caret: self.caret,
+            anchor: self.anchor,
+        };
+        self.caret = snapshot.caret;
+        self.anchor = snapshot.anchor;
+        self.last_edit = None;
+        current
+    }
+
+    fn previous_boundary(&self, word: bool) -> usize {
+        let before = &self.text[..self.caret];
+        if !word {
+            return before.char_indices().last().map_or(0, |(i, _)| i);
+        }
+
+        // Back over any spaces, then the word before them
+        let trimmed = before.trim_end_matches(|c| !is_word_char(c));
+        trimmed
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_word_char(*c))
+            .last()
+            .map_or(trimmed.len(), |(i, _)| i)
+    }
+
+    fn next_boundary(&self, word: bool) -> usize {
+        let after = &self.text[self.caret..];
+        let length = if word {
+            let skipped = after.len() - after.trim_start_matches(|c| !is_word_char(c)).len();
+            skipped
+                + after[skipped..]
+                    .find(|c| !is_word_char(c))
+                    .unwrap_or(after.len() - skipped)
+        } else {
+            after.chars().next().map_or(0, char::len_utf8)
+        };
+        self.caret + length
+    }
+
+    pub fn event<E: GenericEvent>(&mut self, e: &E, modifiers: Modifiers) -> Option<FieldEvent> {
+        if e.update_args().is_some() {
+            return self.take_paste().then_some(FieldEvent::Changed);
+        }
+
+        if let Some(typed) = e.text_args() {
+            // Shortcuts come through as control characters, or their letter on some systems
+            let typed = typed
+                .chars()
+                .filter(|c| !c.is_control())
+                .collect::<String>();
+            if !typed.is_empty() && !modifiers.ctrl {
+                self.insert(&typed);
+                return Some(FieldEvent::Changed);
+            }
+        }
+
+        let Some(input::Button::Keyboard(key)) = e.press_args() else {
+            return None;
+        };
+
+        let Modifiers { ctrl, shift } = modifiers;
+        let selection = self.selection();
+        let changed = match key {
+            // Without shift, a selection collapses to its side
+            Key::Left if !shift && !selection.is_empty() => {
+                self.set_caret(selection.start, false);
+                false
+            }
+            Key::Right if !shift && !selection.is_empty() => {
+                self.set_caret(selection.end, false);
+                false
+            }
+            Key::Left => {
+                self.set_caret(self.previous_boundary(ctrl), shift);
+                false
+            }
+            Key::Right => {
+                self.set_caret(self.next_boundary(ctrl), shift);
+                false
+            }
+            Key::Home => {
+                self.set_caret(0, shift);
+                false
+            }
+            Key::End => {
+                self.set_caret(self.text.len(), shift);
+                false
+            }
+            Key::Backspace if selection.is_empty() => {
+                self.delete(self.previous_boundary(ctrl)..self.caret)
+            }
+            Key::Delete if selection.is_empty() => {
+                self.delete(self.caret..self.next_boundary(ctrl))
+            }
+            Key::Backspace | Key::Delete => self.delete(selection),
+            Key::A if ctrl => {
+                self.select_all();
+                false
+            }
+            Key::C if ctrl => {
+                self.copy();
+                false
+            }
+            Key::X if ctrl => {
+                self.copy();
+                self.delete(selection)
+            }
+            Key::V if ctrl => self.paste(),
+            Key::Z if ctrl && shift => self.redo(),
+            Key::Z if ctrl => self.undo(),
+            Key::Y if ctrl => self.redo(),
+            Key::Return | Key::NumPadEnter => return Some(FieldEvent::Submit),
+            _ => false,
+        };
+
+        changed.then_some(FieldEvent::Changed)
+    }
+
+    // Measures where everything is drawn and scrolls the caret into a view this wide
+    pub fn layout(&mut self, width: f64, measure: &mut dyn FnMut(&str) -> f64) {
+        self.offsets.clear();
+        let mut x = 0.0;
+        for (i, c) in self.text.char_indices() {
+            self.offsets.push((i, x));
+            x += measure(c.encode_utf8(&mut [0; 4]));
+        }
+        self.offsets.push((self.text.len(), x));
+
+        let caret_x = self.x_of(self.caret);
+        let max_scroll = (x - width).max(0.0);
+        if caret_x - self.scroll > width {
+            self.scroll = caret_x - width;
+        } else if caret_x < self.scroll {
+            self.scroll = caret_x;
+        }
+        self.scroll = self.scroll.clamp(0.0, max_scroll);
+    }
+
+    // Where a position is drawn, from the start of the text
+    pub fn x_of(&self, position: usize) -> f64 {
+        match self.offsets.binary_search_by_key(&position, |(i, _)| *i) {
+            Ok(index) => self.offsets[index].1,
+            // Not laid out since the text changed
+            Err(_) => self.offsets.last().map_or(0.0, |(_, x)| *x),
+        }
+    }
+
+    // The char boundary closest to x, in the same coordinates as `x_of` but scrolled
+    pub fn position_at(&self, x: f64) -> usize {
+        let x = x + self.scroll;
+        self.offsets
+            .iter()
+            .filter(|(i, _)| *i <= self.text.len() && self.text.is_char_boundary(*i))
+            .min_by(|(_, a), (_, b)| (a - x).abs().total_cmp(&(b - x).abs()))
+            .map_or(self.text.len(), |(i, _)| *i)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston::input::{ButtonArgs, ButtonState, Event, Input, Loop, UpdateArgs};
+    use std::sync::mpsc;
+
+    const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        shift: false,
+    };
+    const SHIFT: Modifiers = Modifiers {
+        ctrl: false,
+        shift: true,
+    };
+    const CTRL_SHIFT: Modifiers = Modifiers {
+        ctrl: true,
+        shift: true,
+    };
+
+    fn press(field: &mut TextField, key: Key, modifiers: Modifiers) -> Option<FieldEvent> {
+        let e = Event::Input(
+            Input::Button(ButtonArgs {
+                state: ButtonState::Press,
+                button: input::Button::Keyboard(key),
+                scancode: None,
+            }),
+            None,
+        );
+        field.event(&e, modifiers)
+    }
+
+    fn type_text(field: &mut TextField, text: &str) {
+        for c in text.chars() {
+            let e = Event::Input(Input::Text(c.to_string()), None);
+            assert_eq!(
+                field.event(&e, Modifiers::default()),
+                Some(FieldEvent::Changed)
+            );
+        }
+    }
+
+    fn selected(field: &TextField) -> &str {
+        &field.text()[field.selection()]
+    }
+
+    #[test]
+    fn carets_step_over_whole_chars_and_words() {
+        let mut field = TextField::new("héllo wörld_2 ok");
+
+        press(&mut field, Key::Left, CTRL);
+        assert_eq!(field.caret(), "héllo wörld_2 ".len());
+        press(&mut field, Key::Left, CTRL);
+        assert_eq!(field.caret(), "héllo ".len());
+        press(&mut field, Key::Left, Modifiers::default());
+        press(&mut field, Key::Left, Modifiers::default());
+        assert_eq!(field.caret(), "héllo".len() - 1);
+        press(&mut field, Key::Right, CTRL);
+        assert_eq!(field.caret(), "héllo".len());
+        press(&mut field, Key::Right, CTRL);
+        assert_eq!(field.caret(), "héllo wörld_2".len());
+
+        press(&mut field, Key::Home, Modifiers::default());
+        press(&mut field, Key::Right, Modifiers::default());
+        press(&mut field, Key::Right, Modifiers::default());
+        assert_eq!(field.caret(), "hé".len());
+    }
+
+    #[test]
+    fn typing_replaces_the_selection() {
+        let mut field = TextField::new("one two three");
+
+        press(&mut field, Key::Left, CTRL);
+        press(&mut field, Key::Left, CTRL_SHIFT);
+        assert_eq!(selected(&field), "two ");
+        type_text(&mut field, "2 ");
+        assert_eq!(field.text(), "one 2 three");
+
+        // Collapses to the side it was moved towards
+        press(&mut field, Key::End, SHIFT);
+        press(&mut field, Key::Left, Modifiers::default());
+        assert_eq!(field.caret(), "one 2 ".len());
+        assert!(field.selection().is_empty());
+
+        press(&mut field, Key::Backspace, CTRL);
+        assert_eq!(field.text(), "one three");
+        press(&mut field, Key::Delete, CTRL);
+        assert_eq!(field.text(), "one ");
+        press(&mut field, Key::Delete, Modifiers::default());
+        assert_eq!(field.text(), "one ");
+    }
+
+    #[test]
+    fn cut_copy_and_paste_share_a_clipboard() {
+        let mut from = TextField::new("copy me");
+        let mut to = TextField::new("into ");
+
+        press(&mut from, Key::A, CTRL);
+        press(&mut from, Key::C, CTRL);
+        assert_eq!(press(&mut to, Key::V, CTRL), Some(FieldEvent::Changed));
+        assert_eq!(to.text(), "into copy me");
+
+        press(&mut to, Key::Left, CTRL_SHIFT);
+        assert_eq!(press(&mut to, Key::X, CTRL), Some(FieldEvent::Changed));
+        assert_eq!(to.text(), "into copy ");
+        press(&mut to, Key::Home, Modifiers::default());
+        press(&mut to, Key::V, CTRL);
+        assert_eq!(to.text(), "meinto copy ");
+
+        // Control characters from the shortcut don't get typed
+        let e = Event::Input(Input::Text("\u{16}".to_string()), None);
+        assert_eq!(to.event(&e, CTRL), None);
+    }
+
+    #[test]
+    fn pastes_from_other_programs_arrive_on_a_later_update() {
+        let update = Event::Loop(Loop::Update(UpdateArgs { dt: 0.016 }));
+        let mut field = TextField::new("a");
+
+        let (reply, answer) = mpsc::channel();
+        field.pasting = Some(clipboard::Paste::Waiting(answer));
+        assert_eq!(field.event(&update, Modifiers::default()), None);
+
+        reply.send(Some("bc\nd".to_string())).unwrap();
+        assert_eq!(
+            field.event(&update, Modifiers::default()),
+            Some(FieldEvent::Changed)
+        );
+        assert_eq!(field.text(), "abc");
+
+        // One that timed out, or got cancelled, leaves the field alone
+        let (reply, answer) = mpsc::channel::<Option<String>>();
+        field.pasting = Some(clipboard::Paste::Waiting(answer));
+        drop(reply);
+        assert_eq!(field.event(&update, Modifiers::default()), None);
+        assert_eq!(field.text(), "abc");
+
+        let (reply, answer) = mpsc::channel();
+        field.pasting = Some(clipboard::Paste::Waiting(answer));
+        field.cancel_paste();
+        let _ = reply.send(Some("late".to_string()));
+        assert_eq!(field.event(&update, Modifiers::default()), None);
+        assert_eq!(field.text(), "abc");
+    }
+
+    #[test]
+    fn undo_goes_back_a_word_at_a_time() {
+        let mut field = TextField::new("");
+
+        type_text(&mut field, "hello world");
+        press(&mut field, Key::Backspace, Modifiers::default());
+        press(&mut field, Key::Backspace, Modifiers::default());
+
+        assert_eq!(press(&mut field, Key::Z, CTRL), Some(FieldEvent::Changed));
+        assert_eq!(field.text(), "hello world");
+        press(&mut field, Key::Z, CTRL);
+        assert_eq!(field.text(), "hello");
+        press(&mut field, Key::Z, CTRL);
+        assert_eq!(field.text(), "");
+        assert_eq!(press(&mut field, Key::Z, CTRL), None);
+
+        press(&mut field, Key::Y, CTRL);
+        press(&mut field, Key::Z, CTRL_SHIFT);
+        assert_eq!(field.text(), "hello world");
+
+        // Something new can't be redone over
+        type_text(&mut field, "!");
+        assert_eq!(press(&mut field, Key::Y, CTRL), None);
+    }
+
+    #[test]
+    fn input_method_text_is_typed_at_the_caret() {
+        let mut field = TextField::new("ab");
+        press(&mut field, Key::Left, Modifiers::default());
+
+        // Input methods draw what they're composing themselves and commit it as text
+        type_text(&mut field, "日本");
+        assert_eq!(field.text(), "a日本b");
+        assert_eq!(field.caret(), "a日本".len());
+    }
+
+    #[test]
+    fn layout_scrolls_to_the_caret() {
+        let mut field = TextField::new("abcdefghij");
+        let mut measure = |text: &str| text.chars().count() as f64 * 10.0;
+
+        field.layout(50.0, &mut measure);
+        assert_eq!(field.scroll(), 50.0);
+        assert_eq!(field.position_at(12.0), 6);
+
+        press(&mut field, Key::Home, Modifiers::default());
+        field.layout(50.0, &mut measure);
+        assert_eq!(field.scroll(), 0.0);
+        assert_eq!(field.x_of(3), 30.0);
+        assert_eq!(field.position_at(500.0), 10);
+    }
+}
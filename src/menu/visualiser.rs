@@ -0,0 +1,133 @@
+use graphics::math::Scalar;
+use graphics::{rectangle, Context, Transformed};
+use piston_window::G2d;
+use std::f64::consts::PI;
+
+const FFT_SIZE: usize = 1024;
+const BAR_COUNT: usize = 128;
+const BAR_WIDTH: f64 = 5.0;
+// Fraction of the logo radius a fully loud bar reaches past the edge
+const BAR_MAX_LENGTH: f64 = 0.6;
+const DECAY_PER_SECOND: f64 = 2.5;
+const ROTATION_PER_SECOND: f64 = 0.1;
+
+// Radial spectrum drawn around the osu! logo
+pub(crate) struct Visualiser {
+    amplitudes: Vec<f64>,
+    rotation: f64,
+}
+
+impl Visualiser {
+    pub fn new() -> Self {
+        Self {
+            amplitudes: vec![0.0; BAR_COUNT],
+            rotation: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f64, samples: &[f32]) {
+        self.rotation = (self.rotation + ROTATION_PER_SECOND * dt * 2.0 * PI) % (2.0 * PI);
+
+        let spectrum = Self::spectrum(samples);
+
+        // Only the lower half of the useful bins carries anything worth looking at
+        let bins_per_bar = (FFT_SIZE / 4) / BAR_COUNT;
+
+        for (i, amplitude) in self.amplitudes.iter_mut().enumerate() {
+            let start = i * bins_per_bar;
+            let level = spectrum[start..start + bins_per_bar]
+                .iter()
+                .copied()
+                .fold(0.0, f64::max);
+
+            // Bars jump up instantly and fall back down smoothly
+            *amplitude = f64::max(level, *amplitude - DECAY_PER_SECOND * dt).clamp(0.0, 1.0);
+        }
+    }
+
+    pub fn render(&self, c: Context, g: &mut G2d, center: [Scalar; 2], radius: Scalar, alpha: f32) {
+        for (i, amplitude) in self.amplitudes.iter().enumerate() {
+            if *amplitude <= 0.0 {
+                continue;
+            }
+
+            let angle = self.rotation + i as f64 / BAR_COUNT as f64 * 2.0 * PI;
+            let length = amplitude * radius * BAR_MAX_LENGTH;
+
+            let transform = c
+                .transform
+                .trans(center[0], center[1])
+                .rot_rad(angle)
+                .trans(radius, -BAR_WIDTH / 2.0);
+
+            rectangle(
+                [1.0, 1.0, 1.0, alpha],
+                [0.0, 0.0, length, BAR_WIDTH],
+                transform,
+                g,
+            );
+        }
+    }
+
+    // Normalised magnitudes of the latest window of samples
+    fn spectrum(samples: &[f32]) -> Vec<f64> {
+        let mut buffer = vec![(0.0, 0.0); FFT_SIZE];
+        let offset = FFT_SIZE.saturating_sub(samples.len());
+
+        for (i, sample) in samples.iter().rev().take(FFT_SIZE).rev().enumerate() {
+            let window = 0.5 - 0.5 * (2.0 * PI * (i + offset) as f64 / FFT_SIZE as f64).cos();
+            buffer[i + offset].0 = *sample as f64 * window;
+        }
+
+        Self::fft(&mut buffer);
+
+        buffer[..FFT_SIZE / 2]
+            .iter()
+            .map(|(re, im)| (re * re + im * im).sqrt() / (FFT_SIZE as f64 / 8.0))
+            .collect()
+    }
+
+    // In-place iterative radix-2 Cooley-Tukey, the length has to be a power of two
+    fn fft(buffer: &mut [(f64, f64)]) {
+        let n = buffer.len();
+
+        let mut j = 0;
+        for i in 1..n {
+            let mut bit = n >> 1;
+            while j & bit != 0 {
+                j ^= bit;
+                bit >>= 1;
+            }
+            j |= bit;
+
+            if i < j {
+                buffer.swap(i, j);
+            }
+        }
+
+        let mut len = 2;
+        while len <= n {
+            let angle = -2.0 * PI / len as f64;
+            let (w_re, w_im) = (angle.cos(), angle.sin());
+
+            for chunk in buffer.chunks_mut(len) {
+                let (mut cur_re, mut cur_im) = (1.0, 0.0);
+
+                for k in 0..len / 2 {
+                    let (a_re, a_im) = chunk[k];
+                    let (b_re, b_im) = chunk[k + len / 2];
+                    let (t_re, t_im) =
+                        (b_re * cur_re - b_im * cur_im, b_re * cur_im + b_im * cur_re);
+
+                    chunk[k] = (a_re + t_re, a_im + t_im);
+                    chunk[k + len / 2] = (a_re - t_re, a_im - t_im);
+
+                    (cur_re, cur_im) =
+                        (cur_re * w_re - cur_im * w_im, cur_re * w_im + cur_im * w_re);
+                }
+            }
+
+            len <<= 1;
+        }
+    }
+}
@@ -0,0 +1,1654 @@
+use super::button::{Button, ButtonEvent, ButtonState, Layout};
+use super::focus::{self, Navigation};
+use super::text_field::{FieldEvent, Modifiers, TextField};
+use crate::text::{self, Fonts, Measure};
+use crate::viewport::clip;
+use graphics::character::CharacterCache;
+use graphics::types::Color;
+use graphics::{Context, Ellipse, Rectangle};
+use piston_window::{G2d, GenericEvent};
+use std::collections::VecDeque;
+
+pub(crate) const TEXT_SIZE: u32 = 18;
+pub(crate) const CONTROL_HEIGHT: f64 = 32.0;
+const CONTROL_WIDTH: f64 = 220.0;
+const TEXT_PADDING: f64 = 12.0;
+const CHECKBOX_SIZE: f64 = 20.0;
+const SLIDER_VALUE_WIDTH: f64 = 56.0;
+const SLIDER_HANDLE_RADIUS: f64 = 8.0;
+const SLIDER_TRACK_HEIGHT: f64 = 4.0;
+const SCROLL_STEP: f64 = 40.0;
+const SCROLLBAR_WIDTH: f64 = 4.0;
+const TEXT_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+pub(crate) const DIM_TEXT_COLOR: Color = [1.0, 1.0, 1.0, 0.4];
+const ACCENT_COLOR: Color = [0.20, 0.60, 0.87, 1.0];
+const CONTROL_COLOR: Color = [1.0, 1.0, 1.0, 0.08];
+const CONTROL_HOVERED_COLOR: Color = [1.0, 1.0, 1.0, 0.16];
+const CONTROL_PRESSED_COLOR: Color = [1.0, 1.0, 1.0, 0.24];
+const DROPDOWN_LIST_COLOR: Color = [0.12, 0.12, 0.15, 0.95];
+const SELECTION_COLOR: Color = [0.20, 0.60, 0.87, 0.5];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Size {
+    // Whatever the content needs
+    Auto,
+    Fixed(f64),
+    // Fraction of the parent's content area, the window for the root
+    Relative(f64),
+    // All of the parent, or what's left of it along a stack
+    Fill,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Centre,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // How far along the free space the widget sits, on each axis
+    fn factors(self) -> [f64; 2] {
+        match self {
+            Anchor::TopLeft => [0.0, 0.0],
+            Anchor::Top => [0.5, 0.0],
+            Anchor::TopRight => [1.0, 0.0],
+            Anchor::Left => [0.0, 0.5],
+            Anchor::Centre => [0.5, 0.5],
+            Anchor::Right => [1.0, 0.5],
+            Anchor::BottomLeft => [0.0, 1.0],
+            Anchor::Bottom => [0.5, 1.0],
+            Anchor::BottomRight => [1.0, 1.0],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Direction {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum WidgetEvent {
+    Toggle(bool),
+    ValueChanged(f64),
+    Select(usize),
+    TextChanged(String),
+    Submit(String),
+}
+
+enum WidgetKind {
+    Label {
+        text: String,
+        size: u32,
+        color: Color,
+    },
+    // Checked state lives in the widget's toggleable button
+    Checkbox {
+        label: String,
+    },
+    Slider {
+        value: f64,
+        min: f64,
+        max: f64,
+        step: f64,
+    },
+    Dropdown {
+        options: Vec<String>,
+        selected: usize,
+        open: bool,
+        // Option picked by Enter, following the cursor and arrow keys
+        highlighted: usize,
+        items: Vec<Button>,
+    },
+    TextInput {
+        field: TextField,
+        placeholder: String,
+    },
+    Scroll {
+        child: Box<Widget>,
+        scroll: f64,
+        content_height: f64,
+        // Tab order position of the last focused widget scrolled into view, to only do it once
+        followed_focus: Option<usize>,
+    },
+    Panel {
+        children: Vec<Widget>,
+        // Children get stacked one after the other, or all placed by their anchors
+        direction: Option<Direction>,
+        spacing: f64,
+        background: Option<Color>,
+    },
+}
+
+// One node of a retained widget tree. Widgets are built once, laid out every frame against the
+// window and report what happened to them through their id
+pub(crate) struct Widget {
+    id: &'static str,
+    kind: WidgetKind,
+    anchor: Anchor,
+    offset: [f64; 2],
+    width: Size,
+    height: Size,
+    padding: f64,
+    button: Button,
+    rect: [f64; 4],
+}
+
+impl Widget {
+    fn new(id: &'static str, kind: WidgetKind) -> Self {
+        let toggleable = matches!(kind, WidgetKind::Checkbox { .. });
+
+        Self {
+            id,
+            kind,
+            anchor: Anchor::TopLeft,
+            offset: [0.0, 0.0],
+            width: Size::Auto,
+            height: Size::Auto,
+            padding: 0.0,
+            button: Button::new(toggleable),
+            rect: [0.0; 4],
+        }
+    }
+
+    pub fn label(text: &str, size: u32) -> Self {
+        Self::new(
+            "",
+            WidgetKind::Label {
+                text: text.to_string(),
+                size,
+                color: TEXT_COLOR,
+            },
+        )
+    }
+
+    pub fn checkbox(id: &'static str, label: &str, checked: bool) -> Self {
+        let mut widget = Self::new(
+            id,
+            WidgetKind::Checkbox {
+                label: label.to_string(),
+            },
+        );
+        widget.button.set_toggle_state(checked);
+        widget
+    }
+
+    pub fn slider(id: &'static str, value: f64, min: f64, max: f64, step: f64) -> Self {
+        Self::new(
+            id,
+            WidgetKind::Slider {
+                value: value.clamp(min, max),
+                min,
+                max,
+                step,
+            },
+        )
+    }
+
+    pub fn dropdown(id: &'static str, options: &[&str], selected: usize) -> Self {
+        Self::new(
+            id,
+            WidgetKind::Dropdown {
+                options: options.iter().map(|option| option.to_string()).collect(),
+                selected,
+                open: false,
+                highlighted: selected,
+                items: options.iter().map(|_| Button::new(false)).collect(),
+            },
+        )
+    }
+
+    pub fn text_input(id: &'static str, text: &str, placeholder: &str) -> Self {
+        Self::new(
+            id,
+            WidgetKind::TextInput {
+                field: TextField::new(text),
+                placeholder: placeholder.to_string(),
+            },
+        )
+    }
+
+    pub fn scroll(child: Widget) -> Self {
+        Self::new(
+            "",
+            WidgetKind::Scroll {
+                child: Box::new(child),
+                scroll: 0.0,
+                content_height: 0.0,
+                followed_focus: None,
+            },
+        )
+    }
+
+    pub fn panel(direction: Option<Direction>, children: Vec<Widget>) -> Self {
+        Self::new(
+            "",
+            WidgetKind::Panel {
+                children,
+                direction,
+                spacing: 0.0,
+                background: None,
+            },
+        )
+    }
+
+    pub fn anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn size(mut self, width: Size, height: Size) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn padding(mut self, padding: f64) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn spacing(mut self, spacing: f64) -> Self {
+        if let WidgetKind::Panel { spacing: s, .. } = &mut self.kind {
+            *s = spacing;
+        }
+        self
+    }
+
+    pub fn background(mut self, color: Color) -> Self {
+        if let WidgetKind::Panel { background, .. } = &mut self.kind {
+            *background = Some(color);
+        }
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        if let WidgetKind::Label { color: c, .. } = &mut self.kind {
+            *c = color;
+        }
+        self
+    }
+
+    fn layout(&self) -> Layout {
+        let [x, y, dx, dy] = self.rect;
+        Layout::Rectangle { x, y, dx, dy }
+    }
+
+    fn content_rect(&self) -> [f64; 4] {
+        let [x, y, w, h] = self.rect;
+        let padding = self.padding;
+        [
+            x + padding,
+            y + padding,
+            (w - padding * 2.0).max(0.0),
+            (h - padding * 2.0).max(0.0),
+        ]
+    }
+
+    fn natural_size(&self, measure: &mut Measure) -> [f64; 2] {
+        let [width, height] = match &self.kind {
+            WidgetKind::Label { text, size, .. } => [measure(*size, text), *size as f64 * 1.5],
+            WidgetKind::Checkbox { label } => [
+                CHECKBOX_SIZE + TEXT_PADDING + measure(TEXT_SIZE, label),
+                CONTROL_HEIGHT,
+            ],
+            WidgetKind::Slider { .. }
+            | WidgetKind::Dropdown { .. }
+            | WidgetKind::TextInput { .. } => [CONTROL_WIDTH, CONTROL_HEIGHT],
+            WidgetKind::Scroll { child, .. } => child.preferred_size(measure),
+            WidgetKind::Panel {
+                children,
+                direction,
+                spacing,
+                ..
+            } => {
+                let sizes = children
+                    .iter()
+                    .map(|child| child.preferred_size(measure))
+                    .collect::<Vec<_>>();
+                let gaps = *spacing * sizes.len().saturating_sub(1) as f64;
+                let max = |axis: usize| sizes.iter().map(|size| size[axis]).fold(0.0, f64::max);
+                let sum = |axis: usize| sizes.iter().map(|size| size[axis]).sum::<f64>() + gaps;
+
+                match direction {
+                    Some(Direction::Vertical) => [max(0), sum(1)],
+                    Some(Direction::Horizontal) => [sum(0), max(1)],
+                    None => [max(0), max(1)],
+                }
+            }
+        };
+
+        [width + self.padding * 2.0, height + self.padding * 2.0]
+    }
+
+    // Size asked of a parent that's still working out its own
+    fn preferred_size(&self, measure: &mut Measure) -> [f64; 2] {
+        let natural = self.natural_size(measure);
+        let pick = |size: Size, natural: f64| match size {
+            Size::Fixed(value) => value,
+            _ => natural,
+        };
+        [pick(self.width, natural[0]), pick(self.height, natural[1])]
+    }
+
+    // Sizes and anchors the widget inside of an area
+    fn layout_in(&mut self, [x, y, w, h]: [f64; 4], measure: &mut Measure) {
+        let natural = self.natural_size(measure);
+        let width = resolve(self.width, w, natural[0]);
+        let height = resolve(self.height, h, natural[1]);
+        let [fx, fy] = self.anchor.factors();
+
+        self.set_rect(
+            [
+                x + (w - width) * fx + self.offset[0],
+                y + (h - height) * fy + self.offset[1],
+                width,
+                height,
+            ],
+            measure,
+        );
+    }
+
+    fn set_rect(&mut self, rect: [f64; 4], measure: &mut Measure) {
+        self.rect = rect;
+        let content = self.content_rect();
+
+        match &mut self.kind {
+            WidgetKind::Panel {
+                children,
+                direction: Some(direction),
+                spacing,
+                ..
+            } => stack(children, *direction, *spacing, content, measure),
+            WidgetKind::Panel { children, .. } => {
+                for child in children {
+                    child.layout_in(content, measure);
+                }
+            }
+            WidgetKind::Scroll {
+                child,
+                scroll,
+                content_height,
+                followed_focus,
+            } => {
+                let natural = child.natural_size(measure);
+                let width = resolve(child.width, content[2], natural[0]);
+                let height = resolve(child.height, content[3], natural[1]);
+                let max_scroll = (height - content[3]).max(0.0);
+
+                *content_height = height;
+                *scroll = scroll.clamp(0.0, max_scroll);
+                child.set_rect([content[0], content[1] - *scroll, width, height], measure);
+
+                // Newly focused widgets get scrolled into view, the wheel can move away after
+                let mut focusable = Vec::new();
+                child.focusable_widgets(&mut focusable);
+                let focused = focusable
+                    .iter()
+                    .position(|widget| widget.button.is_focused())
+                    .map(|position| (position, focusable[position].rect));
+                match focused {
+                    Some((position, [_, y, _, h])) if Some(position) != *followed_focus => {
+                        *followed_focus = Some(position);
+                        let above = content[1] - y;
+                        let below = y + h - (content[1] + content[3]);
+                        if above > 0.0 || below > 0.0 {
+                            let shift = if above > 0.0 { -above } else { below };
+                            *scroll = (*scroll + shift).clamp(0.0, max_scroll);
+                            child.set_rect(
+                                [content[0], content[1] - *scroll, width, height],
+                                measure,
+                            );
+                        }
+                    }
+                    Some(_) => {}
+                    None => *followed_focus = None,
+                }
+            }
+            WidgetKind::TextInput { field, .. } => {
+                let width = (rect[2] - TEXT_PADDING * 2.0).max(0.0);
+                field.layout(width, &mut |text| measure(TEXT_SIZE, text));
+            }
+            _ => {}
+        }
+    }
+
+    fn open_dropdown(&mut self) -> Option<&mut Widget> {
+        if let WidgetKind::Dropdown { open: true, .. } = self.kind {
+            return Some(self);
+        }
+
+        match &mut self.kind {
+            WidgetKind::Scroll { child, .. } => child.open_dropdown(),
+            WidgetKind::Panel { children, .. } => {
+                children.iter_mut().find_map(Widget::open_dropdown)
+            }
+            _ => None,
+        }
+    }
+
+    fn focused_widget(&mut self) -> Option<&mut Widget> {
+        if self.button.is_focused() {
+            return Some(self);
+        }
+
+        match &mut self.kind {
+            WidgetKind::Scroll { child, .. } => child.focused_widget(),
+            WidgetKind::Panel { children, .. } => {
+                children.iter_mut().find_map(Widget::focused_widget)
+            }
+            _ => None,
+        }
+    }
+
+    // Buttons of the widgets that can take keyboard focus, in tab order
+    fn focusable_buttons<'a>(&'a mut self, buttons: &mut Vec<&'a mut Button>) {
+        match &mut self.kind {
+            WidgetKind::Label { .. } => {}
+            WidgetKind::Scroll { child, .. } => child.focusable_buttons(buttons),
+            WidgetKind::Panel { children, .. } => {
+                for child in children {
+                    child.focusable_buttons(buttons);
+                }
+            }
+            _ => buttons.push(&mut self.button),
+        }
+    }
+
+    fn focusable_widgets<'a>(&'a self, widgets: &mut Vec<&'a Widget>) {
+        match &self.kind {
+            WidgetKind::Label { .. } => {}
+            WidgetKind::Scroll { child, .. } => child.focusable_widgets(widgets),
+            WidgetKind::Panel { children, .. } => {
+                for child in children {
+                    child.focusable_widgets(widgets);
+                }
+            }
+            _ => widgets.push(self),
+        }
+    }
+
+    // Keys for the focused widget, false when it has no use for them
+    fn navigate(&mut self, navigation: Navigation, ctx: &mut EventContext) -> bool {
+        let step_direction = match navigation {
+            Navigation::Left => -1.0,
+            Navigation::Right => 1.0,
+            _ => 0.0,
+        };
+
+        match &mut self.kind {
+            WidgetKind::Checkbox { .. } if navigation == Navigation::Activate => {
+                self.button.activate();
+                self.handle_button_events(ctx);
+                true
+            }
+            WidgetKind::Slider {
+                value,
+                min,
+                max,
+                step,
+            } if step_direction != 0.0 => {
+                let new_value =
+                    (*value + key_step(*min, *max, *step) * step_direction).clamp(*min, *max);
+                if new_value != *value {
+                    *value = new_value;
+                    ctx.push(self.id, WidgetEvent::ValueChanged(new_value));
+                }
+                true
+            }
+            WidgetKind::Dropdown {
+                options,
+                selected,
+                open,
+                highlighted,
+                ..
+            } if navigation == Navigation::Activate => {
+                // Nothing to pick from an empty list
+                *open = !options.is_empty();
+                *highlighted = *selected;
+                true
+            }
+            WidgetKind::Dropdown {
+                options, selected, ..
+            } if step_direction != 0.0 => {
+                let new_selected = selected
+                    .saturating_add_signed(step_direction as isize)
+                    .min(options.len().saturating_sub(1));
+                if new_selected != *selected && !options.is_empty() {
+                    *selected = new_selected;
+                    ctx.push(self.id, WidgetEvent::Select(new_selected));
+                }
+                true
+            }
+            // Typed into the field instead
+            WidgetKind::TextInput { .. }
+                if matches!(
+                    navigation,
+                    Navigation::Activate | Navigation::Left | Navigation::Right
+                ) =>
+            {
+                true
+            }
+            WidgetKind::TextInput { .. } if navigation == Navigation::Back => {
+                self.button.set_focused(false);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn navigate_list(&mut self, navigation: Navigation, ctx: &mut EventContext) {
+        let WidgetKind::Dropdown {
+            options,
+            selected,
+            open,
+            highlighted,
+            ..
+        } = &mut self.kind
+        else {
+            return;
+        };
+
+        match navigation {
+            Navigation::Previous | Navigation::Up | Navigation::Left => {
+                *highlighted = highlighted.saturating_sub(1);
+            }
+            Navigation::Next | Navigation::Down | Navigation::Right => {
+                *highlighted = (*highlighted + 1).min(options.len().saturating_sub(1));
+            }
+            Navigation::Activate => {
+                *open = false;
+                if *highlighted < options.len() {
+                    *selected = *highlighted;
+                    ctx.push(self.id, WidgetEvent::Select(*selected));
+                }
+            }
+            Navigation::Back => *open = false,
+        }
+    }
+
+    fn handle_button_events(&mut self, ctx: &mut EventContext) {
+        while let Some(event) = self.button.next_event() {
+            match event {
+                ButtonEvent::Press => {
+                    ctx.handled = true;
+                    if let WidgetKind::TextInput { field, .. } = &mut self.kind {
+                        self.button.set_focused(true);
+                        let position =
+                            field.position_at(ctx.cursor[0] - self.rect[0] - TEXT_PADDING);
+                        field.set_caret(position, ctx.modifiers.shift);
+                    }
+                }
+                ButtonEvent::Click => {
+                    if let WidgetKind::Dropdown {
+                        options,
+                        open,
+                        selected,
+                        highlighted,
+                        ..
+                    } = &mut self.kind
+                    {
+                        *open = !*open && !options.is_empty();
+                        *highlighted = *selected;
+                    }
+                }
+                ButtonEvent::Toggle(checked) => ctx.push(self.id, WidgetEvent::Toggle(checked)),
+                _ => {}
+            }
+        }
+    }
+
+    fn event<E: GenericEvent>(&mut self, e: &E, ctx: &mut EventContext) {
+        // Presses and scrolling stop at the first widget that takes them
+        let blocked = ctx.handled && is_consumable(e);
+        let layout = self.layout();
+        let inside = layout.mouse_inside(ctx.cursor);
+
+        match &mut self.kind {
+            WidgetKind::Label { .. } => return,
+            WidgetKind::Panel {
+                children,
+                background,
+                ..
+            } => {
+                // Later children are drawn on top, so they get the first go
+                for child in children.iter_mut().rev() {
+                    child.event(e, ctx);
+                }
+
+                // Panels with a background hide whatever is behind them
+                if background.is_some() && !blocked && is_consumable(e) && inside {
+                    ctx.handled = true;
+                }
+                return;
+            }
+            WidgetKind::Scroll {
+                child,
+                scroll,
+                content_height,
+                ..
+            } => {
+                // Children scrolled out of view can't be clicked
+                if inside || !is_consumable(e) {
+                    child.event(e, ctx);
+                }
+
+                if let Some([_, dy]) = e.mouse_scroll_args() {
+                    if inside && !ctx.handled {
+                        let max_scroll = (*content_height - self.rect[3]).max(0.0);
+                        *scroll = (*scroll - dy * SCROLL_STEP).clamp(0.0, max_scroll);
+                        ctx.handled = true;
+                    }
+                }
+                return;
+            }
+            // Open lists are handled before everything else, by the Ui
+            WidgetKind::Dropdown { open: true, .. } if !ctx.overlay => return,
+            _ => {}
+        }
+
+        if !blocked {
+            self.button.event(&layout, e);
+        }
+
+        self.handle_button_events(ctx);
+
+        match &mut self.kind {
+            WidgetKind::Slider {
+                value,
+                min,
+                max,
+                step,
+            } if self.button.state() == ButtonState::Pressed => {
+                let [x, _, w, _] = self.rect;
+                let track_width = (w - SLIDER_VALUE_WIDTH).max(1.0);
+                let progress = ((ctx.cursor[0] - x) / track_width).clamp(0.0, 1.0);
+                let new_value =
+                    snap(*min + progress * (*max - *min), *min, *step).clamp(*min, *max);
+
+                if new_value != *value {
+                    *value = new_value;
+                    ctx.push(self.id, WidgetEvent::ValueChanged(new_value));
+                }
+            }
+            WidgetKind::Dropdown {
+                selected,
+                open,
+                highlighted,
+                items,
+                ..
+            } if *open => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    let [x, y, dx, dy] = dropdown_item_rect(self.rect, index);
+                    item.event(&Layout::Rectangle { x, y, dx, dy }, e);
+                    if e.mouse_cursor_args().is_some() && item.state() != ButtonState::Normal {
+                        *highlighted = index;
+                    }
+
+                    while let Some(event) = item.next_event() {
+                        if event == ButtonEvent::Click {
+                            *selected = index;
+                            *open = false;
+                            ctx.push(self.id, WidgetEvent::Select(index));
+                        }
+                    }
+                }
+
+                // Clicking anywhere else just closes the list
+                let [x, y, dx, dy] = self.rect;
+                let list = Layout::Rectangle {
+                    x,
+                    y,
+                    dx,
+                    dy: dy * (items.len() + 1) as f64,
+                };
+                if focus::is_mouse_press(e) && !list.mouse_inside(ctx.cursor) {
+                    *open = false;
+                }
+            }
+            WidgetKind::TextInput { field, .. } => {
+                // Dragging from a press selects
+                if self.button.state() == ButtonState::Pressed && e.mouse_cursor_args().is_some() {
+                    let position = field.position_at(ctx.cursor[0] - self.rect[0] - TEXT_PADDING);
+                    field.set_caret(position, true);
+                }
+
+                if !self.button.is_focused() {
+                    field.cancel_paste();
+                    return;
+                }
+
+                match field.event(e, ctx.modifiers) {
+                    Some(FieldEvent::Changed) => {
+                        ctx.push(self.id, WidgetEvent::TextChanged(field.text().to_string()));
+                    }
+                    Some(FieldEvent::Submit) => {
+                        ctx.push(self.id, WidgetEvent::Submit(field.text().to_string()));
+                        self.button.set_focused(false);
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts) {
+        let [x, y, w, h] = self.rect;
+        let background = match self.button.state() {
+            ButtonState::Normal => CONTROL_COLOR,
+            ButtonState::Hovered => CONTROL_HOVERED_COLOR,
+            ButtonState::Pressed => CONTROL_PRESSED_COLOR,
+        };
+
+        match &self.kind {
+            WidgetKind::Label { text, size, color } => {
+                let [x, y, _, h] = self.content_rect();
+                draw_text(c, g, glyphs, text, *size, *color, [x, y, h]);
+            }
+            WidgetKind::Checkbox { label } => {
+                let box_rect = [
+                    x,
+                    y + (h - CHECKBOX_SIZE) / 2.0,
+                    CHECKBOX_SIZE,
+                    CHECKBOX_SIZE,
+                ];
+                Rectangle::new(background).draw(box_rect, &c.draw_state, c.transform, g);
+
+                if self.button.toggle_state() {
+                    let [bx, by, bw, bh] = box_rect;
+                    Rectangle::new(ACCENT_COLOR).draw(
+                        [bx + 4.0, by + 4.0, bw - 8.0, bh - 8.0],
+                        &c.draw_state,
+                        c.transform,
+                        g,
+                    );
+                }
+
+                let label_x = x + CHECKBOX_SIZE + TEXT_PADDING;
+                draw_text(c, g, glyphs, label, TEXT_SIZE, TEXT_COLOR, [label_x, y, h]);
+            }
+            WidgetKind::Slider {
+                value,
+                min,
+                max,
+                step,
+            } => {
+                let track_width = (w - SLIDER_VALUE_WIDTH).max(1.0);
+                let progress = if max > min {
+                    (value - min) / (max - min)
+                } else {
+                    0.0
+                };
+                let track_y = y + (h - SLIDER_TRACK_HEIGHT) / 2.0;
+
+                Rectangle::new(CONTROL_HOVERED_COLOR).draw(
+                    [x, track_y, track_width, SLIDER_TRACK_HEIGHT],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+                Rectangle::new(ACCENT_COLOR).draw(
+                    [x, track_y, track_width * progress, SLIDER_TRACK_HEIGHT],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+
+                let radius = match self.button.state() {
+                    ButtonState::Normal => SLIDER_HANDLE_RADIUS,
+                    _ => SLIDER_HANDLE_RADIUS * 1.25,
+                };
+                Ellipse::new(TEXT_COLOR).draw(
+                    [
+                        x + track_width * progress - radius,
+                        y + h / 2.0 - radius,
+                        radius * 2.0,
+                        radius * 2.0,
+                    ],
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+
+                // As many decimals as the step has
+                let decimals = (-key_step(*min, *max, *step).log10())
+                    .ceil()
+                    .clamp(0.0, 4.0) as usize;
+                let text = format!("{:.*}", decimals, value);
+                let text_width = glyphs.width(TEXT_SIZE, &text).unwrap_or(0.0);
+                let text_x = x + w - text_width;
+                draw_text(c, g, glyphs, &text, TEXT_SIZE, TEXT_COLOR, [text_x, y, h]);
+            }
+            WidgetKind::Dropdown {
+                options, selected, ..
+            } => {
+                Rectangle::new(background).draw(self.rect, &c.draw_state, c.transform, g);
+                let text_x = x + TEXT_PADDING;
+                let arrow_x = x + w - TEXT_PADDING - glyphs.width(TEXT_SIZE, "v").unwrap_or(0.0);
+                draw_text(
+                    c,
+                    g,
+                    glyphs,
+                    options.get(*selected).map_or("", String::as_str),
+                    TEXT_SIZE,
+                    TEXT_COLOR,
+                    [text_x, y, h],
+                );
+                draw_text(
+                    c,
+                    g,
+                    glyphs,
+                    "v",
+                    TEXT_SIZE,
+                    DIM_TEXT_COLOR,
+                    [arrow_x, y, h],
+                );
+            }
+            WidgetKind::TextInput { field, placeholder } => {
+                Rectangle::new(background).draw(self.rect, &c.draw_state, c.transform, g);
+                let focused = self.button.is_focused();
+                let text_x = x + TEXT_PADDING;
+
+                if focused {
+                    Rectangle::new_border(ACCENT_COLOR, 1.0).draw(
+                        self.rect,
+                        &c.draw_state,
+                        c.transform,
+                        g,
+                    );
+                }
+
+                if field.text().is_empty() && !focused {
+                    draw_text(
+                        c,
+                        g,
+                        glyphs,
+                        placeholder,
+                        TEXT_SIZE,
+                        DIM_TEXT_COLOR,
+                        [text_x, y, h],
+                    );
+                } else {
+                    let c = clip(c, [text_x, y, (w - TEXT_PADDING * 2.0).max(0.0), h]);
+                    self.render_field(c, g, glyphs, field, focused);
+                }
+            }
+            WidgetKind::Scroll {
+                child,
+                scroll,
+                content_height,
+                ..
+            } => {
+                child.render(clip(c, self.rect), g, glyphs);
+
+                if *content_height > h {
+                    let thumb_height = h * h / content_height;
+                    let thumb_y = y + scroll / content_height * h;
+                    Rectangle::new(CONTROL_PRESSED_COLOR).draw(
+                        [
+                            x + w - SCROLLBAR_WIDTH,
+                            thumb_y,
+                            SCROLLBAR_WIDTH,
+                            thumb_height,
+                        ],
+                        &c.draw_state,
+                        c.transform,
+                        g,
+                    );
+                }
+            }
+            WidgetKind::Panel {
+                children,
+                background,
+                ..
+            } => {
+                if let Some(background) = background {
+                    Rectangle::new(*background).draw(self.rect, &c.draw_state, c.transform, g);
+                }
+
+                for child in children {
+                    child.render(c, g, glyphs);
+                }
+            }
+        }
+
+        // Text inputs show focus with their own border
+        if self.button.is_focused() && !matches!(self.kind, WidgetKind::TextInput { .. }) {
+            focus::draw_focus_ring(c, g, &self.layout());
+        }
+    }
+
+    // Text scrolled to the caret, with the selection behind it
+    fn render_field(
+        &self,
+        c: Context,
+        g: &mut G2d,
+        glyphs: &mut Fonts,
+        field: &TextField,
+        focused: bool,
+    ) {
+        let [x, y, _, h] = self.rect;
+        let origin = x + TEXT_PADDING - field.scroll();
+        let selection = field.selection();
+
+        if focused && !selection.is_empty() {
+            let start = origin + field.x_of(selection.start);
+            let end = origin + field.x_of(selection.end);
+            Rectangle::new(SELECTION_COLOR).draw(
+                [start, y + h * 0.2, end - start, h * 0.6],
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+
+        let caret_x = origin + field.x_of(field.caret());
+        draw_text(
+            c,
+            g,
+            glyphs,
+            field.text(),
+            TEXT_SIZE,
+            TEXT_COLOR,
+            [origin, y, h],
+        );
+
+        if focused {
+            Rectangle::new(TEXT_COLOR).draw(
+                [caret_x, y + h * 0.2, 2.0, h * 0.6],
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+    }
+
+    fn render_dropdown_list(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts) {
+        let WidgetKind::Dropdown {
+            options,
+            highlighted,
+            ..
+        } = &self.kind
+        else {
+            return;
+        };
+
+        for (index, option) in options.iter().enumerate() {
+            let rect = dropdown_item_rect(self.rect, index);
+            let color = if index == *highlighted {
+                ACCENT_COLOR
+            } else {
+                DROPDOWN_LIST_COLOR
+            };
+            Rectangle::new(color).draw(rect, &c.draw_state, c.transform, g);
+
+            let [x, y, _, h] = rect;
+            draw_text(
+                c,
+                g,
+                glyphs,
+                option,
+                TEXT_SIZE,
+                TEXT_COLOR,
+                [x + TEXT_PADDING, y, h],
+            );
+        }
+    }
+}
+
+struct EventContext<'a> {
+    events: &'a mut VecDeque<(&'static str, WidgetEvent)>,
+    cursor: [f64; 2],
+    modifiers: Modifiers,
+    // Set once a press or scroll has been taken by a widget
+    handled: bool,
+    // Whether the open dropdown list is being handled
+    overlay: bool,
+}
+
+impl EventContext<'_> {
+    fn push(&mut self, id: &'static str, event: WidgetEvent) {
+        self.events.push_back((id, event));
+    }
+}
+
+// Root of a widget tree, handing it events and laying it out in the window
+pub(crate) struct Ui {
+    root: Widget,
+    events: VecDeque<(&'static str, WidgetEvent)>,
+    cursor: [f64; 2],
+    modifiers: Modifiers,
+}
+
+impl Ui {
+    pub fn new(root: Widget) -> Self {
+        Self {
+            root,
+            events: VecDeque::new(),
+            cursor: [f64::NEG_INFINITY; 2],
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    pub fn event<E: GenericEvent>(&mut self, e: &E) {
+        if let Some(cursor) = e.mouse_cursor_args() {
+            self.cursor = cursor;
+        }
+        self.modifiers.event(e);
+
+        // Clicking anywhere but a text input takes keyboard focus away
+        if focus::is_mouse_press(e) {
+            focus::clear_focus(&mut self.focusable_buttons());
+        }
+
+        let mut ctx = EventContext {
+            events: &mut self.events,
+            cursor: self.cursor,
+            modifiers: self.modifiers,
+            handled: false,
+            overlay: true,
+        };
+
+        // An open dropdown list is on top of everything, and closes on clicks anywhere else
+        if let Some(dropdown) = self.root.open_dropdown() {
+            dropdown.event(e, &mut ctx);
+            if is_consumable(e) || e.release_args().is_some() {
+                return;
+            }
+        }
+
+        ctx.overlay = false;
+        self.root.event(e, &mut ctx);
+    }
+
+    // Arrows, Enter and Escape for the focused widget or open dropdown list. Returns false when
+    // they're left for the owner, to move focus or go back
+    pub fn navigate(&mut self, navigation: Navigation) -> bool {
+        let mut ctx = EventContext {
+            events: &mut self.events,
+            cursor: self.cursor,
+            modifiers: self.modifiers,
+            handled: false,
+            overlay: true,
+        };
+
+        if let Some(dropdown) = self.root.open_dropdown() {
+            dropdown.navigate_list(navigation, &mut ctx);
+            return true;
+        }
+
+        self.root
+            .focused_widget()
+            .is_some_and(|widget| widget.navigate(navigation, &mut ctx))
+    }
+
+    pub fn focusable_buttons(&mut self) -> Vec<&mut Button> {
+        let mut buttons = Vec::new();
+        self.root.focusable_buttons(&mut buttons);
+        buttons
+    }
+
+    pub fn next_event(&mut self) -> Option<(&'static str, WidgetEvent)> {
+        self.events.pop_front()
+    }
+
+    pub fn layout(&mut self, view_size: [f64; 2], measure: &mut Measure) {
+        self.root
+            .layout_in([0.0, 0.0, view_size[0], view_size[1]], measure);
+    }
+
+    pub fn render(&mut self, c: Context, g: &mut G2d, glyphs: &mut Fonts) {
+        self.layout(c.get_view_size(), &mut |size, text| {
+            glyphs.width(size, text).unwrap_or(0.0)
+        });
+        self.root.render(c, g, glyphs);
+
+        if let Some(dropdown) = self.root.open_dropdown() {
+            dropdown.render_dropdown_list(c, g, glyphs);
+        }
+    }
+}
+
+fn resolve(size: Size, available: f64, natural: f64) -> f64 {
+    match size {
+        Size::Auto => natural,
+        Size::Fixed(value) => value,
+        Size::Relative(fraction) => available * fraction,
+        Size::Fill => available,
+    }
+}
+
+// Places children one after the other, fill ones sharing whatever space the others leave
+fn stack(
+    children: &mut [Widget],
+    direction: Direction,
+    spacing: f64,
+    [x, y, w, h]: [f64; 4],
+    measure: &mut Measure,
+) {
+    let (main, cross) = match direction {
+        Direction::Horizontal => (0, 1),
+        Direction::Vertical => (1, 0),
+    };
+    let origin = [x, y];
+    let area = [w, h];
+
+    let naturals = children
+        .iter()
+        .map(|child| child.natural_size(measure))
+        .collect::<Vec<_>>();
+
+    let mut taken = spacing * children.len().saturating_sub(1) as f64;
+    let mut fills = 0;
+    for (child, natural) in children.iter().zip(&naturals) {
+        match [child.width, child.height][main] {
+            Size::Fill => fills += 1,
+            size => taken += resolve(size, area[main], natural[main]),
+        }
+    }
+    let fill = ((area[main] - taken) / fills.max(1) as f64).max(0.0);
+
+    let mut position = origin[main];
+    for (child, natural) in children.iter_mut().zip(naturals) {
+        let sizes = [child.width, child.height];
+        let mut size = [0.0; 2];
+        size[main] = match sizes[main] {
+            Size::Fill => fill,
+            other => resolve(other, area[main], natural[main]),
+        };
+        size[cross] = resolve(sizes[cross], area[cross], natural[cross]);
+
+        let mut start = [0.0; 2];
+        start[main] = position;
+        start[cross] = origin[cross] + (area[cross] - size[cross]) * child.anchor.factors()[cross];
+        position += size[main] + spacing;
+
+        child.set_rect(
+            [
+                start[0] + child.offset[0],
+                start[1] + child.offset[1],
+                size[0],
+                size[1],
+            ],
+            measure,
+        );
+    }
+}
+
+// Options of a dropdown go below it, each as tall as the dropdown itself
+fn dropdown_item_rect([x, y, w, h]: [f64; 4], index: usize) -> [f64; 4] {
+    [x, y + h * (index + 1) as f64, w, h]
+}
+
+// Sliders without a step are continuous
+fn snap(value: f64, min: f64, step: f64) -> f64 {
+    if step > 0.0 {
+        min + ((value - min) / step).round() * step
+    } else {
+        value
+    }
+}
+
+// What arrow keys move a slider by, a hundredth of the way for continuous ones
+fn key_step(min: f64, max: f64, step: f64) -> f64 {
+    if step > 0.0 {
+        step
+    } else {
+        (max - min) / 100.0
+    }
+}
+
+fn is_consumable<E: GenericEvent>(e: &E) -> bool {
+    focus::is_mouse_press(e) || e.mouse_scroll_args().is_some()
+}
+
+// Text vertically centred on a line starting at x
+fn draw_text(
+    c: Context,
+    g: &mut G2d,
+    glyphs: &mut Fonts,
+    text: &str,
+    size: u32,
+    color: Color,
+    [x, y, h]: [f64; 3],
+) {
+    text::draw_sharp(
+        glyphs,
+        c,
+        g,
+        text,
+        size,
+        color,
+        [x, y + h / 2.0 + size as f64 / 3.0],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use piston::input::{self, ButtonArgs, ButtonState as PressState, Event, Input, Motion};
+    use piston_window::{Key, MouseButton};
+
+    const VIEW: [f64; 2] = [800.0, 600.0];
+
+    // Half the font size per character, close enough to a real font
+    fn layout(ui: &mut Ui) {
+        ui.layout(VIEW, &mut |size, text| {
+            text.chars().count() as f64 * size as f64 / 2.0
+        });
+    }
+
+    fn send(ui: &mut Ui, input: Input) {
+        ui.event(&Event::Input(input, None));
+        layout(ui);
+    }
+
+    fn move_to(ui: &mut Ui, position: [f64; 2]) {
+        send(ui, Input::Move(Motion::MouseCursor(position)));
+    }
+
+    fn mouse(ui: &mut Ui, state: PressState) {
+        send(
+            ui,
+            Input::Button(ButtonArgs {
+                state,
+                button: input::Button::Mouse(MouseButton::Left),
+                scancode: None,
+            }),
+        );
+    }
+
+    fn click(ui: &mut Ui, position: [f64; 2]) {
+        move_to(ui, position);
+        mouse(ui, PressState::Press);
+        mouse(ui, PressState::Release);
+    }
+
+    fn key(ui: &mut Ui, key: Key) {
+        send(
+            ui,
+            Input::Button(ButtonArgs {
+                state: PressState::Press,
+                button: input::Button::Keyboard(key),
+                scancode: None,
+            }),
+        );
+    }
+
+    fn events(ui: &mut Ui) -> Vec<(&'static str, WidgetEvent)> {
+        std::iter::from_fn(|| ui.next_event()).collect()
+    }
+
+    fn children(widget: &Widget) -> &[Widget] {
+        match &widget.kind {
+            WidgetKind::Panel { children, .. } => children,
+            WidgetKind::Scroll { child, .. } => std::slice::from_ref(child),
+            _ => &[],
+        }
+    }
+
+    #[test]
+    fn stacks_share_what_fixed_children_leave() {
+        let mut ui = Ui::new(
+            Widget::panel(
+                Some(Direction::Vertical),
+                vec![
+                    Widget::label("Title", 20).size(Size::Fill, Size::Fixed(40.0)),
+                    Widget::slider("a", 0.0, 0.0, 1.0, 0.1).size(Size::Fill, Size::Fill),
+                    Widget::slider("b", 0.0, 0.0, 1.0, 0.1)
+                        .anchor(Anchor::TopRight)
+                        .size(Size::Relative(0.5), Size::Fill),
+                ],
+            )
+            .size(Size::Fill, Size::Fill)
+            .padding(10.0)
+            .spacing(20.0),
+        );
+        layout(&mut ui);
+
+        let rects = children(&ui.root)
+            .iter()
+            .map(|child| child.rect)
+            .collect::<Vec<_>>();
+        // 580 tall inside the padding, minus 40 for the title and two 20 gaps, split in two
+        assert_eq!(rects[0], [10.0, 10.0, 780.0, 40.0]);
+        assert_eq!(rects[1], [10.0, 70.0, 780.0, 250.0]);
+        assert_eq!(rects[2], [400.0, 340.0, 390.0, 250.0]);
+    }
+
+    #[test]
+    fn anchors_place_widgets_relative_to_the_window() {
+        let mut ui = Ui::new(
+            Widget::panel(
+                None,
+                vec![
+                    Widget::label("Corner", 20).anchor(Anchor::BottomRight),
+                    Widget::slider("a", 0.0, 0.0, 1.0, 0.1)
+                        .anchor(Anchor::Centre)
+                        .size(Size::Relative(0.5), Size::Fixed(30.0)),
+                ],
+            )
+            .size(Size::Fill, Size::Fill),
+        );
+        layout(&mut ui);
+
+        let rects = children(&ui.root)
+            .iter()
+            .map(|child| child.rect)
+            .collect::<Vec<_>>();
+        assert_eq!(rects[0], [740.0, 570.0, 60.0, 30.0]);
+        assert_eq!(rects[1], [200.0, 285.0, 400.0, 30.0]);
+    }
+
+    #[test]
+    fn clicks_only_reach_the_topmost_widget() {
+        let mut ui = Ui::new(
+            Widget::panel(
+                None,
+                vec![
+                    Widget::checkbox("behind", "Behind", false)
+                        .size(Size::Fixed(200.0), Size::Fixed(32.0)),
+                    // Drawn after, so on top of the checkbox
+                    Widget::panel(None, vec![])
+                        .size(Size::Fixed(100.0), Size::Fixed(32.0))
+                        .background([0.0, 0.0, 0.0, 1.0]),
+                ],
+            )
+            .size(Size::Fill, Size::Fill),
+        );
+        layout(&mut ui);
+
+        click(&mut ui, [50.0, 16.0]);
+        assert_eq!(events(&mut ui), vec![]);
+
+        click(&mut ui, [150.0, 16.0]);
+        assert_eq!(events(&mut ui), vec![("behind", WidgetEvent::Toggle(true))]);
+        click(&mut ui, [150.0, 16.0]);
+        assert_eq!(
+            events(&mut ui),
+            vec![("behind", WidgetEvent::Toggle(false))]
+        );
+    }
+
+    #[test]
+    fn sliders_follow_drags_in_steps() {
+        let mut ui = Ui::new(
+            Widget::slider("volume", 0.5, 0.0, 1.0, 0.25)
+                .size(Size::Fixed(100.0 + SLIDER_VALUE_WIDTH), Size::Fixed(32.0)),
+        );
+        layout(&mut ui);
+
+        move_to(&mut ui, [10.0, 16.0]);
+        mouse(&mut ui, PressState::Press);
+        // Still dragging once the cursor leaves the slider
+        move_to(&mut ui, [80.0, 100.0]);
+        move_to(&mut ui, [-50.0, 100.0]);
+        mouse(&mut ui, PressState::Release);
+        move_to(&mut ui, [100.0, 16.0]);
+
+        assert_eq!(
+            events(&mut ui),
+            vec![
+                ("volume", WidgetEvent::ValueChanged(0.0)),
+                ("volume", WidgetEvent::ValueChanged(0.75)),
+                ("volume", WidgetEvent::ValueChanged(0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn open_dropdowns_cover_what_is_below_them() {
+        let mut ui = Ui::new(
+            Widget::panel(
+                Some(Direction::Vertical),
+                vec![
+                    Widget::dropdown("mode", &["Mouse", "Tablet", "Touch"], 0)
+                        .size(Size::Fixed(200.0), Size::Fixed(30.0)),
+                    Widget::checkbox("below", "Below", false)
+                        .size(Size::Fixed(200.0), Size::Fixed(30.0)),
+                ],
+            )
+            .size(Size::Fill, Size::Fill),
+        );
+        layout(&mut ui);
+
+        click(&mut ui, [100.0, 15.0]);
+        // Second option, right on top of the checkbox
+        click(&mut ui, [100.0, 75.0]);
+        assert_eq!(events(&mut ui), vec![("mode", WidgetEvent::Select(1))]);
+
+        // Clicking outside of an open list closes it without reaching anything
+        click(&mut ui, [100.0, 15.0]);
+        click(&mut ui, [500.0, 500.0]);
+        click(&mut ui, [100.0, 45.0]);
+        assert_eq!(events(&mut ui), vec![("below", WidgetEvent::Toggle(true))]);
+    }
+
+    #[test]
+    fn scrolling_stops_at_the_content_edges() {
+        let list = Widget::panel(
+            Some(Direction::Vertical),
+            (0..20)
+                .map(|_| Widget::label("Row", 20).size(Size::Fill, Size::Fixed(50.0)))
+                .collect(),
+        )
+        .size(Size::Fill, Size::Auto);
+        let mut ui = Ui::new(Widget::scroll(list).size(Size::Fill, Size::Fixed(300.0)));
+        layout(&mut ui);
+
+        let scroll = |ui: &Ui| match ui.root.kind {
+            WidgetKind::Scroll { scroll, .. } => scroll,
+            _ => unreachable!(),
+        };
+
+        // Outside of the container
+        move_to(&mut ui, [100.0, 400.0]);
+        send(&mut ui, Input::Move(Motion::MouseScroll([0.0, -1.0])));
+        assert_eq!(scroll(&ui), 0.0);
+
+        move_to(&mut ui, [100.0, 100.0]);
+        send(&mut ui, Input::Move(Motion::MouseScroll([0.0, -2.0])));
+        assert_eq!(scroll(&ui), SCROLL_STEP * 2.0);
+        assert_eq!(children(&ui.root)[0].rect[1], -SCROLL_STEP * 2.0);
+
+        send(&mut ui, Input::Move(Motion::MouseScroll([0.0, -100.0])));
+        assert_eq!(scroll(&ui), 1000.0 - 300.0);
+        send(&mut ui, Input::Move(Motion::MouseScroll([0.0, 100.0])));
+        assert_eq!(scroll(&ui), 0.0);
+    }
+
+    #[test]
+    fn text_inputs_type_while_focused() {
+        let mut ui = Ui::new(
+            Widget::text_input("name", "ab", "Name").size(Size::Fixed(200.0), Size::Fixed(30.0)),
+        );
+        layout(&mut ui);
+
+        // Not focused yet
+        send(&mut ui, Input::Text("x".to_string()));
+        click(&mut ui, [50.0, 15.0]);
+        send(&mut ui, Input::Text("c".to_string()));
+        key(&mut ui, Key::Backspace);
+        key(&mut ui, Key::Backspace);
+        key(&mut ui, Key::Return);
+        // Enter gave up focus
+        send(&mut ui, Input::Text("y".to_string()));
+
+        assert_eq!(
+            events(&mut ui),
+            vec![
+                ("name", WidgetEvent::TextChanged("abc".to_string())),
+                ("name", WidgetEvent::TextChanged("ab".to_string())),
+                ("name", WidgetEvent::TextChanged("a".to_string())),
+                ("name", WidgetEvent::Submit("a".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn text_inputs_place_the_caret_and_select_by_dragging() {
+        let mut ui = Ui::new(
+            Widget::text_input("name", "hello", "Name").size(Size::Fixed(200.0), Size::Fixed(30.0)),
+        );
+        layout(&mut ui);
+
+        // Characters are 9 wide, after 12 of padding
+        click(&mut ui, [12.0 + 9.0 * 2.0 + 1.0, 15.0]);
+        send(&mut ui, Input::Text("X".to_string()));
+
+        move_to(&mut ui, [12.0, 15.0]);
+        mouse(&mut ui, PressState::Press);
+        move_to(&mut ui, [12.0 + 9.0 * 3.0, 15.0]);
+        mouse(&mut ui, PressState::Release);
+        send(&mut ui, Input::Text("Y".to_string()));
+
+        key(&mut ui, Key::LCtrl);
+        key(&mut ui, Key::Z);
+
+        assert_eq!(
+            events(&mut ui),
+            vec![
+                ("name", WidgetEvent::TextChanged("heXllo".to_string())),
+                ("name", WidgetEvent::TextChanged("Yllo".to_string())),
+                ("name", WidgetEvent::TextChanged("heXllo".to_string())),
+            ]
+        );
+    }
+
+    fn focus_next(ui: &mut Ui) {
+        focus::move_focus(&mut ui.focusable_buttons(), false);
+    }
+
+    #[test]
+    fn focused_widgets_take_arrows_and_enter() {
+        let mut ui = Ui::new(Widget::panel(
+            Some(Direction::Vertical),
+            vec![
+                Widget::label("Not focusable", 20),
+                Widget::checkbox("check", "Check", false),
+                Widget::slider("speed", 1.0, 0.5, 2.0, 0.5),
+                Widget::dropdown("mode", &["Mouse", "Tablet", "Touch"], 0),
+                Widget::text_input("name", "", "Name"),
+            ],
+        ));
+        layout(&mut ui);
+
+        // Nothing focused leaves every key to the owner
+        assert!(!ui.navigate(Navigation::Activate));
+        assert_eq!(ui.focusable_buttons().len(), 4);
+
+        focus_next(&mut ui);
+        assert!(ui.navigate(Navigation::Activate));
+        assert!(!ui.navigate(Navigation::Down));
+
+        focus_next(&mut ui);
+        assert!(ui.navigate(Navigation::Right));
+        assert!(ui.navigate(Navigation::Right));
+        // Already at the end
+        assert!(ui.navigate(Navigation::Right));
+        assert!(!ui.navigate(Navigation::Activate));
+
+        focus_next(&mut ui);
+        assert!(ui.navigate(Navigation::Right));
+        assert!(ui.navigate(Navigation::Activate));
+        // The open list takes everything, Escape included
+        assert!(ui.navigate(Navigation::Down));
+        assert!(ui.navigate(Navigation::Down));
+        assert!(ui.navigate(Navigation::Activate));
+        assert!(ui.navigate(Navigation::Activate));
+        assert!(ui.navigate(Navigation::Back));
+        assert!(!ui.navigate(Navigation::Back));
+
+        focus_next(&mut ui);
+        assert!(ui.navigate(Navigation::Activate));
+        assert!(ui.navigate(Navigation::Back));
+        assert!(!ui.navigate(Navigation::Activate));
+
+        assert_eq!(
+            events(&mut ui),
+            vec![
+                ("check", WidgetEvent::Toggle(true)),
+                ("speed", WidgetEvent::ValueChanged(1.5)),
+                ("speed", WidgetEvent::ValueChanged(2.0)),
+                ("mode", WidgetEvent::Select(1)),
+                ("mode", WidgetEvent::Select(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn focus_scrolls_into_view_once() {
+        let list = Widget::panel(
+            Some(Direction::Vertical),
+            (0..20)
+                .map(|_| Widget::checkbox("row", "Row", false).size(Size::Fill, Size::Fixed(50.0)))
+                .collect(),
+        )
+        .size(Size::Fill, Size::Auto);
+        let mut ui = Ui::new(Widget::scroll(list).size(Size::Fill, Size::Fixed(300.0)));
+        layout(&mut ui);
+
+        let scroll = |ui: &Ui| match ui.root.kind {
+            WidgetKind::Scroll { scroll, .. } => scroll,
+            _ => unreachable!(),
+        };
+
+        focus::move_focus(&mut ui.focusable_buttons(), true);
+        layout(&mut ui);
+        assert_eq!(scroll(&ui), 1000.0 - 300.0);
+
+        // Scrolling away from it again is fine
+        move_to(&mut ui, [100.0, 100.0]);
+        send(&mut ui, Input::Move(Motion::MouseScroll([0.0, 100.0])));
+        layout(&mut ui);
+        assert_eq!(scroll(&ui), 0.0);
+
+        // The row before has the same id, but is another widget to scroll to
+        focus::move_focus(&mut ui.focusable_buttons(), true);
+        layout(&mut ui);
+        assert_eq!(scroll(&ui), 950.0 - 300.0);
+    }
+
+    #[test]
+    fn empty_dropdowns_and_stepless_sliders_still_work() {
+        let mut ui = Ui::new(Widget::panel(
+            Some(Direction::Vertical),
+            vec![
+                Widget::dropdown("empty", &[], 0).size(Size::Fixed(200.0), Size::Fixed(30.0)),
+                Widget::slider("smooth", 0.0, 0.0, 2.0, 0.0)
+                    .size(Size::Fixed(100.0 + SLIDER_VALUE_WIDTH), Size::Fixed(30.0)),
+            ],
+        ));
+        layout(&mut ui);
+
+        move_to(&mut ui, [33.0, 45.0]);
+        mouse(&mut ui, PressState::Press);
+        mouse(&mut ui, PressState::Release);
+        click(&mut ui, [100.0, 15.0]);
+
+        focus_next(&mut ui);
+        assert!(ui.navigate(Navigation::Activate));
+        assert!(ui.navigate(Navigation::Right));
+        assert!(ui.navigate(Navigation::Left));
+        focus_next(&mut ui);
+        assert!(ui.navigate(Navigation::Right));
+
+        assert_eq!(
+            events(&mut ui),
+            vec![
+                ("smooth", WidgetEvent::ValueChanged(0.66)),
+                ("smooth", WidgetEvent::ValueChanged(0.68)),
+            ]
+        );
+    }
+}
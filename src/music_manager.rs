@@ -1,32 +1,325 @@
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use rodio::{Decoder, OutputStream, Source};
 use rodio::{OutputStreamHandle, Sink};
 
+use crate::animations::Clock;
+use crate::beatmap::TrackTiming;
+
+// Enough for one FFT window of the visualiser
+const SAMPLE_HISTORY: usize = 2048;
+const SAMPLE_BATCH: usize = 256;
+
 #[allow(dead_code)]
 pub struct MusicManager {
-    stream: OutputStream,
-    stream_handle: OutputStreamHandle,
+    // None without an audio device, when nothing is heard but the clock still runs
+    output: Option<(OutputStream, OutputStreamHandle)>,
     sink: Sink,
+    clock: TrackClock,
+    timing: TrackTiming,
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    // Position in the track of everything the output has pulled, which is what the clock
+    // should agree with give or take the output's buffer
+    pulled: Arc<Mutex<Duration>>,
+    track: Option<Track>,
+}
+
+struct Track {
+    path: PathBuf,
+    // Most decoders can't tell the length up front, so it gets measured in the background
+    duration: Arc<Mutex<Option<Duration>>>,
+}
+
+impl Track {
+    fn new(path: PathBuf) -> Self {
+        let duration = Arc::new(Mutex::new(None));
+
+        let measured_path = path.clone();
+        let measured_duration = duration.clone();
+        thread::spawn(move || {
+            let Ok(file) = File::open(measured_path) else {
+                return;
+            };
+            let Ok(decoder) = Decoder::new(BufReader::new(file)) else {
+                return;
+            };
+
+            let total = decoder.total_duration().unwrap_or_else(|| {
+                let samples_per_second = decoder.sample_rate() as f64 * decoder.channels() as f64;
+                Duration::from_secs_f64(decoder.count() as f64 / samples_per_second)
+            });
+
+            *measured_duration.lock().unwrap() = Some(total);
+        });
+
+        Self { path, duration }
+    }
+}
+
+// Copies the mono downmix of everything played into a shared buffer, as the audio thread
+// pulls it, so the menu can look at what's currently audible
+struct SampleTap<S: Source<Item = f32>> {
+    source: S,
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    pulled: Arc<Mutex<Duration>>,
+    frame: Vec<f32>,
+    batch: Vec<f32>,
+}
+
+impl<S: Source<Item = f32>> SampleTap<S> {
+    fn new(source: S, samples: Arc<Mutex<VecDeque<f32>>>, pulled: Arc<Mutex<Duration>>) -> Self {
+        Self {
+            source,
+            samples,
+            pulled,
+            frame: Vec::new(),
+            batch: Vec::with_capacity(SAMPLE_BATCH),
+        }
+    }
+
+    fn flush(&mut self) {
+        *self.pulled.lock().unwrap() +=
+            Duration::from_secs_f64(self.batch.len() as f64 / self.source.sample_rate() as f64);
+
+        let mut samples = self.samples.lock().unwrap();
+        samples.extend(self.batch.drain(..));
+
+        let overflow = samples.len().saturating_sub(SAMPLE_HISTORY);
+        samples.drain(..overflow);
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for SampleTap<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.source.next()?;
+
+        self.frame.push(sample);
+        if self.frame.len() >= self.source.channels() as usize {
+            self.batch
+                .push(self.frame.iter().sum::<f32>() / self.frame.len() as f32);
+            self.frame.clear();
+
+            if self.batch.len() >= SAMPLE_BATCH {
+                self.flush();
+            }
+        }
+
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for SampleTap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+}
+
+// rodio doesn't expose the playback position, so we keep track of it ourselves
+struct TrackClock {
+    start_position: Duration,
+    resumed_at: Option<Instant>,
+}
+
+impl TrackClock {
+    fn new(start_position: Duration) -> Self {
+        Self {
+            start_position,
+            resumed_at: Some(Instant::now()),
+        }
+    }
+
+    fn position(&self) -> Duration {
+        self.position_at(Instant::now())
+    }
+
+    // Instants from before the last seek or resume are where it resumed from
+    fn position_at(&self, instant: Instant) -> Duration {
+        match self.resumed_at {
+            Some(resumed_at) => self.start_position + instant.saturating_duration_since(resumed_at),
+            None => self.start_position,
+        }
+    }
+
+    fn pause(&mut self) {
+        self.start_position = self.position();
+        self.resumed_at = None;
+    }
+
+    fn resume(&mut self) {
+        if self.resumed_at.is_none() {
+            self.resumed_at = Some(Instant::now());
+        }
+    }
 }
 
 impl MusicManager {
     pub fn new() -> Self {
-        let (stream, stream_handle) = OutputStream::try_default().unwrap();
+        let output = OutputStream::try_default()
+            .map_err(|e| println!("No audio output, playing in silence: {}", e))
+            .ok();
 
         Self {
-            sink: Sink::try_new(&stream_handle).unwrap(),
-            stream,
-            stream_handle,
+            sink: new_sink(&output),
+            output,
+            clock: TrackClock::new(Duration::ZERO),
+            timing: TrackTiming::default(),
+            samples: Arc::new(Mutex::new(VecDeque::new())),
+            pulled: Arc::new(Mutex::new(Duration::ZERO)),
+            track: None,
         }
     }
 
     pub fn play_file(&mut self, filename: &str) {
         let file = BufReader::new(File::open(format!("assets/{}", filename)).unwrap());
-        let source = Decoder::new(file).unwrap();
+        let source = Decoder::new(file).unwrap().convert_samples();
 
-        self.sink.append(source.repeat_infinite());
+        self.pulled = Arc::new(Mutex::new(Duration::ZERO));
+        self.sink.append(SampleTap::new(
+            source.repeat_infinite(),
+            self.samples.clone(),
+            self.pulled.clone(),
+        ));
+
+        self.clock = TrackClock::new(Duration::ZERO);
+        self.timing = TrackTiming::default();
+        self.track = None;
+    }
+
+    // Replaces whatever is playing with a single play-through of the given file. When it can't be
+    // played there's silence instead, with the clock running as if it was
+    pub fn play_track<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        start_position: Duration,
+        timing: TrackTiming,
+    ) -> io::Result<()> {
+        let result = self.start_track(path.as_ref(), start_position);
+
+        self.timing = timing;
+        self.track = match result {
+            Ok(()) => Some(Track::new(path.as_ref().to_path_buf())),
+            Err(_) => None,
+        };
+
+        result
+    }
+
+    fn start_track(&mut self, path: &Path, start_position: Duration) -> io::Result<()> {
+        let decoder = open(path);
+
+        let volume = self.sink.volume();
+        self.sink = new_sink(&self.output);
+        self.sink.set_volume(volume);
+        self.pulled = Arc::new(Mutex::new(start_position));
+
+        let result = decoder.map(|decoder| {
+            self.sink.append(SampleTap::new(
+                decoder.convert_samples().skip_duration(start_position),
+                self.samples.clone(),
+                self.pulled.clone(),
+            ))
+        });
+
+        self.clock = TrackClock::new(start_position);
+        result
+    }
+
+    // Restarts the current track at the given position in milliseconds, keeping it paused if it was
+    pub fn seek(&mut self, position: f64) {
+        let Some(path) = self.track.as_ref().map(|track| track.path.clone()) else {
+            return;
+        };
+
+        let paused = self.is_paused();
+        let position = Duration::from_secs_f64(position.max(0.0) / 1000.0);
+        if let Err(e) = self.start_track(&path, position) {
+            println!("Could not seek in {:?}: {}", path, e);
+        }
+
+        if paused {
+            self.pause();
+        }
+    }
+
+    // Rewinds to the start of the track without playing it
+    pub fn stop(&mut self) {
+        self.pause();
+        self.seek(0.0);
+    }
+
+    pub fn pause(&mut self) {
+        self.sink.pause();
+        self.clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.sink.play();
+        self.clock.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.sink.empty()
+    }
+
+    // Current position in the track, in milliseconds
+    pub fn position(&self) -> f64 {
+        self.clock.position().as_secs_f64() * 1000.0
+    }
+
+    // Position the track was at, or will be at, at the given instant
+    pub fn position_at(&self, instant: Instant) -> f64 {
+        self.clock.position_at(instant).as_secs_f64() * 1000.0
+    }
+
+    // Length of the current track in milliseconds, once known
+    pub fn duration(&self) -> Option<f64> {
+        let track = self.track.as_ref()?;
+        let duration = *track.duration.lock().unwrap();
+
+        duration.map(|duration| duration.as_secs_f64() * 1000.0)
+    }
+
+    // How far the clock is ahead of the audio the output pulled, in milliseconds. Includes the
+    // output's buffer, so it's the changes in it that show drift
+    pub fn audio_drift(&self) -> f64 {
+        let pulled = *self.pulled.lock().unwrap();
+        (self.clock.position().as_secs_f64() - pulled.as_secs_f64()) * 1000.0
+    }
+
+    pub fn timing(&self) -> &TrackTiming {
+        &self.timing
+    }
+
+    // The most recent mono samples sent to the output, oldest first
+    pub fn recent_samples(&self, count: usize) -> Vec<f32> {
+        let samples = self.samples.lock().unwrap();
+        let skip = samples.len().saturating_sub(count);
+
+        samples.iter().skip(skip).copied().collect()
     }
 
     pub fn set_volume(&mut self, volume: f32) {
@@ -37,3 +330,50 @@ impl MusicManager {
         self.sink.volume()
     }
 }
+
+// Sinks without an output never get pulled, so whatever they're given stays queued
+fn new_sink(output: &Option<(OutputStream, OutputStreamHandle)>) -> Sink {
+    match output {
+        Some((_, handle)) => Sink::try_new(handle).unwrap(),
+        None => Sink::new_idle().0,
+    }
+}
+
+fn open(path: &Path) -> io::Result<Decoder<BufReader<File>>> {
+    let file = BufReader::new(File::open(path)?);
+    Decoder::new(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// Animations ticked with the music stay in sync with it, stopping while it's paused
+impl Clock for MusicManager {
+    fn now(&self) -> f64 {
+        self.position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_positions_follow_the_instants() {
+        let mut clock = TrackClock::new(Duration::from_secs(1));
+        let resumed_at = clock.resumed_at.unwrap();
+
+        let first = clock.position_at(resumed_at + Duration::from_millis(2));
+        let second = clock.position_at(resumed_at + Duration::from_millis(7));
+        assert_eq!(first, Duration::from_millis(1002));
+        assert_eq!(second, Duration::from_millis(1007));
+        assert_eq!(
+            clock.position_at(resumed_at - Duration::from_millis(5)),
+            Duration::from_secs(1)
+        );
+
+        clock.pause();
+        let paused_at = clock.position();
+        assert_eq!(
+            clock.position_at(Instant::now() + Duration::from_secs(1)),
+            paused_at
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::slider_path::{self, SliderEvent, SliderPath};
+use graphics::math::{add, Vec2d};
+use rosu_pp::parse::HitObjectKind;
+use rosu_pp::Beatmap;
+
+pub struct HitObject {
+    pub position: Vec2d,
+    pub start_time: f64,
+    pub obj_type: ObjectType,
+}
+
+pub enum ObjectType {
+    Circle,
+    Slider {
+        path: SliderPath,
+        span_duration: f64,
+        span_count: usize,
+        events: Vec<SliderEvent>,
+    },
+    Spinner {
+        duration: f64,
+    },
+}
+
+impl HitObject {
+    pub fn end_time(&self) -> f64 {
+        match &self.obj_type {
+            ObjectType::Circle => self.start_time,
+            ObjectType::Slider {
+                span_duration,
+                span_count,
+                ..
+            } => self.start_time + span_duration * *span_count as f64,
+            ObjectType::Spinner { duration } => self.start_time + duration,
+        }
+    }
+
+    // Where the slider ball is at `time`, going back and forth along the path on repeats
+    pub fn position_at(&self, time: f64) -> Vec2d {
+        let ObjectType::Slider {
+            path,
+            span_duration,
+            span_count,
+            ..
+        } = &self.obj_type
+        else {
+            return self.position;
+        };
+
+        let spans = if *span_duration > 0.0 {
+            ((time - self.start_time) / span_duration).clamp(0.0, *span_count as f64)
+        } else {
+            0.0
+        };
+        let span = (spans.floor() as usize).min(span_count - 1);
+        let span_progress = spans - span as f64;
+
+        let progress = if span % 2 == 1 {
+            1.0 - span_progress
+        } else {
+            span_progress
+        };
+
+        add(self.position, path.position_at(progress))
+    }
+}
+
+pub fn convert(map: &Beatmap) -> Vec<HitObject> {
+    map.hit_objects
+        .iter()
+        .map(|obj| {
+            let obj_type = match &obj.kind {
+                HitObjectKind::Circle => ObjectType::Circle,
+                HitObjectKind::Slider {
+                    pixel_len,
+                    repeats,
+                    control_points,
+                    ..
+                } => {
+                    let path = SliderPath::new(control_points, *pixel_len);
+                    let (velocity, tick_distance) =
+                        slider_path::slider_velocity(map, obj.start_time);
+
+                    let span_count = repeats + 1;
+                    let span_duration = path.distance() / velocity;
+                    let events = slider_path::slider_events(
+                        obj.start_time,
+                        span_duration,
+                        velocity,
+                        tick_distance,
+                        path.distance(),
+                        span_count,
+                    );
+
+                    ObjectType::Slider {
+                        path,
+                        span_duration,
+                        span_count,
+                        events,
+                    }
+                }
+                HitObjectKind::Spinner { end_time } | HitObjectKind::Hold { end_time } => {
+                    ObjectType::Spinner {
+                        duration: end_time - obj.start_time,
+                    }
+                }
+            };
+
+            HitObject {
+                position: [obj.pos.x as f64, obj.pos.y as f64],
+                start_time: obj.start_time,
+                obj_type,
+            }
+        })
+        .collect()
+}
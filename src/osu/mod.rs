@@ -0,0 +1,5 @@
+mod hit_object;
+mod playfield;
+mod score;
+
+pub use playfield::OsuPlayfield;
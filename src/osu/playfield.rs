@@ -0,0 +1,502 @@
+use super::hit_object::{self, HitObject, ObjectType};
+use super::score::{HitResult, HitWindows, OsuScore};
+use crate::beatmap::difficulty_range;
+use crate::key_bindings::{GameAction, KeyBindings};
+use crate::ruleset::{self, Ruleset, RulesetContext, ScoreSummary};
+use crate::slider_path::SliderEventKind;
+use crate::text::Fonts;
+use crate::viewport::SCREEN_SIZE;
+use graphics::line::Line;
+use graphics::math::{add, square_len, sub, Vec2d};
+use graphics::{ellipse, Context, Ellipse, Text, Transformed};
+use piston_window::{Button, G2d};
+use rosu_pp::{Beatmap, GameMode};
+use std::collections::HashSet;
+use std::f64::consts::PI;
+
+const PLAYFIELD_SIZE: Vec2d = [512.0, 384.0];
+// Same part of the window as of osu!'s 640x480 screen
+const PLAYFIELD_FILL: f64 = PLAYFIELD_SIZE[0] / SCREEN_SIZE[0];
+const SPINNER_CENTER: Vec2d = [256.0, 192.0];
+const SPINNER_RADIUS: f64 = 180.0;
+// The slider ball keeps tracking until the cursor leaves this many times the circle radius
+const FOLLOW_RADIUS: f64 = 2.4;
+const JUDGEMENT_DISPLAY_TIME: f64 = 300.0;
+// Distance between two points sampled along a slider body
+const SLIDER_BODY_STEP: f64 = 8.0;
+
+const CIRCLE_COLOR: [f32; 4] = [0.3, 0.55, 0.9, 1.0];
+const SLIDER_BODY_COLOR: [f32; 4] = [0.1, 0.12, 0.2, 1.0];
+const SPINNER_COLOR: [f32; 4] = [0.9, 0.9, 1.0, 1.0];
+
+#[derive(Default)]
+struct ObjectState {
+    // Result of clicking a circle or slider head
+    head: Option<HitResult>,
+    // Index of the next slider event to go through
+    next_event: usize,
+    // Slider head, ticks, repeats and end that were hit
+    events_hit: usize,
+    // Radians the cursor went around a spinner
+    rotation: f64,
+    judged: bool,
+}
+
+pub struct OsuPlayfield {
+    objects: Vec<HitObject>,
+    states: Vec<ObjectState>,
+    // Everything before this has been judged
+    next_object: usize,
+    bindings: KeyBindings,
+    hit_windows: HitWindows,
+    score: OsuScore,
+    radius: f64,
+    // How long objects are on screen before they have to be hit, and how long they take to
+    // fade in
+    preempt: f64,
+    fade_in: f64,
+    spins_per_second: f64,
+    // In playfield coordinates
+    cursor: Vec2d,
+    last_cursor_angle: Option<f64>,
+    // Hit keys and mouse buttons currently down
+    held: HashSet<GameAction>,
+    last_judgement: Option<(HitResult, Vec2d, f64)>,
+}
+
+impl Ruleset for OsuPlayfield {
+    // One of the K1, K2, M1 and M2 hit actions
+    type Action = GameAction;
+
+    const MODE: GameMode = GameMode::Osu;
+
+    fn new(map: &Beatmap, ctx: &RulesetContext) -> Self {
+        let objects = hit_object::convert(map);
+        println!("Loaded {} objects", objects.len());
+
+        let od = map.od as f64;
+        let ar = map.ar as f64;
+
+        Self {
+            states: objects.iter().map(|_| ObjectState::default()).collect(),
+            objects,
+            next_object: 0,
+            bindings: ctx.settings.key_bindings.clone(),
+            hit_windows: HitWindows::new(od),
+            score: OsuScore::default(),
+            radius: 54.4 - 4.48 * map.cs as f64,
+            preempt: difficulty_range(ar, 1800.0, 1200.0, 450.0),
+            fade_in: difficulty_range(ar, 1200.0, 800.0, 300.0),
+            spins_per_second: difficulty_range(od, 3.0, 5.0, 7.5),
+            cursor: SPINNER_CENTER,
+            last_cursor_angle: None,
+            held: HashSet::new(),
+            last_judgement: None,
+        }
+    }
+
+    fn action_for(&self, button: Button) -> Option<Self::Action> {
+        self.bindings
+            .action_for(button, GameMode::Osu)
+            .filter(|action| {
+                matches!(
+                    action,
+                    GameAction::K1 | GameAction::K2 | GameAction::M1 | GameAction::M2
+                )
+            })
+    }
+
+    // Key repeat sends presses again while a key is held, only the first one clicks
+    fn press(&mut self, action: Self::Action, time: f64) {
+        if !self.held.insert(action) {
+            return;
+        }
+
+        // Notelock: only the earliest object still waiting for a click can be hit
+        let Some(i) = (self.next_object..self.objects.len()).find(|&i| {
+            self.states[i].head.is_none()
+                && !matches!(self.objects[i].obj_type, ObjectType::Spinner { .. })
+        }) else {
+            return;
+        };
+
+        let object = &self.objects[i];
+        if square_len(sub(self.cursor, object.position)) > self.radius * self.radius {
+            return;
+        }
+
+        if let Some(result) = self.hit_windows.result_for(time - object.start_time) {
+            self.judge_head(i, result, time);
+        }
+    }
+
+    fn release(&mut self, action: Self::Action, _time: f64) {
+        self.held.remove(&action);
+    }
+
+    fn cursor_moved(&mut self, position: Vec2d, window_size: Vec2d) {
+        let (scale, offset) = ruleset::fit_playfield(window_size, PLAYFIELD_SIZE, PLAYFIELD_FILL);
+        self.cursor = [
+            (position[0] - offset[0]) / scale,
+            (position[1] - offset[1]) / scale,
+        ];
+    }
+
+    fn update(&mut self, time: f64) {
+        for i in self.next_object..self.objects.len() {
+            if self.objects[i].start_time > time {
+                break;
+            }
+
+            if !self.states[i].judged {
+                self.update_object(i, time);
+            }
+        }
+
+        let cursor = sub(self.cursor, SPINNER_CENTER);
+        self.last_cursor_angle = Some(cursor[1].atan2(cursor[0]));
+
+        while self
+            .states
+            .get(self.next_object)
+            .is_some_and(|state| state.judged)
+        {
+            self.next_object += 1;
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next_object >= self.objects.len()
+    }
+
+    fn score(&self) -> ScoreSummary {
+        ScoreSummary {
+            score: self.score.score,
+            max_combo: self.score.max_combo,
+            accuracy: self.score.accuracy(),
+        }
+    }
+
+    fn judgement_counts(&self) -> String {
+        format!(
+            "{} / {} / {} / {}",
+            self.score.great, self.score.ok, self.score.meh, self.score.miss
+        )
+    }
+
+    fn render(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, time: f64) {
+        let (scale, [x, y]) =
+            ruleset::fit_playfield(c.get_view_size(), PLAYFIELD_SIZE, PLAYFIELD_FILL);
+        let playfield = c.trans(x, y).scale(scale, scale);
+
+        let visible_end = self.objects[self.next_object..]
+            .iter()
+            .position(|object| object.start_time - time > self.preempt)
+            .map_or(self.objects.len(), |i| self.next_object + i);
+
+        // Drawn back to front so earlier objects end up on top
+        for i in (self.next_object..visible_end).rev() {
+            if !self.states[i].judged {
+                self.render_object(playfield, g, i, time);
+            }
+        }
+
+        if let Some((result, position, judged_at)) = self.last_judgement {
+            if time - judged_at < JUDGEMENT_DISPLAY_TIME {
+                let (text, color) = match result {
+                    HitResult::Great => ("300", [0.4, 0.8, 1.0, 1.0]),
+                    HitResult::Ok => ("100", [0.5, 1.0, 0.5, 1.0]),
+                    HitResult::Meh => ("50", [1.0, 0.85, 0.3, 1.0]),
+                    HitResult::Miss => ("X", [1.0, 0.2, 0.2, 1.0]),
+                };
+
+                Text::new_color(color, 24)
+                    .draw(
+                        text,
+                        glyphs,
+                        &playfield.draw_state,
+                        playfield.transform.trans(position[0] - 16.0, position[1]),
+                        g,
+                    )
+                    .unwrap();
+            }
+        }
+
+        self.render_combo(c, g, glyphs);
+    }
+}
+
+impl OsuPlayfield {
+    fn is_holding(&self) -> bool {
+        !self.held.is_empty()
+    }
+
+    fn judge(&mut self, i: usize, result: HitResult, time: f64) {
+        self.states[i].judged = true;
+        self.last_judgement = Some((result, self.objects[i].position_at(time), time));
+    }
+
+    fn judge_head(&mut self, i: usize, result: HitResult, time: f64) {
+        self.states[i].head = Some(result);
+
+        match self.objects[i].obj_type {
+            ObjectType::Slider { .. } => {
+                // The head only counts towards the slider's result, missing it breaks combo
+                let hit = result != HitResult::Miss;
+                self.score.add_tick(30, hit);
+                if hit {
+                    self.states[i].events_hit += 1;
+                }
+            }
+            _ => {
+                self.score.add_hit(result);
+                self.judge(i, result, time);
+            }
+        }
+    }
+
+    fn update_object(&mut self, i: usize, time: f64) {
+        let object = &self.objects[i];
+        let late = time - object.start_time > self.hit_windows.miss_window();
+
+        match object.obj_type {
+            ObjectType::Circle => {
+                if late {
+                    self.judge_head(i, HitResult::Miss, time);
+                }
+            }
+            ObjectType::Slider { .. } => {
+                if late && self.states[i].head.is_none() {
+                    self.judge_head(i, HitResult::Miss, time);
+                }
+
+                self.update_slider(i, time);
+            }
+            ObjectType::Spinner { duration } => {
+                let end_time = object.end_time();
+
+                if time < end_time && self.is_holding() {
+                    let cursor = sub(self.cursor, SPINNER_CENTER);
+                    let angle = cursor[1].atan2(cursor[0]);
+
+                    if let Some(last) = self.last_cursor_angle {
+                        // Wrapped so going through -π to π isn't a whole turn backwards
+                        let delta = (angle - last + PI).rem_euclid(2.0 * PI) - PI;
+                        self.states[i].rotation += delta.abs();
+                    }
+                }
+
+                if time >= end_time {
+                    let progress = self.spinner_progress(i, duration);
+                    let result = if progress >= 1.0 {
+                        HitResult::Great
+                    } else if progress > 0.9 {
+                        HitResult::Ok
+                    } else if progress > 0.75 {
+                        HitResult::Meh
+                    } else {
+                        HitResult::Miss
+                    };
+
+                    self.score.add_hit(result);
+                    self.judge(i, result, time);
+                }
+            }
+        }
+    }
+
+    // Ticks, repeats and the end are hit by keeping a key held with the cursor near the ball
+    fn update_slider(&mut self, i: usize, time: f64) {
+        let ObjectType::Slider { events, .. } = &self.objects[i].obj_type else {
+            return;
+        };
+
+        let follow_radius = self.radius * FOLLOW_RADIUS;
+        let holding = self.is_holding();
+
+        while let Some(event) = events.get(self.states[i].next_event) {
+            if event.time > time {
+                break;
+            }
+            self.states[i].next_event += 1;
+
+            let ball = self.objects[i].position_at(event.time);
+            let tracking =
+                holding && square_len(sub(self.cursor, ball)) <= follow_radius * follow_radius;
+
+            match event.kind {
+                // Judged by clicking instead
+                SliderEventKind::Head => continue,
+                SliderEventKind::Tick => self.score.add_tick(10, tracking),
+                SliderEventKind::Repeat => self.score.add_tick(30, tracking),
+                // Where stable judged the end of the slider, dropping it there doesn't break
+                // combo
+                SliderEventKind::LegacyLastTick => {
+                    if tracking {
+                        self.score.add_tick(30, true);
+                    }
+                }
+                SliderEventKind::Tail => {
+                    // Everything but the tail, which is only where the slider ends
+                    let total = events.len() - 1;
+                    let ratio = self.states[i].events_hit as f64 / total as f64;
+                    let result = if ratio >= 1.0 {
+                        HitResult::Great
+                    } else if ratio >= 0.5 {
+                        HitResult::Ok
+                    } else if ratio > 0.0 {
+                        HitResult::Meh
+                    } else {
+                        HitResult::Miss
+                    };
+
+                    self.score.add_slider_end(result);
+                    self.judge(i, result, time);
+                    return;
+                }
+            }
+
+            if tracking {
+                self.states[i].events_hit += 1;
+            }
+        }
+    }
+
+    fn spinner_progress(&self, i: usize, duration: f64) -> f64 {
+        let required_spins = duration / 1000.0 * self.spins_per_second;
+
+        if required_spins <= 0.0 {
+            1.0
+        } else {
+            self.states[i].rotation / (2.0 * PI) / required_spins
+        }
+    }
+
+    fn render_object(&self, c: Context, g: &mut G2d, i: usize, time: f64) {
+        let object = &self.objects[i];
+        let alpha = ((time - (object.start_time - self.preempt)) / self.fade_in).clamp(0.0, 1.0);
+        let faded = |color: [f32; 4]| [color[0], color[1], color[2], color[3] * alpha as f32];
+
+        match &object.obj_type {
+            ObjectType::Circle => self.render_circle(c, g, object.position, object, alpha, time),
+            ObjectType::Slider { path, .. } => {
+                let steps = (path.distance() / SLIDER_BODY_STEP).ceil().max(1.0) as usize;
+                let points = (0..=steps)
+                    .map(|step| {
+                        add(
+                            object.position,
+                            path.position_at(step as f64 / steps as f64),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                for (radius, color) in [
+                    (self.radius, faded([1.0, 1.0, 1.0, 1.0])),
+                    (self.radius * 0.85, faded(SLIDER_BODY_COLOR)),
+                ] {
+                    let line = Line::new_round(color, radius);
+                    for segment in points.windows(2) {
+                        line.draw_from_to(segment[0], segment[1], &c.draw_state, c.transform, g);
+                    }
+                }
+
+                if self.states[i].head.is_none() {
+                    self.render_circle(c, g, object.position, object, alpha, time);
+                }
+
+                if time >= object.start_time {
+                    let ball = object.position_at(time);
+                    ellipse(
+                        CIRCLE_COLOR,
+                        circle_rect(ball, self.radius * 0.8),
+                        c.transform,
+                        g,
+                    );
+
+                    let follow_radius = self.radius * FOLLOW_RADIUS;
+                    if self.is_holding()
+                        && square_len(sub(self.cursor, ball)) <= follow_radius * follow_radius
+                    {
+                        Ellipse::new_border([1.0, 1.0, 1.0, 0.8], 2.0).draw(
+                            circle_rect(ball, follow_radius),
+                            &c.draw_state,
+                            c.transform,
+                            g,
+                        );
+                    }
+                }
+            }
+            ObjectType::Spinner { duration } => {
+                Ellipse::new_border(faded(SPINNER_COLOR), 4.0).draw(
+                    circle_rect(SPINNER_CENTER, SPINNER_RADIUS),
+                    &c.draw_state,
+                    c.transform,
+                    g,
+                );
+
+                let progress = self.spinner_progress(i, *duration).min(1.0);
+                ellipse(
+                    faded([SPINNER_COLOR[0], SPINNER_COLOR[1], SPINNER_COLOR[2], 0.4]),
+                    circle_rect(SPINNER_CENTER, SPINNER_RADIUS * progress),
+                    c.transform,
+                    g,
+                );
+            }
+        }
+    }
+
+    fn render_circle(
+        &self,
+        c: Context,
+        g: &mut G2d,
+        position: Vec2d,
+        object: &HitObject,
+        alpha: f64,
+        time: f64,
+    ) {
+        let alpha = alpha as f32;
+        let rect = circle_rect(position, self.radius);
+
+        ellipse(
+            [CIRCLE_COLOR[0], CIRCLE_COLOR[1], CIRCLE_COLOR[2], alpha],
+            rect,
+            c.transform,
+            g,
+        );
+        Ellipse::new_border([1.0, 1.0, 1.0, alpha], 3.0).draw(rect, &c.draw_state, c.transform, g);
+
+        // Shrinks from 4 times the circle's size down to it right when it should be hit
+        if time < object.start_time {
+            let approach = 1.0 + 3.0 * (object.start_time - time) / self.preempt;
+            Ellipse::new_border([1.0, 1.0, 1.0, alpha], 2.0).draw(
+                circle_rect(position, self.radius * approach),
+                &c.draw_state,
+                c.transform,
+                g,
+            );
+        }
+    }
+
+    fn render_combo(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts) {
+        let [_, win_height] = c.get_view_size();
+
+        Text::new_color([1.0, 1.0, 1.0, 1.0], 24)
+            .draw(
+                &format!("{}x", self.score.combo),
+                glyphs,
+                &c.draw_state,
+                c.transform.trans(20.0, win_height - 20.0),
+                g,
+            )
+            .unwrap();
+    }
+}
+
+fn circle_rect(center: Vec2d, radius: f64) -> [f64; 4] {
+    [
+        center[0] - radius,
+        center[1] - radius,
+        radius * 2.0,
+        radius * 2.0,
+    ]
+}
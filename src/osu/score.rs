@@ -0,0 +1,130 @@
+use crate::beatmap::difficulty_range;
+
+// Clicking earlier than this doesn't count as a hit at all
+const MISS_WINDOW: f64 = 400.0;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HitResult {
+    Great,
+    Ok,
+    Meh,
+    Miss,
+}
+
+impl HitResult {
+    fn value(self) -> u64 {
+        match self {
+            HitResult::Great => 300,
+            HitResult::Ok => 100,
+            HitResult::Meh => 50,
+            HitResult::Miss => 0,
+        }
+    }
+}
+
+pub struct HitWindows {
+    great: f64,
+    ok: f64,
+    meh: f64,
+}
+
+impl HitWindows {
+    pub fn new(od: f64) -> Self {
+        Self {
+            great: difficulty_range(od, 80.0, 50.0, 20.0),
+            ok: difficulty_range(od, 140.0, 100.0, 60.0),
+            meh: difficulty_range(od, 200.0, 150.0, 100.0),
+        }
+    }
+
+    // None when the hit is too early to count for anything
+    pub fn result_for(&self, offset: f64) -> Option<HitResult> {
+        let offset = offset.abs();
+
+        if offset <= self.great {
+            Some(HitResult::Great)
+        } else if offset <= self.ok {
+            Some(HitResult::Ok)
+        } else if offset <= self.meh {
+            Some(HitResult::Meh)
+        } else if offset <= MISS_WINDOW {
+            Some(HitResult::Miss)
+        } else {
+            None
+        }
+    }
+
+    // Past this an object can't be hit anymore
+    pub fn miss_window(&self) -> f64 {
+        self.meh
+    }
+}
+
+// osu!stable's ScoreV1 without the difficulty and mod multipliers: combo adds a 1/25th of the
+// value per object already in it, slider ticks and repeats are worth a flat amount
+#[derive(Default)]
+pub struct OsuScore {
+    pub score: u64,
+    pub combo: u32,
+    pub max_combo: u32,
+    pub great: u32,
+    pub ok: u32,
+    pub meh: u32,
+    pub miss: u32,
+}
+
+impl OsuScore {
+    pub fn add_hit(&mut self, result: HitResult) {
+        let value = result.value();
+        self.score += value + value * self.combo as u64 / 25;
+
+        self.count(result);
+
+        if result == HitResult::Miss {
+            self.combo = 0;
+        } else {
+            self.increase_combo();
+        }
+    }
+
+    // Combo was already built up by the slider's head, ticks and repeats
+    pub fn add_slider_end(&mut self, result: HitResult) {
+        let value = result.value();
+        self.score += value + value * self.combo as u64 / 25;
+        self.count(result);
+    }
+
+    // Slider heads, ticks and repeats: no judgement of their own, only score and combo
+    pub fn add_tick(&mut self, value: u64, hit: bool) {
+        if hit {
+            self.score += value;
+            self.increase_combo();
+        } else {
+            self.combo = 0;
+        }
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        let total = self.great + self.ok + self.meh + self.miss;
+
+        if total == 0 {
+            1.0
+        } else {
+            (300 * self.great + 100 * self.ok + 50 * self.meh) as f64 / (300 * total) as f64
+        }
+    }
+
+    fn count(&mut self, result: HitResult) {
+        match result {
+            HitResult::Great => self.great += 1,
+            HitResult::Ok => self.ok += 1,
+            HitResult::Meh => self.meh += 1,
+            HitResult::Miss => self.miss += 1,
+        }
+    }
+
+    fn increase_combo(&mut self) {
+        self.combo += 1;
+        self.max_combo = self.max_combo.max(self.combo);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::text::{Fonts, RichText};
+use graphics::types::Color;
+use graphics::{rectangle, Context, Line};
+use piston_window::G2d;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Frames the graph and the lows are taken over
+const HISTORY: usize = 1000;
+const PANEL_WIDTH: f64 = 300.0;
+const GRAPH_HEIGHT: f64 = 80.0;
+const PADDING: f64 = 10.0;
+const TEXT_SIZE: u32 = 14;
+const LINE_HEIGHT: f64 = 21.0;
+const LINES: usize = 5;
+// The graph always shows at least this much, so a smooth 60fps doesn't fill it
+const GRAPH_MIN_SCALE: f64 = 1000.0 / 30.0;
+// Marked on the graph, a frame at 60fps
+const REFERENCE_FRAME: f64 = 1000.0 / 60.0;
+
+const PANEL_COLOR: Color = [0.0, 0.0, 0.0, 0.6];
+const BAR_COLOR: Color = [0.4, 0.9, 0.5, 0.9];
+const SLOW_BAR_COLOR: Color = [1.0, 0.4, 0.3, 0.9];
+const REFERENCE_COLOR: Color = [1.0, 1.0, 1.0, 0.3];
+const TEXT_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+
+// Numbers from outside the main loop shown along with the frame timings
+pub struct PerfStats {
+    pub animations: usize,
+    pub audio_drift: f64,
+}
+
+// Frame timings in the corner of the window, for finding stutters. Times are in milliseconds
+pub struct PerfOverlay {
+    visible: bool,
+    frame_times: VecDeque<f64>,
+    last_frame: Option<Instant>,
+    update_time: f64,
+    draw_time: f64,
+    // Oldest input not yet on screen
+    pending_input: Option<Instant>,
+    latency: f64,
+}
+
+impl PerfOverlay {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            frame_times: VecDeque::with_capacity(HISTORY),
+            last_frame: None,
+            update_time: 0.0,
+            draw_time: 0.0,
+            pending_input: None,
+            latency: 0.0,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn input(&mut self) {
+        self.pending_input.get_or_insert_with(Instant::now);
+    }
+
+    pub fn updated(&mut self, took: Duration) {
+        self.update_time = millis(took);
+    }
+
+    // Called once a frame is drawn. Latency is estimated as the time from the oldest input to
+    // the end of the frame that shows it, the swap and the monitor come on top of that
+    pub fn drawn(&mut self, took: Duration) {
+        let now = Instant::now();
+        self.draw_time = millis(took);
+
+        if let Some(last_frame) = self.last_frame {
+            if self.frame_times.len() == HISTORY {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(millis(now - last_frame));
+        }
+        self.last_frame = Some(now);
+
+        if let Some(input) = self.pending_input.take() {
+            self.latency = millis(now - input);
+        }
+    }
+
+    pub fn render(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, stats: PerfStats) {
+        if !self.visible {
+            return;
+        }
+
+        let [_, view_height] = c.get_view_size();
+        let height = PADDING * 3.0 + LINE_HEIGHT * LINES as f64 + GRAPH_HEIGHT;
+        let [x, y] = [PADDING, view_height - height - PADDING];
+        rectangle(PANEL_COLOR, [x, y, PANEL_WIDTH, height], c.transform, g);
+
+        let frame_time = self.frame_times.back().copied().unwrap_or(0.0);
+        let [average, low, lowest] = [
+            average(&self.frame_times),
+            slowest_average(&self.frame_times, 0.01),
+            slowest_average(&self.frame_times, 0.001),
+        ];
+        let text = format!(
+            "Frame: {:.2}ms ({:.0} fps avg)\n\
+             Update: {:.2}ms  Draw: {:.2}ms\n\
+             1% low: {:.0} fps  0.1% low: {:.0} fps\n\
+             Input latency: ~{:.1}ms\n\
+             Audio drift: {:+.1}ms  Animations: {}",
+            frame_time,
+            fps(average),
+            self.update_time,
+            self.draw_time,
+            fps(low),
+            fps(lowest),
+            self.latency,
+            stats.audio_drift,
+            stats.animations,
+        );
+        RichText::new(&text, TEXT_SIZE, TEXT_COLOR).draw(glyphs, c, g, [x + PADDING, y + PADDING]);
+
+        // Newest frame on the right, bars going up from the bottom
+        let graph = [
+            x + PADDING,
+            y + height - PADDING - GRAPH_HEIGHT,
+            PANEL_WIDTH - PADDING * 2.0,
+            GRAPH_HEIGHT,
+        ];
+        let scale = self
+            .frame_times
+            .iter()
+            .copied()
+            .fold(GRAPH_MIN_SCALE, f64::max);
+        let bar_width = graph[2] / HISTORY as f64;
+        let first = HISTORY - self.frame_times.len();
+
+        for (i, time) in self.frame_times.iter().enumerate() {
+            let bar_height = time / scale * GRAPH_HEIGHT;
+            let color = if *time > REFERENCE_FRAME * 2.0 {
+                SLOW_BAR_COLOR
+            } else {
+                BAR_COLOR
+            };
+            rectangle(
+                color,
+                [
+                    graph[0] + (first + i) as f64 * bar_width,
+                    graph[1] + GRAPH_HEIGHT - bar_height,
+                    bar_width,
+                    bar_height,
+                ],
+                c.transform,
+                g,
+            );
+        }
+
+        let reference_y = graph[1] + GRAPH_HEIGHT - REFERENCE_FRAME / scale * GRAPH_HEIGHT;
+        Line::new(REFERENCE_COLOR, 0.5).draw_from_to(
+            [graph[0], reference_y],
+            [graph[0] + graph[2], reference_y],
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn fps(frame_time: f64) -> f64 {
+    if frame_time > 0.0 {
+        1000.0 / frame_time
+    } else {
+        0.0
+    }
+}
+
+fn average(times: &VecDeque<f64>) -> f64 {
+    if times.is_empty() {
+        return 0.0;
+    }
+    times.iter().sum::<f64>() / times.len() as f64
+}
+
+// The average time of the slowest given part of frames, at least one of them. The 1% low is the
+// frame rate over the slowest 1%
+fn slowest_average(times: &VecDeque<f64>, part: f64) -> f64 {
+    if times.is_empty() {
+        return 0.0;
+    }
+
+    let mut sorted = times.iter().copied().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| b.total_cmp(a));
+    let count = ((sorted.len() as f64 * part).ceil() as usize).clamp(1, sorted.len());
+    sorted[..count].iter().sum::<f64>() / count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lows_come_from_the_slowest_frames() {
+        // 990 frames at 4ms with 9 stutters at 20ms and one at 50ms
+        let mut times = VecDeque::from(vec![4.0; 990]);
+        times.extend([20.0; 9]);
+        times.push_back(50.0);
+
+        // The slowest 1% is exactly the stutters
+        assert_eq!(slowest_average(&times, 0.01), 23.0);
+        assert_eq!(slowest_average(&times, 0.005), 26.0);
+        assert_eq!(slowest_average(&times, 0.001), 50.0);
+        assert_eq!(slowest_average(&times, 1.0), 4.19);
+        assert_eq!(fps(slowest_average(&times, 0.001)), 20.0);
+
+        assert_eq!(slowest_average(&VecDeque::new(), 0.01), 0.0);
+        assert_eq!(slowest_average(&VecDeque::from(vec![8.0]), 0.001), 8.0);
+    }
+
+    #[test]
+    fn frame_times_are_kept_for_the_history() {
+        let mut overlay = PerfOverlay::new();
+        for _ in 0..HISTORY + 10 {
+            overlay.drawn(Duration::from_millis(1));
+        }
+        assert_eq!(overlay.frame_times.len(), HISTORY);
+
+        overlay.input();
+        overlay.drawn(Duration::ZERO);
+        assert!(overlay.pending_input.is_none());
+    }
+}
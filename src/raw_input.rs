@@ -0,0 +1,428 @@
+use graphics::math::Vec2d;
+use piston_window::{Button, ButtonArgs, ButtonState, MouseButton};
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Presses the window hasn't reported by then never reach it, like those made in other windows
+const STALE: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RawButton {
+    // X keycodes less 8, which winit reports as the scancode
+    Key(u32),
+    // X button numbers
+    Mouse(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RawEvent {
+    Button(RawButton, ButtonState),
+    // Pen position in millimetres from the tablet's top left corner
+    Pen(Vec2d),
+}
+
+// Input read from the X server on a thread of its own, which gets it when it happens instead of
+// when the window is done drawing and gets around to it. Piston still says what was pressed, this
+// only says when. Tablets are read here too, piston only sees the system cursor they move
+pub struct RawInput {
+    receiver: Receiver<(RawEvent, Instant)>,
+    arrivals: Arrivals,
+    pens: VecDeque<(Vec2d, Instant)>,
+}
+
+impl RawInput {
+    // Nothing when there's no X server to listen to, inputs are then timed by the window
+    pub fn start() -> Option<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let (ready_sender, ready) = mpsc::channel();
+
+        thread::spawn(move || {
+            if let Err(e) = listen(sender, &ready_sender) {
+                let _ = ready_sender.send(Err(e));
+            }
+        });
+
+        match ready.recv() {
+            Ok(Ok(())) => Some(Self {
+                receiver,
+                arrivals: Arrivals::new(),
+                pens: VecDeque::new(),
+            }),
+            Ok(Err(e)) => {
+                println!("Could not read raw input, timing inputs by frame: {}", e);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    // When the press or release the window reported happened, if it was seen here
+    pub fn arrival(&mut self, args: &ButtonArgs) -> Option<Instant> {
+        self.receive();
+        self.arrivals
+            .take(raw_button(args)?, args.state, Instant::now())
+    }
+
+    // Pen positions in the order they came in, with when they did
+    pub fn next_pen(&mut self) -> Option<(Vec2d, Instant)> {
+        self.receive();
+        self.pens.pop_front()
+    }
+
+    // Presses made while the window is in the background are never reported by it
+    pub fn clear(&mut self) {
+        self.receive();
+        self.arrivals = Arrivals::new();
+        self.pens.clear();
+    }
+
+    fn receive(&mut self) {
+        for (event, at) in self.receiver.try_iter() {
+            match event {
+                RawEvent::Button(button, state) => self.arrivals.push(button, state, at),
+                RawEvent::Pen(position) => self.pens.push_back((position, at)),
+            }
+        }
+    }
+}
+
+// Button events waiting for the window to report them, oldest first
+struct Arrivals {
+    buttons: VecDeque<(RawButton, ButtonState, Instant)>,
+}
+
+impl Arrivals {
+    fn new() -> Self {
+        Self {
+            buttons: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, button: RawButton, state: ButtonState, at: Instant) {
+        self.buttons.push_back((button, state, at));
+    }
+
+    fn take(&mut self, button: RawButton, state: ButtonState, now: Instant) -> Option<Instant> {
+        self.buttons
+            .retain(|(.., at)| now.saturating_duration_since(*at) < STALE);
+
+        let index = self
+            .buttons
+            .iter()
+            .position(|(b, s, _)| (*b, *s) == (button, state))?;
+        self.buttons.remove(index).map(|(.., at)| at)
+    }
+}
+
+// A device's first two axes when they're absolute, like a tablet's or a touchscreen's
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PenAxes {
+    min: Vec2d,
+    // Device units per millimetre
+    resolution: Vec2d,
+    position: Vec2d,
+}
+
+impl PenAxes {
+    // Raw events only carry the axes that changed, their values packed in the order of the mask
+    fn moved(&mut self, mask: &[u8], values: &[f64]) -> Option<Vec2d> {
+        let changed = (0..mask.len() * 8).filter(|axis| mask[axis / 8] & (1 << (axis % 8)) != 0);
+        let mut moved = false;
+
+        for (axis, value) in changed.zip(values) {
+            if axis < 2 {
+                self.position[axis] = *value;
+                moved = true;
+            }
+        }
+
+        moved.then(|| {
+            [
+                (self.position[0] - self.min[0]) / self.resolution[0],
+                (self.position[1] - self.min[1]) / self.resolution[1],
+            ]
+        })
+    }
+}
+
+fn raw_button(args: &ButtonArgs) -> Option<RawButton> {
+    match args.button {
+        Button::Keyboard(_) => args
+            .scancode
+            .map(|scancode| RawButton::Key(scancode as u32)),
+        Button::Mouse(MouseButton::Left) => Some(RawButton::Mouse(1)),
+        Button::Mouse(MouseButton::Middle) => Some(RawButton::Mouse(2)),
+        Button::Mouse(MouseButton::Right) => Some(RawButton::Mouse(3)),
+        _ => None,
+    }
+}
+
+// Reports ready once it's listening, then keeps going until the receiver is dropped
+#[cfg(target_os = "linux")]
+fn listen(
+    sender: Sender<(RawEvent, Instant)>,
+    ready: &Sender<Result<(), String>>,
+) -> Result<(), String> {
+    use std::collections::HashMap;
+    use std::{mem, ptr, slice};
+    use x11_dl::xinput2::{
+        self, XIAnyClassInfo, XIEventMask, XIRawEvent, XIValuatorClassInfo, XInput2,
+    };
+    use x11_dl::xlib::{self, Xlib};
+
+    let xlib = Xlib::open().map_err(|e| e.to_string())?;
+    let xi = XInput2::open().map_err(|e| e.to_string())?;
+
+    unsafe {
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        if display.is_null() {
+            return Err(String::from("no X display"));
+        }
+
+        let (mut opcode, mut first_event, mut first_error) = (0, 0, 0);
+        if (xlib.XQueryExtension)(
+            display,
+            c"XInputExtension".as_ptr(),
+            &mut opcode,
+            &mut first_event,
+            &mut first_error,
+        ) == 0
+        {
+            return Err(String::from("the X server has no XInput extension"));
+        }
+
+        // Raw events only go to clients other than the one grabbing the device since 2.1
+        let (mut major, mut minor) = (2, 1);
+        if (xi.XIQueryVersion)(display, &mut major, &mut minor) != xlib::Success as i32 {
+            return Err(String::from("the X server doesn't support XInput 2.1"));
+        }
+
+        // Master devices send every press once, whichever keyboard or mouse it came from. Motion
+        // comes from the devices themselves, the master only moves in screen pixels
+        let mut button_mask = [0u8; 4];
+        for event in [
+            xinput2::XI_RawKeyPress,
+            xinput2::XI_RawKeyRelease,
+            xinput2::XI_RawButtonPress,
+            xinput2::XI_RawButtonRelease,
+        ] {
+            xinput2::XISetMask(&mut button_mask, event);
+        }
+        let mut motion_mask = [0u8; 4];
+        xinput2::XISetMask(&mut motion_mask, xinput2::XI_RawMotion);
+
+        let mut event_masks = [
+            XIEventMask {
+                deviceid: xinput2::XIAllMasterDevices,
+                mask_len: button_mask.len() as i32,
+                mask: button_mask.as_mut_ptr(),
+            },
+            XIEventMask {
+                deviceid: xinput2::XIAllDevices,
+                mask_len: motion_mask.len() as i32,
+                mask: motion_mask.as_mut_ptr(),
+            },
+        ];
+        (xi.XISelectEvents)(
+            display,
+            (xlib.XDefaultRootWindow)(display),
+            event_masks.as_mut_ptr(),
+            event_masks.len() as i32,
+        );
+        (xlib.XFlush)(display);
+
+        // Looked up the first time a device moves, which also covers tablets plugged in later
+        let pen_axes = |deviceid| {
+            let mut count = 0;
+            let info = (xi.XIQueryDevice)(display, deviceid, &mut count);
+            if info.is_null() {
+                return None;
+            }
+
+            let mut axes = [None; 2];
+            let classes = slice::from_raw_parts((*info).classes, (*info).num_classes as usize);
+            for class in classes
+                .iter()
+                .map(|class| &*(*class as *const XIAnyClassInfo))
+            {
+                if class._type != xinput2::XIValuatorClass {
+                    continue;
+                }
+
+                let valuator = &*(class as *const XIAnyClassInfo as *const XIValuatorClassInfo);
+                // Resolutions are in units per metre, and unknown without one
+                if valuator.number < 2
+                    && valuator.mode == xinput2::XIModeAbsolute
+                    && valuator.resolution > 0
+                {
+                    axes[valuator.number as usize] = Some((
+                        valuator.min,
+                        valuator.resolution as f64 / 1000.0,
+                        valuator.value,
+                    ));
+                }
+            }
+            (xi.XIFreeDeviceInfo)(info);
+
+            let [Some(x), Some(y)] = axes else {
+                return None;
+            };
+            Some(PenAxes {
+                min: [x.0, y.0],
+                resolution: [x.1, y.1],
+                position: [x.2, y.2],
+            })
+        };
+        let mut pens: HashMap<i32, Option<PenAxes>> = HashMap::new();
+
+        let _ = ready.send(Ok(()));
+
+        loop {
+            let mut event: xlib::XEvent = mem::zeroed();
+            (xlib.XNextEvent)(display, &mut event);
+            let at = Instant::now();
+
+            let cookie = &mut event.generic_event_cookie;
+            if cookie.type_ != xlib::GenericEvent
+                || cookie.extension != opcode
+                || (xlib.XGetEventData)(display, cookie) == 0
+            {
+                continue;
+            }
+
+            let raw = &*(cookie.data as *const XIRawEvent);
+            let detail = raw.detail as u32;
+            let event = match cookie.evtype {
+                // Each device's own motion, not the copy its master sends
+                xinput2::XI_RawMotion if raw.deviceid != raw.sourceid => None,
+                xinput2::XI_RawMotion => {
+                    let pen = pens
+                        .entry(raw.deviceid)
+                        .or_insert_with(|| pen_axes(raw.deviceid));
+
+                    pen.as_mut().and_then(|pen| {
+                        let mask = slice::from_raw_parts(
+                            raw.valuators.mask,
+                            raw.valuators.mask_len as usize,
+                        );
+                        let count = mask.iter().map(|bits| bits.count_ones() as usize).sum();
+                        let values = slice::from_raw_parts(raw.raw_values, count);
+
+                        pen.moved(mask, values).map(RawEvent::Pen)
+                    })
+                }
+                xinput2::XI_RawKeyPress => Some(RawEvent::Button(
+                    RawButton::Key(detail - 8),
+                    ButtonState::Press,
+                )),
+                xinput2::XI_RawKeyRelease => Some(RawEvent::Button(
+                    RawButton::Key(detail - 8),
+                    ButtonState::Release,
+                )),
+                xinput2::XI_RawButtonPress => Some(RawEvent::Button(
+                    RawButton::Mouse(detail),
+                    ButtonState::Press,
+                )),
+                _ => Some(RawEvent::Button(
+                    RawButton::Mouse(detail),
+                    ButtonState::Release,
+                )),
+            };
+            (xlib.XFreeEventData)(display, cookie);
+
+            let Some(event) = event else {
+                continue;
+            };
+            if sender.send((event, at)).is_err() {
+                (xlib.XCloseDisplay)(display);
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn listen(
+    _sender: Sender<(RawEvent, Instant)>,
+    _ready: &Sender<Result<(), String>>,
+) -> Result<(), String> {
+    Err(String::from("only X11 is supported"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_keep_the_time_they_happened() {
+        let frame = Instant::now();
+        let mut arrivals = Arrivals::new();
+        let (x, z) = (RawButton::Key(45), RawButton::Key(44));
+
+        // Both pressed and one released while the last frame was drawn
+        arrivals.push(z, ButtonState::Press, frame);
+        arrivals.push(x, ButtonState::Press, frame + Duration::from_millis(3));
+        arrivals.push(z, ButtonState::Release, frame + Duration::from_millis(9));
+
+        let now = frame + Duration::from_millis(16);
+        assert_eq!(
+            arrivals.take(x, ButtonState::Press, now),
+            Some(frame + Duration::from_millis(3))
+        );
+        assert_eq!(
+            arrivals.take(z, ButtonState::Release, now),
+            Some(frame + Duration::from_millis(9))
+        );
+        assert_eq!(arrivals.take(z, ButtonState::Press, now), Some(frame));
+        assert_eq!(arrivals.take(z, ButtonState::Press, now), None);
+    }
+
+    #[test]
+    fn repeated_presses_are_matched_in_order() {
+        let frame = Instant::now();
+        let mut arrivals = Arrivals::new();
+        let key = RawButton::Key(45);
+
+        for (i, state) in [ButtonState::Press, ButtonState::Release, ButtonState::Press]
+            .into_iter()
+            .enumerate()
+        {
+            arrivals.push(key, state, frame + Duration::from_millis(i as u64 * 4));
+        }
+
+        let now = frame + Duration::from_millis(16);
+        let first = arrivals.take(key, ButtonState::Press, now).unwrap();
+        let second = arrivals.take(key, ButtonState::Press, now).unwrap();
+        assert!(first < second);
+    }
+
+    #[test]
+    fn presses_the_window_never_reports_are_dropped() {
+        let frame = Instant::now();
+        let mut arrivals = Arrivals::new();
+        let key = RawButton::Mouse(1);
+
+        arrivals.push(key, ButtonState::Press, frame);
+        assert_eq!(arrivals.take(key, ButtonState::Press, frame + STALE), None);
+        assert!(arrivals.buttons.is_empty());
+    }
+
+    #[test]
+    fn pens_report_millimetres_from_the_corner() {
+        // A tablet with 100 units per millimetre
+        let mut pen = PenAxes {
+            min: [0.0, 0.0],
+            resolution: [100.0, 100.0],
+            position: [0.0, 0.0],
+        };
+
+        assert_eq!(pen.moved(&[0b11], &[7600.0, 4750.0]), Some([76.0, 47.5]));
+        // Only the y axis changed
+        assert_eq!(pen.moved(&[0b10], &[950.0]), Some([76.0, 9.5]));
+        // Pressure and tilt alone don't move the pen
+        assert_eq!(pen.moved(&[0b1100], &[512.0, 10.0]), None);
+        assert_eq!(pen.moved(&[0b101, 0], &[100.0, 512.0]), Some([1.0, 9.5]));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::settings::Settings;
+use crate::skin::Skin;
+use crate::text::Fonts;
+use graphics::math::{Scalar, Vec2d};
+use graphics::Context;
+use piston_window::{Button, G2d};
+use rosu_pp::{AnyStars, Beatmap, GameMode};
+
+// What a ruleset gets to build itself from besides the beatmap
+pub struct RulesetContext<'a> {
+    pub settings: &'a Settings,
+    pub skin: &'a Skin,
+}
+
+// Score state every mode exposes, for the HUD and the results
+pub struct ScoreSummary {
+    pub score: u64,
+    pub max_combo: u32,
+    pub accuracy: f64,
+}
+
+// A game mode: how beatmaps convert to its objects, which inputs it reacts to, how objects
+// get judged and scored and how its playfield is drawn. The game screen does the rest
+pub trait Ruleset: Sized {
+    // Input once bound from a key or mouse button, like a drum side or a mania column
+    type Action: Copy + Eq;
+
+    const MODE: GameMode;
+
+    // Converts the beatmap into the mode's own objects
+    fn new(map: &Beatmap, ctx: &RulesetContext) -> Self;
+
+    fn action_for(&self, button: Button) -> Option<Self::Action>;
+
+    fn press(&mut self, action: Self::Action, time: f64);
+
+    fn release(&mut self, _action: Self::Action, _time: f64) {}
+
+    // Only modes aiming with the cursor care about it
+    fn cursor_moved(&mut self, _position: Vec2d, _window_size: Vec2d) {}
+
+    // Judges whatever went by without being hit, runs every update
+    fn update(&mut self, time: f64);
+
+    fn is_finished(&self) -> bool;
+
+    fn score(&self) -> ScoreSummary;
+
+    // Count of each judgement, printed with the results
+    fn judgement_counts(&self) -> String;
+
+    fn render(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, time: f64);
+
+    // Difficulty calculation hook, rosu-pp's star rating unless the mode knows better
+    fn stars(map: &Beatmap) -> f64 {
+        AnyStars::new(map).mode(Self::MODE).calculate().stars()
+    }
+}
+
+// Scale and offset fitting a playfield of the given size into the window, keeping its aspect
+// ratio and leaving a margin around it
+pub fn fit_playfield(window_size: Vec2d, playfield_size: Vec2d, fill: Scalar) -> (Scalar, Vec2d) {
+    let [win_width, win_height] = window_size;
+    let [width, height] = playfield_size;
+
+    let scale = f64::min(win_width * fill / width, win_height * fill / height);
+    let offset = [
+        (win_width - width * scale) / 2.0,
+        (win_height - height * scale) / 2.0,
+    ];
+
+    (scale, offset)
+}
@@ -0,0 +1,397 @@
+use crate::cursor::CursorTrail;
+use crate::cursor_input::{InputMode, TabletArea};
+use crate::display::{DisplayMode, FrameLimiter, Resolution};
+use crate::key_bindings::{GameAction, KeyBindings};
+use crate::skin::DEFAULT_SKIN;
+use piston_window::{Button, Key};
+use rosu_pp::GameMode;
+use std::collections::HashMap;
+use std::fs;
+
+const SETTINGS_FILE: &str = "settings.cfg";
+pub const MAX_MANIA_KEYS: usize = 10;
+
+// Stored the same way as osu!'s own .cfg files: one `Key = Value` pair per line
+pub struct Settings {
+    pub background_video: bool,
+    // Mode standard maps get converted to, other maps always play in their own mode
+    pub ruleset: GameMode,
+    pub skin: String,
+    // Same 1-40 scale as osu!, higher is faster
+    pub mania_scroll_speed: u32,
+    // Custom mania bindings by key count, saved as `ManiaLayout4K = D F J K`
+    mania_layouts: HashMap<usize, Vec<Key>>,
+    pub key_bindings: KeyBindings,
+    pub input_mode: InputMode,
+    // Multiplies raw mouse movement, only in mouse mode
+    pub cursor_sensitivity: f64,
+    pub tablet_area: TabletArea,
+    // Keeps the gameplay cursor inside of the window
+    pub confine_cursor: bool,
+    // Scale of the cursor on top of the skin's, 0.5-2
+    pub cursor_size: f64,
+    pub cursor_rotate: bool,
+    pub cursor_trail: CursorTrail,
+    pub cursor_ripples: bool,
+    // Speed of menu animations, 0.25-4
+    pub animation_speed: f64,
+    // Size of menus on top of scaling with the window's height, 0.5-2
+    pub ui_scale: f64,
+    // Keeps gameplay to a 4:3 area in the middle of wider windows
+    pub letterbox: bool,
+    pub display_mode: DisplayMode,
+    // Windowed size, or what fullscreen switches the monitor to
+    pub resolution: Resolution,
+    pub vsync: bool,
+    pub frame_limiter: FrameLimiter,
+    // Frames per second with the custom limiter, 30-1000
+    pub custom_frame_limit: u32,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let mut settings = Self::default();
+
+        let content = match fs::read_to_string(SETTINGS_FILE) {
+            Ok(content) => content,
+            Err(_) => {
+                println!("No settings file found, using defaults");
+                return settings;
+            }
+        };
+
+        let values = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect::<HashMap<_, _>>();
+
+        if let Some(value) = values.get("BackgroundVideo") {
+            settings.background_video = *value == "1";
+        }
+
+        if let Some(value) = values.get("Ruleset") {
+            settings.ruleset = match *value {
+                "taiko" => GameMode::Taiko,
+                "catch" => GameMode::Catch,
+                "mania" => GameMode::Mania,
+                _ => GameMode::Osu,
+            };
+        }
+
+        if let Some(value) = values.get("Skin") {
+            settings.skin = value.to_string();
+        }
+
+        if let Some(speed) = values.get("ManiaScrollSpeed").and_then(|v| v.parse().ok()) {
+            settings.mania_scroll_speed = u32::clamp(speed, 1, 40);
+        }
+
+        settings.key_bindings = KeyBindings::load(&values);
+
+        if let Some(value) = values.get("InputMode") {
+            settings.input_mode = match *value {
+                "tablet" => InputMode::Tablet,
+                _ => InputMode::Mouse,
+            };
+        }
+
+        if let Some(sensitivity) = values
+            .get("CursorSensitivity")
+            .and_then(|v| v.parse::<f64>().ok())
+        {
+            settings.cursor_sensitivity = sensitivity.clamp(0.1, 6.0);
+        }
+
+        if let Some(value) = values.get("TabletArea") {
+            match TabletArea::parse(value) {
+                Some(area) => settings.tablet_area = area,
+                None => println!("Ignoring invalid tablet area \"{}\"", value),
+            }
+        }
+
+        if let Some(value) = values.get("ConfineCursor") {
+            settings.confine_cursor = *value == "1";
+        }
+
+        if let Some(size) = values.get("CursorSize").and_then(|v| v.parse::<f64>().ok()) {
+            settings.cursor_size = size.clamp(0.5, 2.0);
+        }
+
+        if let Some(value) = values.get("CursorRotate") {
+            settings.cursor_rotate = *value == "1";
+        }
+
+        if let Some(value) = values.get("CursorTrail") {
+            settings.cursor_trail = match *value {
+                "off" => CursorTrail::Off,
+                "long" => CursorTrail::Long,
+                _ => CursorTrail::Sprites,
+            };
+        }
+
+        if let Some(value) = values.get("CursorRipples") {
+            settings.cursor_ripples = *value == "1";
+        }
+
+        if let Some(speed) = values
+            .get("AnimationSpeed")
+            .and_then(|v| v.parse::<f64>().ok())
+        {
+            settings.animation_speed = speed.clamp(0.25, 4.0);
+        }
+
+        if let Some(scale) = values.get("UiScale").and_then(|v| v.parse::<f64>().ok()) {
+            settings.ui_scale = scale.clamp(0.5, 2.0);
+        }
+
+        if let Some(value) = values.get("Letterbox") {
+            settings.letterbox = *value == "1";
+        }
+
+        if let Some(value) = values.get("DisplayMode") {
+            settings.display_mode = match *value {
+                "borderless" => DisplayMode::Borderless,
+                "fullscreen" => DisplayMode::Fullscreen,
+                _ => DisplayMode::Windowed,
+            };
+        }
+
+        if let Some(value) = values.get("Resolution") {
+            match Resolution::parse(value) {
+                Some(resolution) => settings.resolution = resolution,
+                None => println!("Invalid resolution: {}", value),
+            }
+        }
+
+        if let Some(value) = values.get("VSync") {
+            settings.vsync = *value == "1";
+        }
+
+        if let Some(value) = values.get("FrameLimiter") {
+            settings.frame_limiter = match *value {
+                "2x" => FrameLimiter::Refresh2x,
+                "4x" => FrameLimiter::Refresh4x,
+                "8x" => FrameLimiter::Refresh8x,
+                "custom" => FrameLimiter::Custom,
+                _ => FrameLimiter::Unlimited,
+            };
+        }
+
+        if let Some(limit) = values
+            .get("CustomFrameLimit")
+            .and_then(|v| v.parse::<u32>().ok())
+        {
+            settings.custom_frame_limit = limit.clamp(30, 1000);
+        }
+
+        for keys in 1..=MAX_MANIA_KEYS {
+            let Some(value) = values.get(format!("ManiaLayout{}K", keys).as_str()) else {
+                continue;
+            };
+
+            let layout = value
+                .split_whitespace()
+                .map(parse_key)
+                .collect::<Option<Vec<_>>>();
+
+            match layout {
+                Some(layout) if layout.len() == keys => {
+                    if let Err((key, action)) = settings.set_mania_layout(keys, layout) {
+                        println!(
+                            "Ignoring {}K mania layout \"{}\", {:?} is bound to {:?}",
+                            keys, value, key, action
+                        );
+                    }
+                }
+                _ => println!("Ignoring invalid {}K mania layout \"{}\"", keys, value),
+            }
+        }
+
+        settings
+    }
+
+    // Keys the game screen uses would never reach the column. Skipping the intro gives way to
+    // the column instead, it's on the middle one by default
+    fn set_mania_layout(&mut self, keys: usize, layout: Vec<Key>) -> Result<(), (Key, GameAction)> {
+        let taken = layout.iter().find_map(|key| {
+            self.key_bindings
+                .action_for(Button::Keyboard(*key), GameMode::Mania)
+                .filter(|action| *action != GameAction::SkipIntro)
+                .map(|action| (*key, action))
+        });
+        if let Some(taken) = taken {
+            return Err(taken);
+        }
+
+        self.mania_layouts.insert(keys, layout);
+        Ok(())
+    }
+
+    pub fn mania_layout(&self, keys: usize) -> Vec<Key> {
+        self.mania_layouts
+            .get(&keys)
+            .cloned()
+            .unwrap_or_else(|| default_mania_layout(keys))
+    }
+
+    pub fn save(&self) {
+        let ruleset = match self.ruleset {
+            GameMode::Osu => "osu",
+            GameMode::Taiko => "taiko",
+            GameMode::Catch => "catch",
+            GameMode::Mania => "mania",
+        };
+
+        let mut content = format!(
+            "BackgroundVideo = {}\nRuleset = {}\nSkin = {}\nManiaScrollSpeed = {}\n",
+            self.background_video as u8, ruleset, self.skin, self.mania_scroll_speed
+        );
+
+        content += &self.key_bindings.save();
+
+        let input_mode = match self.input_mode {
+            InputMode::Mouse => "mouse",
+            InputMode::Tablet => "tablet",
+        };
+        content += &format!(
+            "InputMode = {}\nCursorSensitivity = {}\nTabletArea = {}\nConfineCursor = {}\n",
+            input_mode,
+            self.cursor_sensitivity,
+            self.tablet_area.to_setting(),
+            self.confine_cursor as u8
+        );
+
+        let cursor_trail = match self.cursor_trail {
+            CursorTrail::Off => "off",
+            CursorTrail::Sprites => "sprites",
+            CursorTrail::Long => "long",
+        };
+        content += &format!(
+            "CursorSize = {}\nCursorRotate = {}\nCursorTrail = {}\nCursorRipples = {}\n",
+            self.cursor_size, self.cursor_rotate as u8, cursor_trail, self.cursor_ripples as u8
+        );
+
+        content += &format!("AnimationSpeed = {}\n", self.animation_speed);
+        content += &format!(
+            "UiScale = {}\nLetterbox = {}\n",
+            self.ui_scale, self.letterbox as u8
+        );
+
+        let display_mode = match self.display_mode {
+            DisplayMode::Windowed => "windowed",
+            DisplayMode::Borderless => "borderless",
+            DisplayMode::Fullscreen => "fullscreen",
+        };
+        let frame_limiter = match self.frame_limiter {
+            FrameLimiter::Unlimited => "unlimited",
+            FrameLimiter::Refresh2x => "2x",
+            FrameLimiter::Refresh4x => "4x",
+            FrameLimiter::Refresh8x => "8x",
+            FrameLimiter::Custom => "custom",
+        };
+        content += &format!(
+            "DisplayMode = {}\nResolution = {}\nVSync = {}\nFrameLimiter = {}\nCustomFrameLimit = {}\n",
+            display_mode,
+            self.resolution.to_setting(),
+            self.vsync as u8,
+            frame_limiter,
+            self.custom_frame_limit
+        );
+
+        let mut layouts = self.mania_layouts.iter().collect::<Vec<_>>();
+        layouts.sort_by_key(|(keys, _)| **keys);
+
+        for (keys, layout) in layouts {
+            let names = layout
+                .iter()
+                .map(|key| format!("{:?}", key))
+                .collect::<Vec<_>>();
+            content += &format!("ManiaLayout{}K = {}\n", keys, names.join(" "));
+        }
+
+        if let Err(e) = fs::write(SETTINGS_FILE, content) {
+            println!("Could not save settings: {}", e);
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            background_video: true,
+            ruleset: GameMode::Osu,
+            skin: DEFAULT_SKIN.to_string(),
+            mania_scroll_speed: 8,
+            mania_layouts: HashMap::new(),
+            key_bindings: KeyBindings::default(),
+            input_mode: InputMode::Mouse,
+            cursor_sensitivity: 1.0,
+            tablet_area: TabletArea::default(),
+            confine_cursor: true,
+            cursor_size: 1.0,
+            cursor_rotate: false,
+            cursor_trail: CursorTrail::Sprites,
+            cursor_ripples: true,
+            animation_speed: 1.0,
+            ui_scale: 1.0,
+            letterbox: false,
+            display_mode: DisplayMode::Windowed,
+            resolution: Resolution::default(),
+            vsync: false,
+            frame_limiter: FrameLimiter::Unlimited,
+            custom_frame_limit: 240,
+        }
+    }
+}
+
+// Keys are saved by name, piston has no parser for them so every key code gets tried
+pub fn parse_key(name: &str) -> Option<Key> {
+    (0..0x80)
+        .chain(0x4000_0039..=0x4000_011A)
+        .map(Key::from)
+        .find(|key| *key != Key::Unknown && format!("{:?}", key) == name)
+}
+
+// Same layouts osu! starts with, the middle key being space on odd key counts
+fn default_mania_layout(keys: usize) -> Vec<Key> {
+    use Key::*;
+
+    match keys {
+        1 => vec![Space],
+        2 => vec![F, J],
+        3 => vec![F, Space, J],
+        4 => vec![D, F, J, K],
+        5 => vec![D, F, Space, J, K],
+        6 => vec![S, D, F, J, K, L],
+        7 => vec![S, D, F, Space, J, K, L],
+        8 => vec![A, S, D, F, J, K, L, Semicolon],
+        9 => vec![A, S, D, F, Space, J, K, L, Semicolon],
+        _ => vec![A, S, D, F, V, N, J, K, L, Semicolon],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mania_layouts_cannot_use_the_game_screen_keys() {
+        let mut settings = Settings::default();
+
+        assert_eq!(
+            settings.set_mania_layout(2, vec![Key::Escape, Key::J]),
+            Err((Key::Escape, GameAction::Pause))
+        );
+        assert_eq!(settings.mania_layout(2), default_mania_layout(2));
+
+        // Skipping is on space too, but gives way to the column
+        assert_eq!(
+            settings.set_mania_layout(3, vec![Key::A, Key::Space, Key::L]),
+            Ok(())
+        );
+        assert_eq!(settings.mania_layout(3), [Key::A, Key::Space, Key::L]);
+    }
+}
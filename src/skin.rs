@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SKINS_DIRECTORY: &str = "Skins";
+pub const DEFAULT_SKIN: &str = "default";
+
+// Column background and note colours of the built-in layout
+const COLUMN_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.8];
+const WHITE_NOTE_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const BLUE_NOTE_COLOR: [f32; 4] = [0.3, 0.6, 1.0, 1.0];
+const CENTER_NOTE_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 1.0];
+
+// Mania layout for one key count. Sizes are in osu!'s 640x480 skin space
+#[derive(Clone, Debug)]
+pub struct ManiaSkin {
+    pub column_start: f64,
+    pub column_widths: Vec<f64>,
+    // Gap after each column but the last
+    pub column_spacing: Vec<f64>,
+    pub hit_position: f64,
+    pub column_colors: Vec<[f32; 4]>,
+    pub note_colors: Vec<[f32; 4]>,
+}
+
+impl ManiaSkin {
+    fn new(keys: usize) -> Self {
+        // Notes mirror around the middle, with the centre column of odd layouts standing out
+        let note_colors = (0..keys)
+            .map(|column| match column.min(keys - 1 - column) {
+                _ if keys % 2 == 1 && column == keys / 2 => CENTER_NOTE_COLOR,
+                distance if distance % 2 == 0 => WHITE_NOTE_COLOR,
+                _ => BLUE_NOTE_COLOR,
+            })
+            .collect();
+
+        Self {
+            column_start: 136.0,
+            column_widths: vec![30.0; keys],
+            column_spacing: vec![0.0; keys.saturating_sub(1)],
+            hit_position: 402.0,
+            column_colors: vec![COLUMN_COLOR; keys],
+            note_colors,
+        }
+    }
+
+    pub fn keys(&self) -> usize {
+        self.column_widths.len()
+    }
+
+    // Left edge of a column relative to the start of the stage
+    pub fn column_x(&self, column: usize) -> f64 {
+        self.column_widths[..column].iter().sum::<f64>()
+            + self.column_spacing[..column].iter().sum::<f64>()
+    }
+
+    pub fn stage_width(&self) -> f64 {
+        self.column_x(self.keys() - 1) + self.column_widths[self.keys() - 1]
+    }
+
+    // Keys from a skin.ini [Mania] section, anything missing or malformed keeps its default
+    fn apply(&mut self, values: &HashMap<&str, &str>) {
+        let keys = self.keys();
+
+        if let Some(start) = values.get("ColumnStart").and_then(|v| v.parse().ok()) {
+            self.column_start = start;
+        }
+
+        if let Some(position) = values.get("HitPosition").and_then(|v| v.parse().ok()) {
+            self.hit_position = position;
+        }
+
+        if let Some(widths) = values.get("ColumnWidth") {
+            Self::apply_list(&mut self.column_widths, widths);
+        }
+
+        if let Some(spacing) = values.get("ColumnSpacing") {
+            Self::apply_list(&mut self.column_spacing, spacing);
+        }
+
+        for column in 0..keys {
+            let name = format!("Colour{}", column + 1);
+            if let Some(color) = values.get(name.as_str()).and_then(|v| parse_color(v)) {
+                self.column_colors[column] = color;
+            }
+
+            let name = format!("ColourLight{}", column + 1);
+            if let Some(color) = values.get(name.as_str()).and_then(|v| parse_color(v)) {
+                self.note_colors[column] = color;
+            }
+        }
+    }
+
+    fn apply_list(list: &mut [f64], value: &str) {
+        for (item, value) in list.iter_mut().zip(value.split(',')) {
+            if let Ok(value) = value.trim().parse() {
+                *item = value;
+            }
+        }
+    }
+}
+
+// Skins are folders in Skins/ holding an osu! skin.ini, only the parts we draw are read
+pub struct Skin {
+    mania: HashMap<usize, ManiaSkin>,
+    // None for the built-in skin, which has no images
+    directory: Option<PathBuf>,
+}
+
+impl Skin {
+    pub fn load(name: &str) -> Self {
+        let mut skin = Self {
+            mania: HashMap::new(),
+            directory: None,
+        };
+
+        if name == DEFAULT_SKIN {
+            return skin;
+        }
+
+        let directory = Path::new(SKINS_DIRECTORY).join(name);
+        let path = directory.join("skin.ini");
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                println!(
+                    "Could not load skin {:?}: {}, using the default one",
+                    path, e
+                );
+                return skin;
+            }
+        };
+
+        skin.directory = Some(directory);
+
+        // Every [Mania] section applies to the key count it declares
+        let mut sections = Vec::new();
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                sections.push((line, HashMap::new()));
+            } else if let Some((key, value)) =
+                line.split_once(':').filter(|_| !line.starts_with("//"))
+            {
+                if let Some((_, values)) = sections.last_mut() {
+                    values.insert(key.trim(), value.trim());
+                }
+            }
+        }
+
+        for (_, values) in sections.iter().filter(|(name, _)| *name == "[Mania]") {
+            let Some(keys) = values.get("Keys").and_then(|v| v.parse::<usize>().ok()) else {
+                continue;
+            };
+
+            if keys > 0 {
+                let mut mania = ManiaSkin::new(keys);
+                mania.apply(values);
+                skin.mania.insert(keys, mania);
+            }
+        }
+
+        skin
+    }
+
+    // Path of one of the skin's images, when it has it
+    pub fn image(&self, name: &str) -> Option<PathBuf> {
+        let path = self.directory.as_ref()?.join(format!("{}.png", name));
+        path.is_file().then_some(path)
+    }
+
+    pub fn mania(&self, keys: usize) -> ManiaSkin {
+        self.mania
+            .get(&keys)
+            .cloned()
+            .unwrap_or_else(|| ManiaSkin::new(keys))
+    }
+}
+
+// Comma separated 0-255 channels, alpha being optional
+fn parse_color(value: &str) -> Option<[f32; 4]> {
+    let channels = value
+        .split(',')
+        .map(|channel| channel.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match channels[..] {
+        [r, g, b] => Some([r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0]),
+        [r, g, b, a] => Some([
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        ]),
+        _ => None,
+    }
+}
@@ -0,0 +1,430 @@
+use graphics::math::{add, dot, mul_scalar, square_len, sub, Scalar, Vec2d};
+use rosu_pp::parse::{PathControlPoint, PathType};
+use rosu_pp::Beatmap;
+use std::f64::consts::PI;
+
+// Same tolerances osu! uses when flattening curves into line segments
+const BEZIER_TOLERANCE: Scalar = 0.25;
+const CIRCULAR_ARC_TOLERANCE: Scalar = 0.1;
+const CATMULL_DETAIL: usize = 50;
+
+// osu!stable stops generating ticks this long before a slider ends
+const LEGACY_LAST_TICK_OFFSET: f64 = 36.0;
+const MAX_SLIDER_LENGTH: f64 = 100000.0;
+const BASE_SCORING_DISTANCE: f64 = 100.0;
+
+// Slider curve flattened into a polyline, relative to the slider's head
+pub struct SliderPath {
+    points: Vec<Vec2d>,
+    // Distance along the path at each point
+    lengths: Vec<Scalar>,
+}
+
+impl SliderPath {
+    // The path gets cut or extended to the expected distance, as osu! does with the pixel
+    // length stored in the beatmap
+    pub fn new(control_points: &[PathControlPoint], expected_distance: Option<f64>) -> Self {
+        let vertices = control_points
+            .iter()
+            .map(|point| [point.pos.x as Scalar, point.pos.y as Scalar])
+            .collect::<Vec<_>>();
+
+        let mut points = Vec::new();
+        let mut start = 0;
+
+        // Each control point with a type starts a new segment, which shares its first
+        // vertex with the end of the previous one
+        for i in 0..vertices.len() {
+            if control_points[i].kind.is_none() && i < vertices.len() - 1 {
+                continue;
+            }
+
+            let segment = &vertices[start..=i];
+
+            match control_points[start].kind.unwrap_or(PathType::Linear) {
+                _ if segment.len() == 1 => points.push(segment[0]),
+                PathType::Linear => points.extend_from_slice(segment),
+                PathType::PerfectCurve => match segment {
+                    [a, b, c] if Self::approximate_circular_arc(*a, *b, *c, &mut points) => {}
+                    _ => Self::approximate_bezier(segment, &mut points),
+                },
+                PathType::Bezier => Self::approximate_bezier(segment, &mut points),
+                PathType::Catmull => Self::approximate_catmull(segment, &mut points),
+            }
+
+            start = i;
+        }
+
+        points.dedup();
+
+        let mut path = Self {
+            points,
+            lengths: Vec::new(),
+        };
+        path.compute_lengths(control_points, expected_distance);
+
+        path
+    }
+
+    fn compute_lengths(&mut self, control_points: &[PathControlPoint], expected: Option<f64>) {
+        let mut calculated = 0.0;
+        self.lengths.push(0.0);
+
+        for pair in self.points.windows(2) {
+            calculated += square_len(sub(pair[1], pair[0])).sqrt();
+            self.lengths.push(calculated);
+        }
+
+        let Some(expected) = expected.filter(|&expected| expected != calculated) else {
+            return;
+        };
+
+        // osu!stable doesn't extend sliders ending on two identical control points
+        if let [.., a, b] = control_points {
+            if a.pos == b.pos && expected > calculated {
+                return;
+            }
+        }
+
+        // The last segment is the one that gets resized, drop every point past the expected end
+        self.lengths.pop();
+        while self
+            .lengths
+            .last()
+            .is_some_and(|&length| length >= expected)
+        {
+            self.lengths.pop();
+            self.points.pop();
+        }
+
+        if self.points.len() < 2 || self.lengths.is_empty() {
+            self.lengths = vec![0.0];
+            self.points.truncate(1);
+            return;
+        }
+
+        let end = self.points.len() - 1;
+        let direction = sub(self.points[end], self.points[end - 1]);
+        let direction_length = square_len(direction).sqrt();
+        let direction = if direction_length > 0.0 {
+            mul_scalar(direction, 1.0 / direction_length)
+        } else {
+            [0.0, 0.0]
+        };
+
+        let remaining = expected - self.lengths[self.lengths.len() - 1];
+        self.points[end] = add(self.points[end - 1], mul_scalar(direction, remaining));
+        self.lengths.push(expected);
+    }
+
+    pub fn distance(&self) -> f64 {
+        self.lengths.last().copied().unwrap_or(0.0)
+    }
+
+    // Position at a fraction of the path's length, relative to its first point
+    pub fn position_at(&self, progress: f64) -> Vec2d {
+        let (Some(&first), Some(&last)) = (self.points.first(), self.points.last()) else {
+            return [0.0, 0.0];
+        };
+
+        let distance = progress.clamp(0.0, 1.0) * self.distance();
+        let i = self.lengths.partition_point(|&length| length < distance);
+
+        if i == 0 {
+            return first;
+        }
+        if i >= self.points.len() {
+            return last;
+        }
+
+        let (d0, d1) = (self.lengths[i - 1], self.lengths[i]);
+        if (d1 - d0).abs() <= f64::EPSILON {
+            return self.points[i - 1];
+        }
+
+        let w = (distance - d0) / (d1 - d0);
+        add(
+            self.points[i - 1],
+            mul_scalar(sub(self.points[i], self.points[i - 1]), w),
+        )
+    }
+
+    // Adaptive subdivision: halves keep getting split until they are flat enough to be
+    // approximated by their own control points
+    fn approximate_bezier(control_points: &[Vec2d], output: &mut Vec<Vec2d>) {
+        let mut to_flatten = vec![control_points.to_vec()];
+
+        while let Some(parent) = to_flatten.pop() {
+            if Self::bezier_is_flat_enough(&parent) {
+                Self::bezier_approximate(&parent, output);
+                continue;
+            }
+
+            let (left, right) = Self::bezier_subdivide(&parent);
+            to_flatten.push(right);
+            to_flatten.push(left);
+        }
+
+        output.push(control_points[control_points.len() - 1]);
+    }
+
+    fn bezier_is_flat_enough(points: &[Vec2d]) -> bool {
+        let limit = BEZIER_TOLERANCE * BEZIER_TOLERANCE * 4.0;
+
+        points.windows(3).all(|triple| {
+            let curvature = add(sub(triple[0], mul_scalar(triple[1], 2.0)), triple[2]);
+            square_len(curvature) <= limit
+        })
+    }
+
+    // De Casteljau split of a curve into its two halves
+    fn bezier_subdivide(points: &[Vec2d]) -> (Vec<Vec2d>, Vec<Vec2d>) {
+        let count = points.len();
+        let mut midpoints = points.to_vec();
+        let mut left = vec![[0.0, 0.0]; count];
+        let mut right = vec![[0.0, 0.0]; count];
+
+        for i in 0..count {
+            left[i] = midpoints[0];
+            right[count - i - 1] = midpoints[count - i - 1];
+
+            for j in 0..count - i - 1 {
+                midpoints[j] = mul_scalar(add(midpoints[j], midpoints[j + 1]), 0.5);
+            }
+        }
+
+        (left, right)
+    }
+
+    fn bezier_approximate(points: &[Vec2d], output: &mut Vec<Vec2d>) {
+        let count = points.len();
+        let (mut left, right) = Self::bezier_subdivide(points);
+        left.extend_from_slice(&right[1..]);
+
+        output.push(points[0]);
+
+        for i in 1..count - 1 {
+            let index = 2 * i;
+            let point = add(
+                add(left[index - 1], mul_scalar(left[index], 2.0)),
+                left[index + 1],
+            );
+            output.push(mul_scalar(point, 0.25));
+        }
+    }
+
+    fn approximate_catmull(points: &[Vec2d], output: &mut Vec<Vec2d>) {
+        let count = points.len();
+
+        for i in 0..count - 1 {
+            let v1 = if i > 0 { points[i - 1] } else { points[i] };
+            let v2 = points[i];
+            let v3 = if i < count - 1 {
+                points[i + 1]
+            } else {
+                sub(mul_scalar(v2, 2.0), v1)
+            };
+            let v4 = if i < count - 2 {
+                points[i + 2]
+            } else {
+                sub(mul_scalar(v3, 2.0), v2)
+            };
+
+            for c in 0..CATMULL_DETAIL {
+                let t = c as f64 / CATMULL_DETAIL as f64;
+                let next_t = (c + 1) as f64 / CATMULL_DETAIL as f64;
+
+                output.push(Self::catmull_point(v1, v2, v3, v4, t));
+                output.push(Self::catmull_point(v1, v2, v3, v4, next_t));
+            }
+        }
+    }
+
+    fn catmull_point(v1: Vec2d, v2: Vec2d, v3: Vec2d, v4: Vec2d, t: f64) -> Vec2d {
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        let component = |i: usize| {
+            0.5 * (2.0 * v2[i]
+                + (-v1[i] + v3[i]) * t
+                + (2.0 * v1[i] - 5.0 * v2[i] + 4.0 * v3[i] - v4[i]) * t2
+                + (-v1[i] + 3.0 * v2[i] - 3.0 * v3[i] + v4[i]) * t3)
+        };
+
+        [component(0), component(1)]
+    }
+
+    // Returns false for degenerate arcs, which are drawn as beziers instead
+    fn approximate_circular_arc(a: Vec2d, b: Vec2d, c: Vec2d, output: &mut Vec<Vec2d>) -> bool {
+        let d = 2.0 * (a[0] * (b[1] - c[1]) + b[0] * (c[1] - a[1]) + c[0] * (a[1] - b[1]));
+        if d.abs() < 1e-3 {
+            return false;
+        }
+
+        let (a_sq, b_sq, c_sq) = (square_len(a), square_len(b), square_len(c));
+        let centre = [
+            (a_sq * (b[1] - c[1]) + b_sq * (c[1] - a[1]) + c_sq * (a[1] - b[1])) / d,
+            (a_sq * (c[0] - b[0]) + b_sq * (a[0] - c[0]) + c_sq * (b[0] - a[0])) / d,
+        ];
+
+        let (d_a, d_c) = (sub(a, centre), sub(c, centre));
+        let radius = square_len(d_a).sqrt();
+
+        let theta_start = d_a[1].atan2(d_a[0]);
+        let mut theta_end = d_c[1].atan2(d_c[0]);
+        while theta_end < theta_start {
+            theta_end += 2.0 * PI;
+        }
+
+        let mut direction = 1.0;
+        let mut theta_range = theta_end - theta_start;
+
+        // Go the other way around when b isn't on the arc between a and c
+        let a_to_c = sub(c, a);
+        if dot([a_to_c[1], -a_to_c[0]], sub(b, a)) < 0.0 {
+            direction = -1.0;
+            theta_range = 2.0 * PI - theta_range;
+        }
+
+        // Enough points to keep the distance between the arc and its chords under the tolerance
+        let amount_points = if 2.0 * radius <= CIRCULAR_ARC_TOLERANCE {
+            2
+        } else {
+            let step = 2.0 * (1.0 - CIRCULAR_ARC_TOLERANCE / radius).acos();
+            ((theta_range / step).ceil() as usize).max(2)
+        };
+
+        for i in 0..amount_points {
+            let fract = i as f64 / (amount_points - 1) as f64;
+            let theta = theta_start + direction * fract * theta_range;
+
+            output.push(add(centre, mul_scalar([theta.cos(), theta.sin()], radius)));
+        }
+
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SliderEventKind {
+    Head,
+    Tick,
+    Repeat,
+    // Where osu!stable considered the slider over, only matters for timing what comes after
+    LegacyLastTick,
+    Tail,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SliderEvent {
+    pub kind: SliderEventKind,
+    pub time: f64,
+    // Fraction of the path's length the event is at
+    pub progress: f64,
+}
+
+// Velocity in pixels per millisecond and distance between ticks for a slider starting at `time`
+pub fn slider_velocity(map: &Beatmap, time: f64) -> (f64, f64) {
+    let timing_point = map.timing_point_at(time);
+    let slider_vel = map.difficulty_point_at(time).unwrap_or_default().slider_vel;
+
+    let scoring_distance = BASE_SCORING_DISTANCE * map.slider_mult * slider_vel;
+    let velocity = scoring_distance / timing_point.beat_len;
+    // Maps older than v8 spaced ticks regardless of slider velocity
+    let tick_distance = if map.version < 8 {
+        scoring_distance / map.tick_rate / slider_vel
+    } else {
+        scoring_distance / map.tick_rate
+    };
+
+    (velocity, tick_distance)
+}
+
+// Everything happening along a slider, in order. Velocity is in pixels per millisecond
+pub fn slider_events(
+    start_time: f64,
+    span_duration: f64,
+    velocity: f64,
+    tick_distance: f64,
+    total_distance: f64,
+    span_count: usize,
+) -> Vec<SliderEvent> {
+    let length = total_distance.min(MAX_SLIDER_LENGTH);
+    let tick_distance = tick_distance.clamp(0.0, length);
+    // Ticks too close to the end of a span are skipped
+    let min_distance_from_end = velocity * 10.0;
+
+    let mut events = vec![SliderEvent {
+        kind: SliderEventKind::Head,
+        time: start_time,
+        progress: 0.0,
+    }];
+
+    if tick_distance != 0.0 {
+        for span in 0..span_count {
+            let span_start_time = start_time + span as f64 * span_duration;
+            let reversed = span % 2 == 1;
+
+            // Ticks are placed from the start of the path so that they land on the same spot
+            // on every span
+            let mut ticks = Vec::new();
+            let mut distance = tick_distance;
+
+            while distance <= length && distance < length - min_distance_from_end {
+                let progress = distance / length;
+                let time_progress = if reversed { 1.0 - progress } else { progress };
+
+                ticks.push(SliderEvent {
+                    kind: SliderEventKind::Tick,
+                    time: span_start_time + time_progress * span_duration,
+                    progress,
+                });
+
+                distance += tick_distance;
+            }
+
+            if reversed {
+                ticks.reverse();
+            }
+            events.extend(ticks);
+
+            if span < span_count - 1 {
+                events.push(SliderEvent {
+                    kind: SliderEventKind::Repeat,
+                    time: span_start_time + span_duration,
+                    progress: ((span + 1) % 2) as f64,
+                });
+            }
+        }
+    }
+
+    let total_duration = span_count as f64 * span_duration;
+    let final_span_start_time = start_time + (span_count - 1) as f64 * span_duration;
+    let final_span_end_time = f64::max(
+        start_time + total_duration / 2.0,
+        final_span_start_time + span_duration - LEGACY_LAST_TICK_OFFSET,
+    );
+
+    let mut final_progress = if span_duration > 0.0 {
+        (final_span_end_time - final_span_start_time) / span_duration
+    } else {
+        0.0
+    };
+    if span_count.is_multiple_of(2) {
+        final_progress = 1.0 - final_progress;
+    }
+
+    events.push(SliderEvent {
+        kind: SliderEventKind::LegacyLastTick,
+        time: final_span_end_time,
+        progress: final_progress,
+    });
+
+    events.push(SliderEvent {
+        kind: SliderEventKind::Tail,
+        time: start_time + total_duration,
+        progress: (span_count % 2) as f64,
+    });
+
+    events
+}
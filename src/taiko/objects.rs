@@ -0,0 +1,104 @@
+use crate::beatmap::difficulty_range;
+use rosu_pp::parse::{HitObjectKind, HitSound};
+use rosu_pp::{Beatmap, GameMode};
+
+const SWELL_HIT_MULTIPLIER: f64 = 1.65;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TaikoObjectKind {
+    Don,
+    Kat,
+    DrumRoll { end_time: f64, tick_spacing: f64 },
+    Swell { end_time: f64, required_hits: u32 },
+}
+
+#[derive(Clone, Debug)]
+pub struct TaikoObject {
+    pub start_time: f64,
+    pub kind: TaikoObjectKind,
+    pub big: bool,
+    // Pixels per millisecond at which the object scrolls towards the hit target
+    pub velocity: f64,
+}
+
+// Base scroll speed, in pixels per millisecond at 1.0x slider velocity and 60 BPM
+const BASE_VELOCITY: f64 = 0.35;
+
+// Native taiko maps are read as they are, anything else goes through rosu-pp's taiko
+// conversion first: short sliders become strings of hits, the rest become drum rolls
+pub fn convert(map: &Beatmap) -> Vec<TaikoObject> {
+    let converted = map.convert_mode(GameMode::Taiko);
+
+    // The converter bakes taiko's 1.4x velocity multiplier in, but drum roll lengths are
+    // measured against the original one
+    let slider_mult = match map.mode {
+        GameMode::Taiko => map.slider_mult,
+        _ => converted.slider_mult / 1.4,
+    };
+
+    converted
+        .hit_objects
+        .iter()
+        .zip(converted.sounds.iter())
+        .map(|(obj, sound)| {
+            let timing_point = converted.timing_point_at(obj.start_time);
+            let difficulty_point = converted
+                .difficulty_point_at(obj.start_time)
+                .unwrap_or_default();
+
+            let velocity =
+                BASE_VELOCITY * difficulty_point.slider_vel * 1000.0 / timing_point.beat_len;
+
+            let kind = match &obj.kind {
+                HitObjectKind::Circle if sound.clap() || sound.whistle() => TaikoObjectKind::Kat,
+                HitObjectKind::Circle => TaikoObjectKind::Don,
+                HitObjectKind::Slider {
+                    pixel_len,
+                    repeats,
+                    control_points,
+                    ..
+                } => {
+                    let length = pixel_len.unwrap_or_else(|| {
+                        control_points
+                            .windows(2)
+                            .map(|pair| {
+                                let (a, b) = (pair[0].pos, pair[1].pos);
+                                ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt() as f64
+                            })
+                            .sum()
+                    });
+
+                    let beat_len = timing_point.beat_len * difficulty_point.bpm_mult;
+                    let spans = (*repeats + 1) as f64;
+                    let duration = length * spans / (100.0 * slider_mult) * beat_len;
+
+                    // Like osu!, a tick rate of 3 keeps triplets, anything else uses 1/4 ticks
+                    let ticks_per_beat = if converted.tick_rate == 3.0 { 3.0 } else { 4.0 };
+
+                    TaikoObjectKind::DrumRoll {
+                        end_time: obj.start_time + duration,
+                        tick_spacing: timing_point.beat_len / ticks_per_beat,
+                    }
+                }
+                HitObjectKind::Spinner { end_time } | HitObjectKind::Hold { end_time } => {
+                    let hit_multiplier =
+                        difficulty_range(converted.od as f64, 3.0, 5.0, 7.5) * SWELL_HIT_MULTIPLIER;
+                    let required_hits =
+                        f64::max(1.0, (end_time - obj.start_time) / 1000.0 * hit_multiplier);
+
+                    TaikoObjectKind::Swell {
+                        end_time: *end_time,
+                        required_hits: required_hits as u32,
+                    }
+                }
+            };
+
+            TaikoObject {
+                start_time: obj.start_time,
+                kind,
+                big: sound.finish(),
+                velocity,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,377 @@
+use super::objects::{self, TaikoObject, TaikoObjectKind};
+use super::score::{HitResult, HitWindows, TaikoScore};
+use crate::key_bindings::{GameAction, KeyBindings};
+use crate::ruleset::{Ruleset, RulesetContext, ScoreSummary};
+use crate::text::Fonts;
+use graphics::math::Scalar;
+use graphics::{ellipse, rectangle, Context, Ellipse, Text, Transformed};
+use piston_window::{Button, G2d};
+use rosu_pp::{Beatmap, GameMode};
+
+// Window for the second key of a big note to count
+const STRONG_HIT_WINDOW: f64 = 30.0;
+const JUDGEMENT_DISPLAY_TIME: f64 = 300.0;
+const DRUM_FLASH_TIME: f64 = 100.0;
+// Objects further away than this are never on screen
+const MAX_LOOKAHEAD: f64 = 10000.0;
+
+const DON_COLOR: [f32; 4] = [0.92, 0.33, 0.24, 1.0];
+const KAT_COLOR: [f32; 4] = [0.27, 0.55, 0.75, 1.0];
+const DRUM_ROLL_COLOR: [f32; 4] = [0.98, 0.72, 0.0, 1.0];
+const SWELL_COLOR: [f32; 4] = [0.95, 0.55, 0.1, 1.0];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Drum {
+    Don,
+    Kat,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+// A big note that was hit with one key and can still get its second one
+struct StrongHit {
+    drum: Drum,
+    side: Side,
+    time: f64,
+    result: HitResult,
+}
+
+pub struct TaikoPlayfield {
+    objects: Vec<TaikoObject>,
+    // Everything before this has been judged
+    next_object: usize,
+    hit_windows: HitWindows,
+    bindings: KeyBindings,
+    score: TaikoScore,
+    strong_hit: Option<StrongHit>,
+    // Progress on the drum roll or swell at `next_object`
+    ticks_hit: u32,
+    last_judgement: Option<(HitResult, f64)>,
+    last_press: Option<(Drum, Side, f64)>,
+}
+
+impl TaikoPlayfield {
+    fn hit(&mut self, drum: Drum, side: Side, time: f64) {
+        if let Some(strong_hit) = self.strong_hit.take() {
+            if strong_hit.drum == drum
+                && strong_hit.side != side
+                && time - strong_hit.time <= STRONG_HIT_WINDOW
+            {
+                self.score.add_strong_bonus(strong_hit.result);
+                return;
+            }
+        }
+
+        let Some(object) = self.objects.get(self.next_object) else {
+            return;
+        };
+
+        match object.kind {
+            TaikoObjectKind::DrumRoll { tick_spacing, .. } if object.start_time <= time => {
+                // Every tick that went by can be claimed by one hit
+                let ticks_passed = ((time - object.start_time) / tick_spacing) as u32 + 1;
+
+                if self.ticks_hit < ticks_passed {
+                    self.ticks_hit += 1;
+                    self.score.add_drum_roll_tick(object.big);
+                }
+            }
+            TaikoObjectKind::Swell { required_hits, .. } if object.start_time <= time => {
+                self.ticks_hit += 1;
+                self.score.add_swell_tick();
+
+                // Finishing a swell is a bonus, it doesn't count towards accuracy or combo
+                if self.ticks_hit >= required_hits {
+                    self.score.add_swell_bonus();
+                    self.next_object += 1;
+                    self.ticks_hit = 0;
+                }
+            }
+            TaikoObjectKind::Don | TaikoObjectKind::Kat => {
+                let Some(result) = self.hit_windows.result_for(time - object.start_time) else {
+                    return;
+                };
+
+                let expected = match object.kind {
+                    TaikoObjectKind::Don => Drum::Don,
+                    _ => Drum::Kat,
+                };
+
+                let result = if drum == expected {
+                    result
+                } else {
+                    HitResult::Miss
+                };
+
+                if object.big && result != HitResult::Miss {
+                    self.strong_hit = Some(StrongHit {
+                        drum,
+                        side,
+                        time,
+                        result,
+                    });
+                }
+
+                self.judge(result, time);
+            }
+            _ => {}
+        }
+    }
+
+    fn judge(&mut self, result: HitResult, time: f64) {
+        self.score.add_hit(result);
+        self.last_judgement = Some((result, time));
+        self.next_object += 1;
+        self.ticks_hit = 0;
+    }
+
+    fn render_drum(&self, c: Context, g: &mut G2d, [right, y, height]: [Scalar; 3], time: f64) {
+        let width = height;
+        let x = right - width;
+
+        rectangle([0.2, 0.2, 0.2, 1.0], [x, y, width, height], c.transform, g);
+
+        let Some((drum, side, pressed_at)) = self.last_press else {
+            return;
+        };
+
+        let flash = 1.0 - (time - pressed_at) / DRUM_FLASH_TIME;
+        if flash <= 0.0 {
+            return;
+        }
+
+        let mut color = match drum {
+            Drum::Don => DON_COLOR,
+            Drum::Kat => KAT_COLOR,
+        };
+        color[3] = flash as f32;
+
+        let half_x = match side {
+            Side::Left => x,
+            Side::Right => x + width / 2.0,
+        };
+
+        rectangle(color, [half_x, y, width / 2.0, height], c.transform, g);
+    }
+
+    fn render_note(c: Context, g: &mut G2d, center: [Scalar; 2], radius: Scalar, color: [f32; 4]) {
+        ellipse(color, Self::circle_rect(center, radius), c.transform, g);
+        Ellipse::new_border([1.0, 1.0, 1.0, 1.0], 2.0).draw(
+            Self::circle_rect(center, radius),
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+    }
+
+    fn render_combo(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, y: Scalar) {
+        Text::new_color([1.0, 1.0, 1.0, 1.0], 24)
+            .draw(
+                &format!("{}x", self.score.combo),
+                glyphs,
+                &c.draw_state,
+                c.transform.trans(20.0, y + 40.0),
+                g,
+            )
+            .unwrap();
+    }
+
+    fn circle_rect([x, y]: [Scalar; 2], radius: Scalar) -> [Scalar; 4] {
+        [x - radius, y - radius, radius * 2.0, radius * 2.0]
+    }
+}
+
+impl Ruleset for TaikoPlayfield {
+    type Action = (Drum, Side);
+
+    const MODE: GameMode = GameMode::Taiko;
+
+    fn new(map: &Beatmap, ctx: &RulesetContext) -> Self {
+        let objects = objects::convert(map);
+        println!("Loaded {} taiko objects", objects.len());
+
+        Self {
+            objects,
+            next_object: 0,
+            hit_windows: HitWindows::new(map.od as f64),
+            bindings: ctx.settings.key_bindings.clone(),
+            score: TaikoScore::default(),
+            strong_hit: None,
+            ticks_hit: 0,
+            last_judgement: None,
+            last_press: None,
+        }
+    }
+
+    fn action_for(&self, button: Button) -> Option<Self::Action> {
+        match self.bindings.action_for(button, GameMode::Taiko)? {
+            GameAction::TaikoLeftKat => Some((Drum::Kat, Side::Left)),
+            GameAction::TaikoLeftDon => Some((Drum::Don, Side::Left)),
+            GameAction::TaikoRightDon => Some((Drum::Don, Side::Right)),
+            GameAction::TaikoRightKat => Some((Drum::Kat, Side::Right)),
+            _ => None,
+        }
+    }
+
+    fn press(&mut self, (drum, side): Self::Action, time: f64) {
+        self.last_press = Some((drum, side, time));
+        self.hit(drum, side, time);
+    }
+
+    fn update(&mut self, time: f64) {
+        while let Some(object) = self.objects.get(self.next_object) {
+            match object.kind {
+                TaikoObjectKind::Don | TaikoObjectKind::Kat => {
+                    if time - object.start_time <= self.hit_windows.miss_window() {
+                        break;
+                    }
+
+                    self.judge(HitResult::Miss, time);
+                }
+                TaikoObjectKind::DrumRoll { end_time, .. } => {
+                    if time <= end_time {
+                        break;
+                    }
+
+                    self.next_object += 1;
+                    self.ticks_hit = 0;
+                }
+                // An unfinished swell gives nothing but doesn't break combo either
+                TaikoObjectKind::Swell { end_time, .. } => {
+                    if time <= end_time {
+                        break;
+                    }
+
+                    self.next_object += 1;
+                    self.ticks_hit = 0;
+                }
+            }
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next_object >= self.objects.len()
+    }
+
+    fn score(&self) -> ScoreSummary {
+        ScoreSummary {
+            score: self.score.score,
+            max_combo: self.score.max_combo,
+            accuracy: self.score.accuracy(),
+        }
+    }
+
+    fn judgement_counts(&self) -> String {
+        format!(
+            "{} / {} / {}",
+            self.score.great, self.score.good, self.score.miss
+        )
+    }
+
+    fn render(&self, c: Context, g: &mut G2d, glyphs: &mut Fonts, time: f64) {
+        let [win_width, win_height] = c.get_view_size();
+
+        let lane_height = win_height * 0.18;
+        let lane_y = win_height * 0.25;
+        let lane_center = lane_y + lane_height / 2.0;
+        let target_x = win_width * 0.2;
+        let radius = lane_height * 0.3;
+
+        rectangle(
+            [0.0, 0.0, 0.0, 0.7],
+            [0.0, lane_y, win_width, lane_height],
+            c.transform,
+            g,
+        );
+
+        self.render_drum(c, g, [target_x - radius * 2.0, lane_y, lane_height], time);
+
+        Ellipse::new_border([1.0, 1.0, 1.0, 0.5], 2.0).draw(
+            Self::circle_rect([target_x, lane_center], radius),
+            &c.draw_state,
+            c.transform,
+            g,
+        );
+
+        let visible_end = self.objects[self.next_object..]
+            .iter()
+            .position(|object| object.start_time - time > MAX_LOOKAHEAD)
+            .map_or(self.objects.len(), |i| self.next_object + i);
+
+        // Drawn back to front so earlier objects end up on top
+        for object in self.objects[self.next_object..visible_end].iter().rev() {
+            let x = target_x + (object.start_time - time) * object.velocity;
+            let radius = if object.big { radius * 1.5 } else { radius };
+
+            if x - radius > win_width {
+                continue;
+            }
+
+            match object.kind {
+                TaikoObjectKind::Don => {
+                    Self::render_note(c, g, [x, lane_center], radius, DON_COLOR)
+                }
+                TaikoObjectKind::Kat => {
+                    Self::render_note(c, g, [x, lane_center], radius, KAT_COLOR)
+                }
+                TaikoObjectKind::DrumRoll { end_time, .. } => {
+                    let x = x.max(target_x);
+                    let end_x = target_x + (end_time - time) * object.velocity;
+
+                    rectangle(
+                        DRUM_ROLL_COLOR,
+                        [x, lane_center - radius, (end_x - x).max(0.0), radius * 2.0],
+                        c.transform,
+                        g,
+                    );
+                    Self::render_note(c, g, [end_x, lane_center], radius, DRUM_ROLL_COLOR);
+                    Self::render_note(c, g, [x, lane_center], radius, DRUM_ROLL_COLOR);
+                }
+                TaikoObjectKind::Swell { required_hits, .. } => {
+                    let x = x.max(target_x);
+                    Self::render_note(c, g, [x, lane_center], radius, SWELL_COLOR);
+
+                    if object.start_time <= time {
+                        let remaining = required_hits.saturating_sub(self.ticks_hit);
+
+                        Text::new_color([1.0, 1.0, 1.0, 1.0], 24)
+                            .draw(
+                                &remaining.to_string(),
+                                glyphs,
+                                &c.draw_state,
+                                c.transform.trans(x - 12.0, lane_center + 8.0),
+                                g,
+                            )
+                            .unwrap();
+                    }
+                }
+            }
+        }
+
+        if let Some((result, judged_at)) = self.last_judgement {
+            if time - judged_at < JUDGEMENT_DISPLAY_TIME {
+                let (text, color) = match result {
+                    HitResult::Great => ("GREAT", [1.0, 0.85, 0.3, 1.0]),
+                    HitResult::Good => ("GOOD", [0.5, 1.0, 0.5, 1.0]),
+                    HitResult::Miss => ("MISS", [1.0, 0.2, 0.2, 1.0]),
+                };
+
+                Text::new_color(color, 22)
+                    .draw(
+                        text,
+                        glyphs,
+                        &c.draw_state,
+                        c.transform.trans(target_x - 30.0, lane_y - 10.0),
+                        g,
+                    )
+                    .unwrap();
+            }
+        }
+
+        self.render_combo(c, g, glyphs, lane_y + lane_height);
+    }
+}
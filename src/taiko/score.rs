@@ -0,0 +1,132 @@
+use crate::beatmap::difficulty_range;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HitResult {
+    Great,
+    Good,
+    Miss,
+}
+
+pub struct HitWindows {
+    great: f64,
+    good: f64,
+    miss: f64,
+}
+
+impl HitWindows {
+    pub fn new(od: f64) -> Self {
+        Self {
+            great: difficulty_range(od, 50.0, 35.0, 20.0),
+            good: difficulty_range(od, 120.0, 80.0, 50.0),
+            miss: difficulty_range(od, 135.0, 95.0, 70.0),
+        }
+    }
+
+    // None when the hit is too early to count for anything
+    pub fn result_for(&self, offset: f64) -> Option<HitResult> {
+        let offset = offset.abs();
+
+        if offset <= self.great {
+            Some(HitResult::Great)
+        } else if offset <= self.good {
+            Some(HitResult::Good)
+        } else if offset <= self.miss {
+            Some(HitResult::Miss)
+        } else {
+            None
+        }
+    }
+
+    // Past this an object can't be hit anymore
+    pub fn miss_window(&self) -> f64 {
+        self.miss
+    }
+}
+
+// Classic scoring: combo multiplies the base value and big notes hit with both keys count double
+#[derive(Default)]
+pub struct TaikoScore {
+    pub score: u64,
+    pub combo: u32,
+    pub max_combo: u32,
+    pub great: u32,
+    pub good: u32,
+    pub miss: u32,
+}
+
+impl TaikoScore {
+    pub fn add_hit(&mut self, result: HitResult) {
+        let base = match result {
+            HitResult::Great => {
+                self.great += 1;
+                300
+            }
+            HitResult::Good => {
+                self.good += 1;
+                150
+            }
+            HitResult::Miss => {
+                self.miss += 1;
+                self.combo = 0;
+                return;
+            }
+        };
+
+        self.combo += 1;
+        self.max_combo = self.max_combo.max(self.combo);
+
+        let combo_bonus = (self.combo / 10).min(10) as u64 * base / 10;
+        self.score += base + combo_bonus;
+    }
+
+    // Second key of a big note, worth the same as the first one again
+    pub fn add_strong_bonus(&mut self, result: HitResult) {
+        self.score += match result {
+            HitResult::Great => 300,
+            HitResult::Good => 150,
+            HitResult::Miss => 0,
+        };
+    }
+
+    pub fn add_drum_roll_tick(&mut self, big: bool) {
+        self.score += if big { 600 } else { 300 };
+    }
+
+    pub fn add_swell_tick(&mut self) {
+        self.score += 300;
+    }
+
+    pub fn add_swell_bonus(&mut self) {
+        self.score += 300;
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        let total = self.great + self.good + self.miss;
+
+        if total == 0 {
+            1.0
+        } else {
+            (self.great as f64 + self.good as f64 * 0.5) / total as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swells_only_add_score() {
+        let mut score = TaikoScore::default();
+        score.add_hit(HitResult::Great);
+        score.add_hit(HitResult::Good);
+        let accuracy = score.accuracy();
+
+        score.add_swell_tick();
+        score.add_swell_bonus();
+        assert_eq!(score.accuracy(), accuracy);
+        assert_eq!(score.combo, 2);
+        assert_eq!((score.great, score.good, score.miss), (1, 1, 0));
+        assert_eq!(score.score, 300 + 150 + 600);
+    }
+}
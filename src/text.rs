@@ -0,0 +1,583 @@
+use crate::viewport;
+use graphics::character::{Character, CharacterCache};
+use graphics::types::{Color, FontSize};
+use graphics::{Context, Text, Transformed};
+use piston_window::{G2d, G2dTexture, GfxDevice, Glyphs, PistonWindow};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+const FONT: &str = "assets/Roboto-Regular.ttf";
+// Any fonts in here are tried for characters Roboto doesn't have, in name order
+const FALLBACK_DIRECTORY: &str = "assets/fonts";
+// Then the usual system ones, mostly for Japanese song metadata
+const SYSTEM_FALLBACK_FONTS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/noto/NotoEmoji-Regular.ttf",
+    "/usr/share/fonts/TTF/Symbola.ttf",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "C:\\Windows\\Fonts\\YuGothM.ttc",
+    "C:\\Windows\\Fonts\\msgothic.ttc",
+    "C:\\Windows\\Fonts\\seguisym.ttf",
+];
+// Of the font size, with sizes in points drawn a third bigger in pixels
+const LINE_HEIGHT: f64 = 1.5;
+const BASELINE: f64 = 1.15;
+const ELLIPSIS: &str = "…";
+
+// Text width for a font size
+pub type Measure<'a> = dyn FnMut(FontSize, &str) -> f64 + 'a;
+
+// Roboto with fallbacks, picking whichever font has each character. Draws with `Text` like a
+// single font would
+pub struct Fonts {
+    // Roboto first
+    caches: Vec<Glyphs>,
+    // Which font each character came from
+    chosen: HashMap<char, usize>,
+}
+
+impl Fonts {
+    pub fn load(window: &mut PistonWindow) -> Self {
+        let mut caches = vec![window.load_font(FONT).unwrap()];
+
+        let mut paths = fs::read_dir(FALLBACK_DIRECTORY)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        paths.extend(SYSTEM_FALLBACK_FONTS.iter().map(PathBuf::from));
+
+        for path in paths.iter().filter(|path| path.is_file()) {
+            match window.load_font(path) {
+                Ok(glyphs) => {
+                    println!("Loaded fallback font {}", path.display());
+                    caches.push(glyphs);
+                }
+                Err(err) => println!("Couldn't load font {}: {}", path.display(), err),
+            }
+        }
+
+        Self {
+            caches,
+            chosen: HashMap::new(),
+        }
+    }
+
+    fn font_for(&mut self, ch: char) -> usize {
+        if self.caches.len() == 1 || ch.is_whitespace() || ch.is_control() {
+            return 0;
+        }
+
+        let caches = &self.caches;
+        *self.chosen.entry(ch).or_insert_with(|| {
+            // Glyph 0 is what fonts draw for characters they're missing
+            caches
+                .iter()
+                .position(|glyphs| glyphs.font.glyph(ch).id().0 != 0)
+                .unwrap_or(0)
+        })
+    }
+
+    // Uploads newly cached glyphs before the frame is drawn
+    pub fn flush(&mut self, device: &mut GfxDevice) {
+        for glyphs in &mut self.caches {
+            glyphs.factory.encoder.flush(device);
+        }
+    }
+}
+
+impl CharacterCache for Fonts {
+    type Texture = G2dTexture;
+    type Error = <Glyphs as CharacterCache>::Error;
+
+    fn character(
+        &mut self,
+        font_size: FontSize,
+        ch: char,
+    ) -> Result<Character<'_, G2dTexture>, Self::Error> {
+        let index = self.font_for(ch);
+        self.caches[index].character(font_size, ch)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Align {
+    Left,
+    Centre,
+    Right,
+}
+
+impl Align {
+    fn factor(self) -> f64 {
+        match self {
+            Align::Left => 0.0,
+            Align::Centre => 0.5,
+            Align::Right => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub text: String,
+    pub size: FontSize,
+    pub color: Color,
+}
+
+impl Span {
+    pub fn new(text: &str, size: FontSize, color: Color) -> Self {
+        Self {
+            text: text.to_string(),
+            size,
+            color,
+        }
+    }
+}
+
+// Part of a line in a single style, x from where the text is drawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run {
+    pub text: String,
+    pub size: FontSize,
+    pub color: Color,
+    pub x: f64,
+    pub width: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Line {
+    pub runs: Vec<Run>,
+    pub width: f64,
+    // From the top of the text
+    pub y: f64,
+    pub height: f64,
+}
+
+impl Line {
+    fn push(&mut self, run: Run) {
+        self.width += run.width;
+        match self.runs.last_mut() {
+            Some(last) if last.size == run.size && last.color == run.color => {
+                last.text += &run.text;
+                last.width += run.width;
+            }
+            _ => self.runs.push(run),
+        }
+    }
+
+    fn pop_char(&mut self, measure: &mut Measure) -> Option<char> {
+        let last = self.runs.last_mut()?;
+        let ch = last.text.pop()?;
+        let width = measure(last.size, ch.encode_utf8(&mut [0; 4]));
+        last.width -= width;
+        self.width -= width;
+        if last.text.is_empty() {
+            self.runs.pop();
+        }
+        Some(ch)
+    }
+}
+
+enum Piece {
+    Word(Vec<Run>),
+    Space(Vec<Run>),
+    Break(FontSize),
+}
+
+// Styled text laid out in lines, wrapped within a width and cut short with an ellipsis
+#[derive(Clone, Debug, PartialEq)]
+pub struct RichText {
+    spans: Vec<Span>,
+    align: Align,
+    width: Option<f64>,
+    max_lines: Option<usize>,
+}
+
+impl RichText {
+    pub fn new(text: &str, size: FontSize, color: Color) -> Self {
+        Self::spans(vec![Span::new(text, size, color)])
+    }
+
+    pub fn spans(spans: Vec<Span>) -> Self {
+        Self {
+            spans,
+            align: Align::Left,
+            width: None,
+            max_lines: None,
+        }
+    }
+
+    // Without a width, lines are aligned around where the text is drawn
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn wrap(mut self, width: f64) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines.max(1));
+        self
+    }
+
+    // A single line ending in an ellipsis when it doesn't fit
+    pub fn truncate(self, width: f64) -> Self {
+        self.wrap(width).max_lines(1)
+    }
+
+    pub fn layout(&self, measure: &mut Measure) -> Vec<Line> {
+        let max_lines = self.max_lines.unwrap_or(usize::MAX);
+        let width = self.width.unwrap_or(f64::INFINITY);
+        let mut lines = Vec::new();
+        let mut line = Line::default();
+        let mut space = Vec::new();
+        let mut truncated = false;
+
+        for piece in pieces(&self.spans, measure) {
+            // Everything left goes on the last line, to be cut short after
+            let last_line = lines.len() + 1 >= max_lines;
+            match piece {
+                Piece::Break(_) if last_line => {
+                    truncated = true;
+                    break;
+                }
+                Piece::Break(size) => {
+                    if line.runs.is_empty() {
+                        line.height = size as f64;
+                    }
+                    lines.push(std::mem::take(&mut line));
+                    space.clear();
+                }
+                Piece::Space(runs) => space.extend(runs),
+                Piece::Word(runs) => {
+                    let word_width = runs.iter().map(|run| run.width).sum::<f64>();
+                    let space_width = space.iter().map(|run: &Run| run.width).sum::<f64>();
+
+                    if !last_line
+                        && !line.runs.is_empty()
+                        && line.width + space_width + word_width > width
+                    {
+                        lines.push(std::mem::take(&mut line));
+                        space.clear();
+                    }
+                    for run in space.drain(..) {
+                        line.push(run);
+                    }
+
+                    // Words too long for any line get broken anywhere
+                    if word_width > width && !last_line {
+                        for run in runs {
+                            for ch in run.text.chars() {
+                                let text = ch.to_string();
+                                let ch_width = measure(run.size, &text);
+                                if !line.runs.is_empty()
+                                    && line.width + ch_width > width
+                                    && lines.len() + 1 < max_lines
+                                {
+                                    lines.push(std::mem::take(&mut line));
+                                }
+                                line.push(Run {
+                                    text,
+                                    width: ch_width,
+                                    ..run.clone()
+                                });
+                            }
+                        }
+                    } else {
+                        for run in runs {
+                            line.push(run);
+                        }
+                    }
+                }
+            }
+        }
+        lines.push(line);
+
+        if let Some(line) = lines.last_mut() {
+            if truncated || line.width > width {
+                ellipsize(line, width, measure);
+            }
+        }
+
+        let mut y = 0.0;
+        for line in &mut lines {
+            let size = line
+                .runs
+                .iter()
+                .map(|run| run.size as f64)
+                .fold(line.height, f64::max);
+            line.height = size * LINE_HEIGHT;
+            line.y = y;
+            y += line.height;
+
+            let free = self.width.unwrap_or(0.0) - line.width;
+            let mut x = free * self.align.factor();
+            for run in &mut line.runs {
+                run.x = x;
+                x += run.width;
+            }
+        }
+
+        lines
+    }
+
+    // Width of the widest line and the height of them all
+    pub fn size(&self, fonts: &mut Fonts) -> [f64; 2] {
+        let lines = self.layout(&mut |size, text| fonts.width(size, text).unwrap_or(0.0));
+        [
+            lines.iter().map(|line| line.width).fold(0.0, f64::max),
+            lines.iter().map(|line| line.height).sum(),
+        ]
+    }
+
+    // From the top of the text, on the left, middle or right depending on the alignment
+    pub fn draw(&self, fonts: &mut Fonts, c: Context, g: &mut G2d, [x, y]: [f64; 2]) {
+        let lines = self.layout(&mut |size, text| fonts.width(size, text).unwrap_or(0.0));
+
+        for line in lines {
+            let size = line.height / LINE_HEIGHT;
+            for run in line.runs {
+                draw_sharp(
+                    fonts,
+                    c,
+                    g,
+                    &run.text,
+                    run.size,
+                    run.color,
+                    [x + run.x, y + line.y + size * BASELINE],
+                );
+            }
+        }
+    }
+}
+
+// Draws text from its baseline, rasterized at the size it takes on screen rather than in view
+// units, so scaled menus and HiDPI windows don't get blurry glyphs
+pub fn draw_sharp(
+    fonts: &mut Fonts,
+    c: Context,
+    g: &mut G2d,
+    text: &str,
+    size: FontSize,
+    color: Color,
+    [x, y]: [f64; 2],
+) {
+    let pixel_size = (size as f64 * viewport::pixel_scale(&c)).round().max(1.0);
+    let scale = size as f64 / pixel_size;
+    Text::new_color(color, pixel_size as FontSize)
+        .draw(
+            text,
+            fonts,
+            &c.draw_state,
+            c.transform.trans(x, y).scale(scale, scale),
+            g,
+        )
+        .unwrap();
+}
+
+// Words, the spaces between them and line breaks. Words can span styles, and characters from
+// scripts written without spaces each count as a word of their own
+fn pieces(spans: &[Span], measure: &mut Measure) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut joins_word = false;
+
+    for span in spans {
+        let run = |text: &str, measure: &mut Measure| Run {
+            text: text.to_string(),
+            size: span.size,
+            color: span.color,
+            x: 0.0,
+            width: measure(span.size, text),
+        };
+
+        let mut rest = span.text.as_str();
+        while let Some(ch) = rest.chars().next() {
+            if ch == '\n' {
+                pieces.push(Piece::Break(span.size));
+                rest = &rest[1..];
+                joins_word = false;
+                continue;
+            }
+
+            let length = if ch.is_whitespace() {
+                rest.find(|c: char| c == '\n' || !c.is_whitespace())
+                    .unwrap_or(rest.len())
+            } else if is_wide(ch) {
+                ch.len_utf8()
+            } else {
+                rest.find(|c: char| c.is_whitespace() || is_wide(c))
+                    .unwrap_or(rest.len())
+            };
+            let (text, remaining) = rest.split_at(length);
+            rest = remaining;
+
+            let run = run(text, measure);
+            match pieces.last_mut() {
+                _ if ch.is_whitespace() => {
+                    pieces.push(Piece::Space(vec![run]));
+                    joins_word = false;
+                }
+                Some(Piece::Word(runs)) if joins_word && !is_wide(ch) => {
+                    runs.push(run);
+                }
+                _ => {
+                    pieces.push(Piece::Word(vec![run]));
+                    joins_word = !is_wide(ch);
+                }
+            }
+        }
+    }
+
+    pieces
+}
+
+// Cuts a line short enough to end in an ellipsis within the width
+fn ellipsize(line: &mut Line, width: f64, measure: &mut Measure) {
+    let Some(last) = line.runs.last() else {
+        return;
+    };
+    let ellipsis = Run {
+        text: ELLIPSIS.to_string(),
+        x: 0.0,
+        width: measure(last.size, ELLIPSIS),
+        ..last.clone()
+    };
+
+    while line.width + ellipsis.width > width && line.pop_char(measure).is_some() {}
+    while line
+        .runs
+        .last()
+        .is_some_and(|run| run.text.ends_with(char::is_whitespace))
+    {
+        line.pop_char(measure);
+    }
+    line.push(ellipsis);
+}
+
+// Kana, kanji and hangul, which lines can break between
+fn is_wide(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x1100..=0x11FF
+            | 0x2E80..=0x303F
+            | 0x3040..=0x30FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xAC00..=0xD7AF
+            | 0xF900..=0xFAFF
+            | 0xFF00..=0xFFEF
+            | 0x20000..=0x2FFFF
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Color = [1.0, 1.0, 1.0, 1.0];
+    const RED: Color = [1.0, 0.0, 0.0, 1.0];
+
+    // Every character as wide as the font size
+    fn layout(text: &RichText) -> Vec<Line> {
+        text.layout(&mut |size, text| text.chars().count() as f64 * size as f64)
+    }
+
+    fn texts(lines: &[Line]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.runs.iter().map(|run| run.text.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn words_wrap_within_the_width() {
+        let text = RichText::new("the quick brown fox\njumps", 10, WHITE).wrap(100.0);
+        let lines = layout(&text);
+
+        assert_eq!(texts(&lines), ["the quick", "brown fox", "jumps"]);
+        assert_eq!(lines[0].width, 90.0);
+        assert_eq!(lines[1].y, 15.0);
+        assert_eq!(lines[2].height, 15.0);
+
+        // Longer than a line on their own
+        let lines = layout(&RichText::new("a abcdefghijklm", 10, WHITE).wrap(50.0));
+        assert_eq!(texts(&lines), ["a", "abcde", "fghij", "klm"]);
+    }
+
+    #[test]
+    fn lines_break_between_japanese_characters() {
+        let lines = layout(&RichText::new("夜に駆ける (YOASOBI)", 10, WHITE).wrap(100.0));
+        assert_eq!(texts(&lines), ["夜に駆ける", "(YOASOBI)"]);
+
+        let lines = layout(&RichText::new("夜に駆ける", 10, WHITE).wrap(30.0));
+        assert_eq!(texts(&lines), ["夜に駆", "ける"]);
+    }
+
+    #[test]
+    fn alignment_uses_the_free_space() {
+        let text = RichText::new("ab\nabcd", 10, WHITE).wrap(100.0);
+
+        let lines = layout(&text.clone().align(Align::Right));
+        assert_eq!(lines[0].runs[0].x, 80.0);
+        assert_eq!(lines[1].runs[0].x, 60.0);
+
+        let lines = layout(&text.align(Align::Centre));
+        assert_eq!(lines[0].runs[0].x, 40.0);
+
+        // Around x without a width
+        let lines = layout(&RichText::new("abcd", 10, WHITE).align(Align::Right));
+        assert_eq!(lines[0].runs[0].x, -40.0);
+    }
+
+    #[test]
+    fn truncated_text_ends_in_an_ellipsis() {
+        let lines = layout(&RichText::new("Camellia - Exit This Earth", 10, WHITE).truncate(100.0));
+        assert_eq!(texts(&lines), ["Camellia…"]);
+        assert!(lines[0].width <= 100.0);
+
+        let lines = layout(
+            &RichText::new("one two three four five", 10, WHITE)
+                .wrap(90.0)
+                .max_lines(2),
+        );
+        assert_eq!(texts(&lines), ["one two", "three fo…"]);
+
+        // Fits already
+        let lines = layout(&RichText::new("short", 10, WHITE).truncate(100.0));
+        assert_eq!(texts(&lines), ["short"]);
+    }
+
+    #[test]
+    fn spans_keep_their_style_and_join_words() {
+        let text = RichText::spans(vec![
+            Span::new("Artist", 10, RED),
+            Span::new("'s - Title", 20, WHITE),
+        ])
+        .wrap(200.0);
+        let lines = layout(&text);
+
+        assert_eq!(lines[0].runs.len(), 2);
+        assert_eq!(lines[0].runs[0].text, "Artist");
+        assert_eq!(lines[0].runs[1].x, 60.0);
+        assert_eq!(lines[0].height, 30.0);
+
+        // Both halves of a word move to the next line together
+        let lines = layout(
+            &RichText::spans(vec![
+                Span::new("x Art", 10, WHITE),
+                Span::new("ist", 10, RED),
+            ])
+            .wrap(70.0),
+        );
+        assert_eq!(texts(&lines), ["x", "Artist"]);
+    }
+}
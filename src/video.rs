@@ -0,0 +1,275 @@
+use graphics::{image, Context};
+use piston_window::texture::{CreateTexture, Format, UpdateTexture};
+use piston_window::{G2d, G2dTexture, G2dTextureContext, TextureSettings, Transformed};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+const MAX_VIDEO_WIDTH: u32 = 1280;
+const MAX_VIDEO_FPS: f64 = 60.0;
+
+// Past this distance between the decoder and the audio clock we restart ffmpeg at the
+// right position instead of decoding our way there
+const RESYNC_THRESHOLD: f64 = 1000.0;
+
+// Decodes frames by piping raw RGBA out of ffmpeg, which keeps codec support out of
+// our build and lets us fall back gracefully when it isn't installed
+struct FrameDecoder {
+    process: Child,
+    frames: Receiver<Vec<u8>>,
+    start_time: f64,
+    decoded_frames: u32,
+}
+
+impl FrameDecoder {
+    fn spawn(path: &Path, start_time: f64, width: u32, height: u32, fps: f64) -> Option<Self> {
+        let mut process = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-ss"])
+            .arg(format!("{:.3}", start_time / 1000.0))
+            .arg("-i")
+            .arg(path)
+            .args(["-an", "-vf"])
+            .arg(format!("fps={},scale={}:{}", fps, width, height))
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+
+        let mut stdout = process.stdout.take()?;
+        let frame_size = (width * height * 4) as usize;
+
+        // A small bound keeps ffmpeg from running ahead of playback
+        let (sender, frames) = mpsc::sync_channel(3);
+
+        thread::spawn(move || loop {
+            let mut frame = vec![0; frame_size];
+
+            if stdout.read_exact(&mut frame).is_err() || sender.send(frame).is_err() {
+                break;
+            }
+        });
+
+        Some(Self {
+            process,
+            frames,
+            start_time,
+            decoded_frames: 0,
+        })
+    }
+
+    fn next_frame_time(&self, fps: f64) -> f64 {
+        self.start_time + self.decoded_frames as f64 * 1000.0 / fps
+    }
+}
+
+impl Drop for FrameDecoder {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+pub struct VideoPlayer {
+    path: PathBuf,
+    offset: f64,
+    width: u32,
+    height: u32,
+    fps: f64,
+    decoder: Option<FrameDecoder>,
+    texture: Option<G2dTexture>,
+    visible: bool,
+    ended: bool,
+    failed: bool,
+}
+
+impl VideoPlayer {
+    pub fn open<P: AsRef<Path>>(path: P, offset: f64) -> Option<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let Some((width, height, fps)) = Self::probe(&path) else {
+            println!("Unsupported video {:?}, falling back to background", path);
+            return None;
+        };
+
+        // Keep the upload size reasonable, ffmpeg wants even dimensions for most pixel formats
+        let scale = f64::min(1.0, MAX_VIDEO_WIDTH as f64 / width as f64);
+        let width = ((width as f64 * scale) as u32) & !1;
+        let height = ((height as f64 * scale) as u32) & !1;
+
+        Some(Self {
+            path,
+            offset,
+            width,
+            height,
+            fps: fps.min(MAX_VIDEO_FPS),
+            decoder: None,
+            texture: None,
+            visible: false,
+            ended: false,
+            failed: false,
+        })
+    }
+
+    fn probe(path: &Path) -> Option<(u32, u32, f64)> {
+        let output = Command::new("ffprobe")
+            .args(["-v", "error", "-select_streams", "v:0"])
+            .args(["-show_entries", "stream=width,height,r_frame_rate"])
+            .args(["-of", "csv=p=0"])
+            .arg(path)
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        let output = String::from_utf8(output.stdout).ok()?;
+        let mut values = output.trim().split(',');
+
+        let width = values.next()?.parse().ok()?;
+        let height = values.next()?.parse().ok()?;
+        let fps = match values.next()?.split_once('/') {
+            Some((num, den)) => num.parse::<f64>().ok()? / den.parse::<f64>().ok()?,
+            None => return None,
+        };
+
+        if width == 0 || height == 0 || !fps.is_normal() {
+            return None;
+        }
+
+        Some((width, height, fps))
+    }
+
+    // Whether the video can't be played and the static background should be used instead
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible && !self.failed
+    }
+
+    // Must be called with the audio clock before drawing, uploads the frame matching it
+    pub fn update(&mut self, audio_time: f64, tex_ctx: &mut G2dTextureContext) {
+        if self.failed {
+            return;
+        }
+
+        let video_time = audio_time - self.offset;
+
+        if video_time < 0.0 {
+            self.visible = false;
+            self.ended = false;
+            self.decoder = None;
+            return;
+        }
+
+        let needs_resync = match &self.decoder {
+            Some(decoder) => {
+                video_time < decoder.start_time
+                    || video_time - decoder.next_frame_time(self.fps) > RESYNC_THRESHOLD
+            }
+            None => !self.ended,
+        };
+
+        if needs_resync {
+            self.ended = false;
+            self.decoder =
+                FrameDecoder::spawn(&self.path, video_time, self.width, self.height, self.fps);
+
+            if self.decoder.is_none() {
+                println!("Could not start ffmpeg, falling back to background");
+                self.failed = true;
+                return;
+            }
+        }
+
+        let Some(decoder) = &mut self.decoder else {
+            return;
+        };
+
+        let mut latest_frame = None;
+
+        while decoder.next_frame_time(self.fps) <= video_time {
+            match decoder.frames.try_recv() {
+                Ok(frame) => {
+                    decoder.decoded_frames += 1;
+                    latest_frame = Some(frame);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // ffmpeg stopped without giving us a single frame: the codec isn't supported
+                    if decoder.decoded_frames == 0 {
+                        println!("Video decoding failed, falling back to background");
+                        self.failed = true;
+                    }
+
+                    self.ended = true;
+                    self.visible = false;
+                    self.decoder = None;
+                    return;
+                }
+            }
+        }
+
+        if let Some(frame) = latest_frame {
+            self.upload_frame(&frame, tex_ctx);
+        }
+    }
+
+    fn upload_frame(&mut self, frame: &[u8], tex_ctx: &mut G2dTextureContext) {
+        let size = [self.width, self.height];
+
+        match &mut self.texture {
+            Some(texture) => {
+                UpdateTexture::update(texture, tex_ctx, Format::Rgba8, frame, [0, 0], size)
+                    .unwrap();
+            }
+            None => {
+                self.texture = Some(
+                    CreateTexture::create(
+                        tex_ctx,
+                        Format::Rgba8,
+                        frame,
+                        size,
+                        &TextureSettings::new(),
+                    )
+                    .unwrap(),
+                );
+            }
+        }
+
+        self.visible = true;
+    }
+
+    pub fn render(&self, c: Context, g: &mut G2d) {
+        let Some(texture) = &self.texture else {
+            return;
+        };
+
+        if !self.is_visible() {
+            return;
+        }
+
+        let [win_width, win_height] = c.get_view_size();
+        let scale = f64::max(
+            win_width / self.width as f64,
+            win_height / self.height as f64,
+        );
+
+        let transform = c
+            .transform
+            .trans(
+                (win_width - self.width as f64 * scale) / 2.0,
+                (win_height - self.height as f64 * scale) / 2.0,
+            )
+            .scale(scale, scale);
+
+        image(texture, transform, g);
+    }
+}
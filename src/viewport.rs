@@ -0,0 +1,212 @@
+use crate::ruleset;
+use graphics::math::{self, Vec2d};
+use graphics::{Context, Transformed};
+use piston_window::{Event, GenericEvent, Input, Motion};
+
+// osu!'s virtual screen. Gameplay is laid out in it, scaled to the window's height
+pub const SCREEN_SIZE: Vec2d = [640.0, 480.0];
+// Menus are laid out for a window this tall, before the UI scale setting
+const UI_REFERENCE_HEIGHT: f64 = 720.0;
+
+// How menus are scaled in the window. Sizes are in window units, the ones piston's view and the
+// mouse use, which HiDPI screens have more pixels for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    window_size: Vec2d,
+    ui_scale: f64,
+}
+
+impl Viewport {
+    pub fn new(window_size: Vec2d, ui_scale: f64) -> Self {
+        Self {
+            window_size,
+            ui_scale,
+        }
+    }
+
+    pub fn event<E: GenericEvent>(&mut self, e: &E) {
+        if let Some(args) = e.resize_args() {
+            self.window_size = args.window_size;
+        }
+    }
+
+    pub fn set_ui_scale(&mut self, ui_scale: f64) {
+        self.ui_scale = ui_scale;
+    }
+
+    // Window units per menu unit
+    pub fn ui_factor(&self) -> f64 {
+        (self.window_size[1] / UI_REFERENCE_HEIGHT * self.ui_scale).max(0.01)
+    }
+
+    // Menus draw in their own units, and see the window's size in them as the view size
+    pub fn ui_context(&self, c: Context) -> Context {
+        let factor = self.ui_factor();
+        Context {
+            view: c.view.scale(factor, factor),
+            transform: c.transform.scale(factor, factor),
+            ..c
+        }
+    }
+
+    // The same event with mouse positions in menu units
+    pub fn ui_event(&self, e: &Event) -> Event {
+        match e {
+            Event::Input(Input::Move(Motion::MouseCursor([x, y])), timestamp) => {
+                let factor = self.ui_factor();
+                Event::Input(
+                    Input::Move(Motion::MouseCursor([x / factor, y / factor])),
+                    *timestamp,
+                )
+            }
+            e => e.clone(),
+        }
+    }
+}
+
+// Pixels per unit of a context's transform, also counting whatever it was scaled by
+pub fn pixel_scale(c: &Context) -> f64 {
+    let [_, draw_height] = draw_size(c);
+    let [[_, _, _], [shear, scale, _]] = c.transform;
+    shear.hypot(scale) * draw_height / 2.0
+}
+
+// Where the 640x480 screen goes in a view, kept at 4:3 with bars on the sides it doesn't fill
+pub fn screen_rect(view_size: Vec2d) -> [f64; 4] {
+    let (scale, [x, y]) = ruleset::fit_playfield(view_size, SCREEN_SIZE, 1.0);
+    [x, y, SCREEN_SIZE[0] * scale, SCREEN_SIZE[1] * scale]
+}
+
+// Draws into a rectangle of the view as if it was the whole view, without scaling anything
+pub fn area_context(c: Context, [x, y, w, h]: [f64; 4]) -> Context {
+    Context {
+        view: math::abs_transform(w, h),
+        transform: c.transform.trans(x, y),
+        ..c
+    }
+}
+
+// Limits drawing to a rectangle, given in the context's coordinates. Nested clips stay inside
+// the outer one
+pub fn clip(c: Context, [x, y, w, h]: [f64; 4]) -> Context {
+    // Scissor rectangles are in pixels
+    let [left, top] = to_pixels(&c, [x, y]);
+    let [right, bottom] = to_pixels(&c, [x + w, y + h]);
+    let mut scissor = [
+        left.max(0.0) as u32,
+        top.max(0.0) as u32,
+        (right - left).max(0.0) as u32,
+        (bottom - top).max(0.0) as u32,
+    ];
+
+    if let Some([outer_x, outer_y, outer_w, outer_h]) = c.draw_state.scissor {
+        let left = scissor[0].max(outer_x);
+        let top = scissor[1].max(outer_y);
+        let right = (scissor[0] + scissor[2]).min(outer_x + outer_w);
+        let bottom = (scissor[1] + scissor[3]).min(outer_y + outer_h);
+        scissor = [
+            left,
+            top,
+            right.saturating_sub(left),
+            bottom.saturating_sub(top),
+        ];
+    }
+
+    Context {
+        draw_state: c.draw_state.scissor(scissor),
+        ..c
+    }
+}
+
+fn draw_size(c: &Context) -> Vec2d {
+    c.viewport.map_or(c.get_view_size(), |viewport| {
+        [viewport.draw_size[0] as f64, viewport.draw_size[1] as f64]
+    })
+}
+
+fn to_pixels(c: &Context, position: Vec2d) -> Vec2d {
+    let [width, height] = draw_size(c);
+    let [x, y] = math::transform_pos(c.transform, position);
+    [
+        ((x + 1.0) / 2.0 * width).round(),
+        ((1.0 - y) / 2.0 * height).round(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::Viewport as GlViewport;
+    use piston_window::MouseCursorEvent;
+
+    // A 1280x720 window on a screen with two pixels per unit
+    fn context() -> Context {
+        Context::new_viewport(GlViewport {
+            rect: [0, 0, 2560, 1440],
+            draw_size: [2560, 1440],
+            window_size: [1280.0, 720.0],
+        })
+    }
+
+    #[test]
+    fn menus_scale_with_the_window_height() {
+        let mut viewport = Viewport::new([1280.0, 720.0], 1.0);
+        assert_eq!(
+            viewport.ui_context(context()).get_view_size(),
+            [1280.0, 720.0]
+        );
+
+        viewport.set_ui_scale(2.0);
+        let c = viewport.ui_context(context());
+        assert_eq!(c.get_view_size(), [640.0, 360.0]);
+        assert_eq!(pixel_scale(&c), 4.0);
+
+        let e = Event::Input(Input::Move(Motion::MouseCursor([200.0, 100.0])), None);
+        assert_eq!(
+            viewport.ui_event(&e).mouse_cursor_args(),
+            Some([100.0, 50.0])
+        );
+
+        // Twice as tall, twice as big
+        let e = Event::Input(
+            Input::Resize(piston_window::ResizeArgs {
+                window_size: [1280.0, 1440.0],
+                draw_size: [1280, 1440],
+            }),
+            None,
+        );
+        viewport.event(&e);
+        assert_eq!(viewport.ui_factor(), 4.0);
+    }
+
+    #[test]
+    fn the_screen_keeps_to_four_by_three() {
+        assert_eq!(screen_rect([1280.0, 720.0]), [160.0, 0.0, 960.0, 720.0]);
+        assert_eq!(screen_rect([640.0, 960.0]), [0.0, 240.0, 640.0, 480.0]);
+    }
+
+    #[test]
+    fn clips_are_in_pixels_and_nest() {
+        let c = clip(context(), [10.0, 20.0, 100.0, 50.0]);
+        assert_eq!(c.draw_state.scissor, Some([20, 40, 200, 100]));
+
+        let c = clip(c, [50.0, 0.0, 200.0, 200.0]);
+        assert_eq!(c.draw_state.scissor, Some([100, 40, 120, 100]));
+
+        // Moved and scaled along with what's drawn
+        let c = clip(
+            context().trans(100.0, 0.0).zoom(2.0),
+            [0.0, 0.0, 10.0, 10.0],
+        );
+        assert_eq!(c.draw_state.scissor, Some([200, 0, 40, 40]));
+    }
+
+    #[test]
+    fn areas_draw_like_a_smaller_view() {
+        let area = screen_rect([1280.0, 720.0]);
+        let c = area_context(context(), area);
+        assert_eq!(c.get_view_size(), [960.0, 720.0]);
+        assert_eq!(pixel_scale(&c), 2.0);
+        assert_eq!(math::transform_pos(c.transform, [0.0, 0.0]), [-0.75, 1.0]);
+    }
+}